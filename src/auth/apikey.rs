@@ -0,0 +1,211 @@
+//! API keys for scripts and integrations.
+//!
+//! Keys are shown to the user only once, at creation. Database keeps SHA-256 hash of the key
+//! and its first characters, so the keys can be told apart in the UI.
+
+use anyhow::{anyhow, Context, Result};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{hash_token, Role, User};
+
+/// Every key starts with this, makes leaked keys easy to recognize.
+const KEY_PREFIX: &str = "tbr_";
+
+/// Characters of the key stored as is, prefix included.
+const KEY_PREFIX_LEN: usize = 12;
+
+/// What an API key may be used for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ApiKeyScope {
+    /// Every /api route.
+    Full,
+    /// GET routes only.
+    ReadOnly,
+    /// Only the listed routes, eg. "POST /api/v1/videos".
+    Endpoints(Vec<String>),
+}
+
+impl ApiKeyScope {
+    /// Build scope from scope and endpoints columns.
+    pub fn from_db(scope: &str, endpoints: Option<&str>) -> Result<Self> {
+        match scope {
+            "FULL" => Ok(ApiKeyScope::Full),
+            "READ" => Ok(ApiKeyScope::ReadOnly),
+            "ENDPOINTS" => Ok(ApiKeyScope::Endpoints(parse_endpoints(
+                endpoints.unwrap_or_default(),
+            ))),
+            _ => Err(anyhow!("Unknown API key scope: {}", scope)),
+        }
+    }
+
+    /// Value for the scope column.
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            ApiKeyScope::Full => "FULL",
+            ApiKeyScope::ReadOnly => "READ",
+            ApiKeyScope::Endpoints(_) => "ENDPOINTS",
+        }
+    }
+
+    /// Can the key be used for the route. Path is the route template, eg. "/api/v1/scheduler/<id>".
+    pub fn allows(&self, method: &str, path: &str) -> bool {
+        match self {
+            ApiKeyScope::Full => true,
+            ApiKeyScope::ReadOnly => method.eq_ignore_ascii_case("GET"),
+            ApiKeyScope::Endpoints(endpoints) => {
+                let endpoint = format!("{} {}", method.to_uppercase(), path);
+                endpoints.contains(&endpoint)
+            }
+        }
+    }
+}
+
+/// Parse endpoint list, one "METHOD /path" per line or separated by commas.
+pub fn parse_endpoints(endpoints: &str) -> Vec<String> {
+    endpoints
+        .split(['\n', ','])
+        .filter_map(|endpoint| {
+            let (method, path) = endpoint.trim().split_once(char::is_whitespace)?;
+            Some(format!("{} {}", method.to_uppercase(), path.trim()))
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ApiKey {
+    pub id: i64,
+    pub name: String,
+    pub key_prefix: String,
+    pub scope: String,
+    pub endpoints: Option<String>,
+    pub created_at: String,
+    pub last_used: Option<String>,
+}
+
+/// Create new API key for the user. Returns the key, which is not stored anywhere.
+pub fn create_api_key(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    user: &User,
+    name: &str,
+    scope: &ApiKeyScope,
+) -> Result<String> {
+    if name.trim().is_empty() {
+        return Err(anyhow!("API key name can not be empty"));
+    }
+
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let key = format!("{}{}", KEY_PREFIX, hex::encode(bytes));
+
+    let endpoints = match scope {
+        ApiKeyScope::Endpoints(endpoints) => Some(endpoints.join("\n")),
+        _ => None,
+    };
+
+    conn.execute(
+        "INSERT INTO api_keys (user_id, name, key_prefix, key_hash, scope, endpoints)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![
+            user.id,
+            name.trim(),
+            &key[..KEY_PREFIX_LEN],
+            hash_token(&key),
+            scope.as_db(),
+            endpoints
+        ],
+    )
+    .context("Failed to create API key")?;
+
+    Ok(key)
+}
+
+/// List API keys of the user.
+pub fn get_api_keys(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    user: &User,
+) -> Result<Vec<ApiKey>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, name, key_prefix, scope, endpoints, created_at, last_used
+            FROM api_keys
+            WHERE user_id = ?1
+            ORDER BY id",
+    )?;
+
+    let keys = stmt
+        .query_map(params![user.id], |row| {
+            Ok(ApiKey {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                key_prefix: row.get(2)?,
+                scope: row.get(3)?,
+                endpoints: row.get(4)?,
+                created_at: row.get(5)?,
+                last_used: row.get(6)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(keys)
+}
+
+/// Revoke API key of the user. Returns false if there was no such key.
+pub fn delete_api_key(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    user: &User,
+    id: i64,
+) -> Result<bool> {
+    let deleted = conn.execute(
+        "DELETE FROM api_keys WHERE id = ?1 AND user_id = ?2",
+        params![id, user.id],
+    )?;
+    Ok(deleted > 0)
+}
+
+/// Find the user and scope of an API key, and mark the key used.
+pub fn get_api_key_user(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    key: &str,
+) -> Result<Option<(User, ApiKeyScope)>> {
+    let key_hash = hash_token(key);
+
+    let row = conn
+        .query_row(
+            "SELECT u.id, ul.username, u.role, k.scope, k.endpoints
+                FROM api_keys k
+                JOIN users u ON u.id = k.user_id
+                JOIN users_local ul ON ul.user_id = u.id
+                WHERE k.key_hash = ?1 AND u.enabled = 1 AND ul.enabled = 1",
+            params![key_hash],
+            |row| {
+                let user = User {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    role: Role::from_db(&row.get::<_, String>(2)?),
+                };
+                Ok((
+                    user,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    let Some((user, scope, endpoints)) = row else {
+        return Ok(None);
+    };
+
+    conn.execute(
+        "UPDATE api_keys SET last_used = datetime('now') WHERE key_hash = ?1",
+        params![key_hash],
+    )?;
+
+    let scope = ApiKeyScope::from_db(&scope, endpoints.as_deref())?;
+    Ok(Some((user, scope)))
+}
@@ -0,0 +1,241 @@
+//! Local user authentication: argon2 password hashes and login sessions.
+//!
+//! Session tokens are random 32-byte values handed to the browser as a cookie.
+//! Database keeps only SHA-256 hashes of them. API keys are handled in apikey.rs,
+//! reverse proxy header login in proxy.rs and OpenID Connect in oidc.rs.
+
+use anyhow::{anyhow, Context, Result};
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rusqlite::{params, OptionalExtension};
+use sha2::{Digest, Sha256};
+
+mod apikey;
+pub use apikey::*;
+
+mod users;
+pub use users::*;
+
+mod proxy;
+pub use proxy::*;
+
+mod oidc;
+pub use oidc::*;
+
+/// Username of the built-in admin account.
+pub const ADMIN_USERNAME: &str = "admin";
+
+/// How long a login stays valid.
+pub const SESSION_DAYS: i64 = 30;
+
+/// What the user is allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Role {
+    /// Everything, including approving requests of others.
+    Admin,
+    /// Browse channels and request videos. Requests wait for admin approval.
+    Requester,
+}
+
+impl Role {
+    /// Role from the users.role column. Anything unknown gets the least rights.
+    pub fn from_db(role: &str) -> Self {
+        match role {
+            "ADMIN" => Role::Admin,
+            _ => Role::Requester,
+        }
+    }
+
+    pub fn as_db(&self) -> &'static str {
+        match self {
+            Role::Admin => "ADMIN",
+            Role::Requester => "REQUESTER",
+        }
+    }
+}
+
+/// Logged in user.
+#[derive(Debug, Clone)]
+pub struct User {
+    pub id: Vec<u8>,
+    pub username: String,
+    pub role: Role,
+}
+
+/// Hash password into PHC string format with random salt.
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow!("Failed to hash password: {}", e))
+}
+
+/// Check password against stored PHC string. Anything unparseable never matches.
+pub fn verify_password(password: &str, password_hash: &str) -> bool {
+    match PasswordHash::new(password_hash) {
+        Ok(hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+/// Has the admin password been set. Initial install has only a placeholder.
+pub fn admin_password_is_set(conn: &PooledConnection<SqliteConnectionManager>) -> Result<bool> {
+    let password_hash: Option<String> = conn
+        .query_row(
+            "SELECT password_hash FROM users_local WHERE username = ?1",
+            params![ADMIN_USERNAME],
+            |row| row.get(0),
+        )
+        .optional()?;
+
+    Ok(password_hash.is_some_and(|hash| PasswordHash::new(&hash).is_ok()))
+}
+
+/// Set password for local user. Existing sessions of the user are logged out.
+pub fn set_password(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    username: &str,
+    password: &str,
+) -> Result<()> {
+    if password.is_empty() {
+        return Err(anyhow!("Password can not be empty"));
+    }
+    let password_hash = hash_password(password)?;
+
+    let updated = conn.execute(
+        "UPDATE users_local SET password_hash = ?1, updated_at = CURRENT_TIMESTAMP
+            WHERE username = ?2",
+        params![password_hash, username],
+    )?;
+    if updated == 0 {
+        return Err(anyhow!("No such user: {}", username));
+    }
+
+    conn.execute(
+        "DELETE FROM sessions
+            WHERE user_id = (SELECT user_id FROM users_local WHERE username = ?1)",
+        params![username],
+    )
+    .context("Failed to remove sessions")?;
+
+    Ok(())
+}
+
+/// Check username and password. Returns the user if they match and the user is enabled.
+pub fn authenticate(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    username: &str,
+    password: &str,
+) -> Result<Option<User>> {
+    let row: Option<(Vec<u8>, String, String)> = conn
+        .query_row(
+            "SELECT ul.user_id, ul.password_hash, u.role
+                FROM users_local ul
+                JOIN users u ON u.id = ul.user_id
+                WHERE ul.username = ?1 AND ul.enabled = 1 AND u.enabled = 1",
+            params![username],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+
+    Ok(row
+        .filter(|(_, password_hash, _)| verify_password(password, password_hash))
+        .map(|(id, _, role)| User {
+            id,
+            username: username.to_string(),
+            role: Role::from_db(&role),
+        }))
+}
+
+/// Start new session for the user. Returns the token for the session cookie.
+pub fn create_session(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    user: &User,
+) -> Result<String> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    // Expired sessions are of no use to anyone
+    conn.execute(
+        "DELETE FROM sessions WHERE expires_at <= datetime('now')",
+        [],
+    )?;
+
+    conn.execute(
+        "INSERT INTO sessions (token_hash, user_id, expires_at)
+            VALUES (?1, ?2, datetime('now', ?3))",
+        params![
+            hash_token(&token),
+            user.id,
+            format!("+{} days", SESSION_DAYS)
+        ],
+    )
+    .context("Failed to create session")?;
+
+    Ok(token)
+}
+
+/// Find the user of a valid session.
+pub fn get_session_user(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    token: &str,
+) -> Result<Option<User>> {
+    let user = conn
+        .query_row(
+            "SELECT u.id, ul.username, u.role
+                FROM sessions s
+                JOIN users u ON u.id = s.user_id
+                JOIN users_local ul ON ul.user_id = u.id
+                WHERE s.token_hash = ?1 AND s.expires_at > datetime('now')
+                    AND u.enabled = 1 AND ul.enabled = 1",
+            params![hash_token(token)],
+            |row| {
+                Ok(User {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    role: Role::from_db(&row.get::<_, String>(2)?),
+                })
+            },
+        )
+        .optional()?;
+
+    Ok(user)
+}
+
+/// End the session (logout).
+pub fn delete_session(conn: &PooledConnection<SqliteConnectionManager>, token: &str) -> Result<()> {
+    conn.execute(
+        "DELETE FROM sessions WHERE token_hash = ?1",
+        params![hash_token(token)],
+    )?;
+    Ok(())
+}
+
+/// Value from app_configuration. Missing key reads as empty.
+fn get_config(conn: &PooledConnection<SqliteConnectionManager>, key: &str) -> Result<String> {
+    let value: Option<String> = conn
+        .query_row(
+            "SELECT value FROM app_configuration WHERE key = ?1",
+            params![key],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(value.unwrap_or_default())
+}
+
+/// Role given to users created by external login.
+fn default_role(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Role> {
+    Ok(Role::from_db(&get_config(conn, "auth_default_role")?))
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
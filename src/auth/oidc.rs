@@ -0,0 +1,295 @@
+//! OpenID Connect login, authorization code flow with PKCE.
+//!
+//! Enabled by setting oidc_issuer, oidc_client_id, oidc_client_secret and oidc_redirect_url.
+//! The redirect URL is this server's /login/oidc/callback as seen by the browser.
+//! ID token comes straight from the token endpoint over the back channel, so its claims are
+//! checked (issuer, audience, expiry, nonce) instead of its signature, as OIDC Core 3.1.3.7
+//! allows. That holds only over TLS, so the issuer and its endpoints must be https://.
+//! The state is also kept in a cookie of the browser that started the login, see
+//! `webui::pages::login`. Users are matched by issuer and subject, and created on first login.
+//!
+//! Calls to the provider block, run them off the async workers.
+
+use anyhow::{anyhow, Context, Result};
+use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+use base64::Engine;
+use chrono::Utc;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rusqlite::{params, OptionalExtension};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use url::Url;
+
+use super::{create_external_user, default_role, get_config, Role, User};
+
+/// How long the user has to finish login at the provider.
+pub const OIDC_LOGIN_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// Timeout for calls to the provider.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Logins started but not yet finished, by state parameter.
+static PENDING: LazyLock<Mutex<HashMap<String, PendingLogin>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct PendingLogin {
+    nonce: String,
+    code_verifier: String,
+    started: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_url: String,
+}
+
+/// OIDC settings, None if OIDC login is not configured.
+pub fn oidc_config(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Option<OidcConfig>> {
+    let config = OidcConfig {
+        issuer: get_config(conn, "oidc_issuer")?.trim().to_string(),
+        client_id: get_config(conn, "oidc_client_id")?.trim().to_string(),
+        client_secret: get_config(conn, "oidc_client_secret")?,
+        redirect_url: get_config(conn, "oidc_redirect_url")?.trim().to_string(),
+    };
+
+    if config.issuer.is_empty() || config.client_id.is_empty() || config.redirect_url.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(config))
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+#[derive(Deserialize)]
+struct Claims {
+    iss: String,
+    sub: String,
+    aud: Audience,
+    exp: i64,
+    nonce: Option<String>,
+    preferred_username: Option<String>,
+    email: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Audience {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl Audience {
+    fn contains(&self, client_id: &str) -> bool {
+        match self {
+            Audience::One(aud) => aud == client_id,
+            Audience::Many(auds) => auds.iter().any(|aud| aud == client_id),
+        }
+    }
+}
+
+/// Start login. Returns the provider URL to send the browser to, and the state. The caller
+/// ties the state to the browser, so a callback URL from someone else's login is refused.
+pub fn authorization_url(config: &OidcConfig) -> Result<(String, String)> {
+    let discovery = discover(&config.issuer)?;
+
+    let state = random_string();
+    let nonce = random_string();
+    let code_verifier = random_string();
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()));
+
+    let url = Url::parse_with_params(
+        &discovery.authorization_endpoint,
+        &[
+            ("response_type", "code"),
+            ("client_id", config.client_id.as_str()),
+            ("redirect_uri", config.redirect_url.as_str()),
+            ("scope", "openid profile email"),
+            ("state", state.as_str()),
+            ("nonce", nonce.as_str()),
+            ("code_challenge", code_challenge.as_str()),
+            ("code_challenge_method", "S256"),
+        ],
+    )
+    .context("Invalid authorization endpoint")?;
+
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    pending.retain(|_, login| login.started.elapsed() < OIDC_LOGIN_TIMEOUT);
+    pending.insert(
+        state.clone(),
+        PendingLogin {
+            nonce,
+            code_verifier,
+            started: Instant::now(),
+        },
+    );
+
+    Ok((url.to_string(), state))
+}
+
+/// Finish login with the code the provider sent back. Returns the logged in user.
+pub fn finish_login(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    config: &OidcConfig,
+    code: &str,
+    state: &str,
+) -> Result<User> {
+    let login = PENDING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(state)
+        .filter(|login| login.started.elapsed() < OIDC_LOGIN_TIMEOUT)
+        .ok_or_else(|| anyhow!("Unknown or expired login state"))?;
+
+    let discovery = discover(&config.issuer)?;
+
+    let credentials = STANDARD.encode(format!(
+        "{}:{}",
+        form_encode(&config.client_id),
+        form_encode(&config.client_secret)
+    ));
+    let response: TokenResponse = agent()
+        .post(&discovery.token_endpoint)
+        .set("Authorization", &format!("Basic {}", credentials))
+        .send_form(&[
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", &config.redirect_url),
+            ("code_verifier", &login.code_verifier),
+        ])
+        .context("Token request failed")?
+        .into_json()
+        .context("Invalid token response")?;
+
+    let claims = decode_claims(&response.id_token)?;
+    if claims.iss != discovery.issuer {
+        return Err(anyhow!("ID token issuer mismatch: {}", claims.iss));
+    }
+    if !claims.aud.contains(&config.client_id) {
+        return Err(anyhow!("ID token is not for this client"));
+    }
+    if claims.exp <= Utc::now().timestamp() {
+        return Err(anyhow!("ID token has expired"));
+    }
+    if claims.nonce.as_deref() != Some(login.nonce.as_str()) {
+        return Err(anyhow!("ID token nonce mismatch"));
+    }
+
+    get_or_create_user(conn, &claims)
+}
+
+fn get_or_create_user(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    claims: &Claims,
+) -> Result<User> {
+    let known: Option<(Vec<u8>, String, String, bool)> = conn
+        .query_row(
+            "SELECT u.id, ul.username, u.role, u.enabled AND ul.enabled
+                FROM users_oidc o
+                JOIN users u ON u.id = o.user_id
+                JOIN users_local ul ON ul.user_id = u.id
+                WHERE o.issuer = ?1 AND o.subject = ?2",
+            params![claims.iss, claims.sub],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        )
+        .optional()?;
+
+    if let Some((id, username, role, enabled)) = known {
+        if !enabled {
+            return Err(anyhow!("User {} is disabled", username));
+        }
+        return Ok(User {
+            id,
+            username,
+            role: Role::from_db(&role),
+        });
+    }
+
+    let username = claims
+        .preferred_username
+        .as_deref()
+        .or(claims.email.as_deref())
+        .unwrap_or(&claims.sub);
+    let user = create_external_user(conn, username, default_role(conn)?)?;
+
+    conn.execute(
+        "INSERT INTO users_oidc (issuer, subject, user_id) VALUES (?1, ?2, ?3)",
+        params![claims.iss, claims.sub, user.id],
+    )?;
+
+    Ok(user)
+}
+
+fn discover(issuer: &str) -> Result<Discovery> {
+    if !issuer.starts_with("https://") {
+        return Err(anyhow!("OIDC issuer must be https:// URL: {}", issuer));
+    }
+    let url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.trim_end_matches('/')
+    );
+    let discovery: Discovery = agent()
+        .get(&url)
+        .call()
+        .context("OIDC discovery failed")?
+        .into_json()
+        .context("Invalid OIDC discovery document")?;
+
+    if discovery.issuer.trim_end_matches('/') != issuer.trim_end_matches('/') {
+        return Err(anyhow!(
+            "OIDC discovery issuer mismatch: {}",
+            discovery.issuer
+        ));
+    }
+    for endpoint in [&discovery.authorization_endpoint, &discovery.token_endpoint] {
+        if !endpoint.starts_with("https://") {
+            return Err(anyhow!("OIDC endpoint must be https:// URL: {}", endpoint));
+        }
+    }
+    Ok(discovery)
+}
+
+/// Payload of a JWT, without checking the signature.
+fn decode_claims(id_token: &str) -> Result<Claims> {
+    let payload = id_token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| anyhow!("Malformed ID token"))?;
+    let payload = URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .context("Malformed ID token")?;
+    serde_json::from_slice(&payload).context("Malformed ID token claims")
+}
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new().timeout(HTTP_TIMEOUT).build()
+}
+
+fn random_string() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// application/x-www-form-urlencoded, as client_secret_basic wants.
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
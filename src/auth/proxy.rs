@@ -0,0 +1,94 @@
+//! Login by trusted reverse proxy header, eg. Remote-User set by Authelia or Authentik.
+//!
+//! Enabled by setting auth_proxy_header. The header is believed only when the request
+//! comes directly from one of auth_proxy_trusted addresses (IPs or CIDR ranges, separated
+//! by commas). Users are matched by username and created when needed.
+
+use anyhow::Result;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, OptionalExtension};
+use std::net::IpAddr;
+
+use super::{create_external_user, default_role, get_config, get_user_by_username, User};
+
+/// Name of the header to read the username from, if the request comes from trusted proxy.
+pub fn trusted_proxy_header(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    remote: IpAddr,
+) -> Result<Option<String>> {
+    let header = get_config(conn, "auth_proxy_header")?;
+    if header.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let trusted = get_config(conn, "auth_proxy_trusted")?;
+    if !ip_in_list(remote, &trusted) {
+        return Ok(None);
+    }
+
+    Ok(Some(header.trim().to_string()))
+}
+
+/// Log in user named by trusted proxy. Returns None if the user exists but is disabled.
+pub fn proxy_login(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    username: &str,
+) -> Result<Option<User>> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Ok(None);
+    }
+
+    if let Some(user) = get_user_by_username(conn, username)? {
+        return Ok(Some(user));
+    }
+
+    // Existing but disabled user must stay out
+    let exists: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM users_local WHERE username = ?1",
+            params![username],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if exists.is_some() {
+        return Ok(None);
+    }
+
+    let user = create_external_user(conn, username, default_role(conn)?)?;
+    Ok(Some(user))
+}
+
+/// Is the address in comma separated list of IPs and CIDR ranges.
+pub fn ip_in_list(ip: IpAddr, list: &str) -> bool {
+    let ip = ip.to_canonical();
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| ip_in_range(ip, entry))
+}
+
+fn ip_in_range(ip: IpAddr, range: &str) -> bool {
+    let (net, prefix) = match range.split_once('/') {
+        Some((net, prefix)) => (net, prefix.parse::<u32>().ok()),
+        None => (range, None),
+    };
+    let Ok(net) = net.parse::<IpAddr>() else {
+        return false;
+    };
+
+    match (ip, net.to_canonical()) {
+        (IpAddr::V4(ip), IpAddr::V4(net)) => {
+            let prefix = prefix.unwrap_or(32).min(32);
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(net) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(net)) => {
+            let prefix = prefix.unwrap_or(128).min(128);
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(net) & mask
+        }
+        _ => false,
+    }
+}
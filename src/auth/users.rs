@@ -0,0 +1,291 @@
+//! Managing local user accounts.
+//!
+//! Protected users (the initial admin) can't be removed, disabled or demoted.
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use thiserror::Error;
+use utoipa::ToSchema;
+
+use super::{hash_password, Role, User};
+
+/// Password hash of users logging in through reverse proxy or OIDC. Never matches a password.
+pub const EXTERNAL_PASSWORD_HASH: &str = "!external";
+
+#[derive(Debug, Error)]
+pub enum UserError {
+    #[error("No such user")]
+    NotFound,
+    #[error("User is protected")]
+    Protected,
+    #[error("Username is already taken")]
+    UsernameTaken,
+    #[error("Invalid user: {0}")]
+    Invalid(&'static str),
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+/// User account as shown to admins. ID is hex encoded.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct UserAccount {
+    pub id: String,
+    pub username: String,
+    pub role: String,
+    pub enabled: bool,
+    pub protected: bool,
+    pub created_at: String,
+}
+
+/// List all local users.
+pub fn get_user_accounts(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<UserAccount>, UserError> {
+    let mut stmt = conn.prepare(
+        "SELECT u.id, ul.username, u.role, u.enabled AND ul.enabled, u.protected, u.created_at
+            FROM users u
+            JOIN users_local ul ON ul.user_id = u.id
+            ORDER BY ul.username",
+    )?;
+
+    let users = stmt
+        .query_map([], |row| {
+            Ok(UserAccount {
+                id: hex::encode(row.get::<_, Vec<u8>>(0)?),
+                username: row.get(1)?,
+                role: row.get(2)?,
+                enabled: row.get(3)?,
+                protected: row.get(4)?,
+                created_at: row.get(5)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(users)
+}
+
+/// Create local user. Returns ID of the new user.
+pub fn create_local_user(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    username: &str,
+    password: &str,
+    role: Role,
+) -> Result<Vec<u8>, UserError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(UserError::Invalid("username can not be empty"));
+    }
+    if password.is_empty() {
+        return Err(UserError::Invalid("password can not be empty"));
+    }
+
+    let taken: Option<i64> = conn
+        .query_row(
+            "SELECT 1 FROM users_local WHERE username = ?1",
+            params![username],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if taken.is_some() {
+        return Err(UserError::UsernameTaken);
+    }
+
+    let id = new_user_id();
+    let password_hash = hash_password(password)?;
+
+    conn.execute(
+        "INSERT INTO users (id, role) VALUES (?1, ?2)",
+        params![id, role.as_db()],
+    )?;
+    conn.execute(
+        "INSERT INTO users_local (username, password_hash, user_id) VALUES (?1, ?2, ?3)",
+        params![username, password_hash, id],
+    )?;
+
+    Ok(id.to_vec())
+}
+
+/// Change role and enabled state of user, and optionally the password.
+/// Disabled users and users with new password are logged out.
+pub fn update_user_account(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: &[u8],
+    role: Role,
+    enabled: bool,
+    password: Option<&str>,
+) -> Result<(), UserError> {
+    let protected = is_protected(conn, id)?;
+    if protected && (role != Role::Admin || !enabled) {
+        return Err(UserError::Protected);
+    }
+    if password.is_some_and(|password| password.is_empty()) {
+        return Err(UserError::Invalid("password can not be empty"));
+    }
+
+    conn.execute(
+        "UPDATE users SET role = ?1, enabled = ?2, updated_at = CURRENT_TIMESTAMP WHERE id = ?3",
+        params![role.as_db(), enabled, id],
+    )?;
+    conn.execute(
+        "UPDATE users_local SET enabled = ?1, updated_at = CURRENT_TIMESTAMP WHERE user_id = ?2",
+        params![enabled, id],
+    )?;
+    if let Some(password) = password {
+        conn.execute(
+            "UPDATE users_local SET password_hash = ?1 WHERE user_id = ?2",
+            params![hash_password(password)?, id],
+        )?;
+    }
+    if !enabled || password.is_some() {
+        conn.execute("DELETE FROM sessions WHERE user_id = ?1", params![id])?;
+    }
+
+    Ok(())
+}
+
+/// Remove user with everything that belongs to them.
+pub fn delete_user_account(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: &[u8],
+) -> Result<(), UserError> {
+    if is_protected(conn, id)? {
+        return Err(UserError::Protected);
+    }
+
+    // Foreign keys are not enforced, so clean up by hand
+    for sql in [
+        "DELETE FROM sessions WHERE user_id = ?1",
+        "DELETE FROM api_keys WHERE user_id = ?1",
+        "DELETE FROM video_requests WHERE user_id = ?1",
+        "DELETE FROM users_oidc WHERE user_id = ?1",
+        "DELETE FROM users_local WHERE user_id = ?1",
+        "DELETE FROM users WHERE id = ?1",
+    ] {
+        conn.execute(sql, params![id])?;
+    }
+
+    Ok(())
+}
+
+/// Find enabled user by username.
+pub fn get_user_by_username(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    username: &str,
+) -> Result<Option<User>, UserError> {
+    let user = conn
+        .query_row(
+            "SELECT u.id, ul.username, u.role
+                FROM users_local ul
+                JOIN users u ON u.id = ul.user_id
+                WHERE ul.username = ?1 AND ul.enabled = 1 AND u.enabled = 1",
+            params![username],
+            |row| {
+                Ok(User {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    role: Role::from_db(&row.get::<_, String>(2)?),
+                })
+            },
+        )
+        .optional()?;
+    Ok(user)
+}
+
+/// Enabled admin to act as when there's no logged in user, like on the command line. The
+/// built-in admin if it's still an enabled admin, otherwise the oldest one.
+pub fn get_any_admin(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Option<User>, UserError> {
+    let user = conn
+        .query_row(
+            "SELECT u.id, ul.username, u.role
+                FROM users u
+                JOIN users_local ul ON ul.user_id = u.id
+                WHERE u.role = 'ADMIN' AND u.enabled = 1 AND ul.enabled = 1
+                ORDER BY u.protected DESC, u.created_at, u.rowid
+                LIMIT 1",
+            [],
+            |row| {
+                Ok(User {
+                    id: row.get(0)?,
+                    username: row.get(1)?,
+                    role: Role::from_db(&row.get::<_, String>(2)?),
+                })
+            },
+        )
+        .optional()?;
+    Ok(user)
+}
+
+/// Create user for external login. If the username is taken, a number is appended to it.
+pub fn create_external_user(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    username: &str,
+    role: Role,
+) -> Result<User, UserError> {
+    let username = username.trim();
+    if username.is_empty() {
+        return Err(UserError::Invalid("username can not be empty"));
+    }
+
+    let mut candidate = username.to_string();
+    let mut suffix = 1;
+    loop {
+        let taken: Option<i64> = conn
+            .query_row(
+                "SELECT 1 FROM users_local WHERE username = ?1",
+                params![candidate],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if taken.is_none() {
+            break;
+        }
+        suffix += 1;
+        candidate = format!("{}-{}", username, suffix);
+    }
+
+    let id = new_user_id();
+    conn.execute(
+        "INSERT INTO users (id, role) VALUES (?1, ?2)",
+        params![id, role.as_db()],
+    )?;
+    conn.execute(
+        "INSERT INTO users_local (username, password_hash, user_id) VALUES (?1, ?2, ?3)",
+        params![candidate, EXTERNAL_PASSWORD_HASH, id],
+    )?;
+
+    Ok(User {
+        id: id.to_vec(),
+        username: candidate,
+        role,
+    })
+}
+
+fn is_protected(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: &[u8],
+) -> Result<bool, UserError> {
+    conn.query_row(
+        "SELECT protected FROM users WHERE id = ?1",
+        params![id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or(UserError::NotFound)
+}
+
+/// Random UUID-v4 as 16 bytes.
+fn new_user_id() -> [u8; 16] {
+    let mut id = [0u8; 16];
+    OsRng.fill_bytes(&mut id);
+    id[6] = (id[6] & 0x0f) | 0x40; // Version 4
+    id[8] = (id[8] & 0x3f) | 0x80; // Variant 1
+    id
+}
@@ -0,0 +1,399 @@
+//! Administration subcommands. These work directly against the database, so they also
+//! work while the server is running, or when the web UI is broken. Restore and import are
+//! the exception, they replace the data under the server and refuse to run alongside it.
+
+use anyhow::{anyhow, Context, Result};
+use clap::Subcommand;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::auth;
+use crate::config::{self, ConfigError};
+use crate::database::{self, db_expected_version, db_get_version};
+use crate::requests;
+use crate::subscriptions;
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Manage tracked channels
+    Channel {
+        #[command(subcommand)]
+        command: ChannelCommand,
+    },
+    /// Request videos
+    Video {
+        #[command(subcommand)]
+        command: VideoCommand,
+    },
+    /// Manage one-off tasks
+    Task {
+        #[command(subcommand)]
+        command: TaskCommand,
+    },
+    /// Read and change configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Manage web UI users
+    User {
+        #[command(subcommand)]
+        command: UserCommand,
+    },
+    /// Database maintenance
+    Db {
+        #[command(subcommand)]
+        command: DbCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ChannelCommand {
+    /// Start tracking channel
+    Add { url: String },
+    /// List tracked channels
+    List,
+    /// Stop tracking channel. Downloaded files are kept.
+    Remove { id: i64 },
+    /// Track channels from OPML or Google Takeout subscriptions.csv. Shows a preview
+    /// unless --yes is given.
+    Import {
+        path: PathBuf,
+        /// Queue the channels that are not followed yet
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Print tracked channels as OPML
+    Export,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum VideoCommand {
+    /// Download video
+    Request { url: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TaskCommand {
+    /// List tasks
+    List {
+        /// Only tasks in the state: WAIT, WIP, ERR, DONE or FAIL
+        #[arg(long)]
+        state: Option<String>,
+    },
+    /// Queue failed task again
+    Retry { id: i64 },
+    /// Cancel task that has not started
+    Cancel { id: i64 },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Show configuration values. Secrets are not shown.
+    Get { key: Option<String> },
+    /// Validate and save configuration value
+    Set { key: String, value: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UserCommand {
+    /// Set password for local user. Asks for the password.
+    Passwd {
+        #[arg(default_value = auth::ADMIN_USERNAME)]
+        username: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DbCommand {
+    /// Upgrade database schema to this version of Tubarr
+    Migrate,
+    /// Show database schema version
+    Version,
+    /// Check database integrity and compare the schema to the expected one
+    Check,
+    /// Write consistent copy of the database. Safe while the server is running.
+    Backup { path: PathBuf },
+    /// Replace the database with a backup. Refused while the server is running.
+    Restore { path: PathBuf },
+    /// Write channels, videos, tasks and configuration to JSON file
+    Export { path: PathBuf },
+    /// Replace channels, videos, tasks and configuration from JSON export. Refused while the
+    /// server is running.
+    Import { path: PathBuf },
+}
+
+/// Run the command against the database file.
+pub fn run(command: Command, db_path: &PathBuf) -> Result<()> {
+    // Restore can also create the database
+    if let Command::Db {
+        command: DbCommand::Restore { path },
+    } = &command
+    {
+        let _lock = database::ServerLock::acquire(db_path)?;
+        let version = database::restore_from(db_path, path)?;
+        println!("Restored {} (schema version {})", path.display(), version);
+        if version < db_expected_version() {
+            println!("The database is upgraded on the next start, or with 'tubarr db migrate'.");
+        }
+        return Ok(());
+    }
+
+    if !db_path.exists() {
+        return Err(anyhow!("Database {} does not exist", db_path.display()));
+    }
+
+    let dbp = database::open(db_path);
+    if let Command::Db { command } = command {
+        return run_db(command, dbp, db_path);
+    }
+
+    let mut conn = dbp.get()?;
+    let version = db_get_version(&conn)?;
+    if version != db_expected_version() {
+        return Err(anyhow!(
+            "Database schema version is {}, expected {}. Run 'tubarr db migrate' first.",
+            version,
+            db_expected_version()
+        ));
+    }
+
+    match command {
+        Command::Channel { command } => run_channel(command, &conn),
+        Command::Video { command } => run_video(command, &conn),
+        Command::Task { command } => run_task(command, &conn),
+        Command::Config { command } => run_config(command, &mut conn),
+        Command::User { command } => run_user(command, &conn),
+        Command::Db { .. } => unreachable!(),
+    }
+}
+
+fn run_channel(
+    command: ChannelCommand,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<()> {
+    match command {
+        ChannelCommand::Add { url } => {
+            let task_id = database::enqueue_url_task(conn, "CHANNEL-ADD", &url, &admin_id(conn)?)?;
+            println!("Queued task {}", task_id);
+        }
+        ChannelCommand::List => {
+            for channel in database::get_channels(conn)? {
+                println!(
+                    "{}\t{}\t{}\t{}",
+                    channel.id, channel.domain, channel.channel_name, channel.url
+                );
+            }
+        }
+        ChannelCommand::Remove { id } => match database::delete_channel(conn, id)? {
+            Some(videos) => println!("Removed channel {} and {} videos", id, videos),
+            None => return Err(anyhow!("No such channel: {}", id)),
+        },
+        ChannelCommand::Import { path, yes } => {
+            let contents = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let preview = subscriptions::preview(conn, &contents)?;
+            let new: Vec<String> = preview
+                .subscriptions
+                .iter()
+                .filter(|sub| !sub.followed)
+                .map(|sub| sub.channel_id.clone())
+                .collect();
+
+            if !yes {
+                for sub in &preview.subscriptions {
+                    let state = if sub.followed { "followed" } else { "new" };
+                    println!("{}\t{}\t{}", state, sub.channel_id, sub.title);
+                }
+                println!(
+                    "{} new channels, {} already followed, {} entries skipped",
+                    new.len(),
+                    preview.subscriptions.len() - new.len(),
+                    preview.skipped
+                );
+                if !new.is_empty() {
+                    println!("Run again with --yes to add them.");
+                }
+                return Ok(());
+            }
+
+            let queued = subscriptions::import(conn, &new, &admin_id(conn)?)?;
+            println!("Queued {} channels", queued);
+        }
+        ChannelCommand::Export => print!("{}", subscriptions::export_opml(conn)?),
+    }
+    Ok(())
+}
+
+fn run_video(
+    command: VideoCommand,
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<()> {
+    match command {
+        VideoCommand::Request { url } => {
+            if !requests::is_valid_url(&url) {
+                return Err(anyhow!("Not a http:// or https:// URL: {}", url));
+            }
+            let task_id = requests::enqueue_download(conn, &url, &admin_id(conn)?)?;
+            println!("Queued task {}", task_id);
+        }
+    }
+    Ok(())
+}
+
+fn run_task(command: TaskCommand, conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+    match command {
+        TaskCommand::List { state } => {
+            let state = state.map(|state| state.to_uppercase());
+            for task in database::get_tasks(conn, state.as_deref())? {
+                println!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                    task.id,
+                    task.task_type,
+                    task.task_state,
+                    task.retry_count,
+                    task.updated_at,
+                    task.task_data,
+                    task.last_error.unwrap_or_default()
+                );
+            }
+        }
+        TaskCommand::Retry { id } => match database::retry_task(conn, id)? {
+            true => println!("Task {} queued again", id),
+            false => return Err(anyhow!("No failed task {}", id)),
+        },
+        TaskCommand::Cancel { id } => match database::cancel_task(conn, id)? {
+            true => println!("Task {} cancelled", id),
+            false => return Err(anyhow!("No waiting task {}", id)),
+        },
+    }
+    Ok(())
+}
+
+fn run_config(
+    command: ConfigCommand,
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+) -> Result<()> {
+    match command {
+        ConfigCommand::Get { key } => {
+            let values: BTreeMap<String, String> = config::get_values(conn)?.into_iter().collect();
+            if let Some(key) = &key {
+                if !values.contains_key(key) {
+                    return Err(anyhow!("Unknown configuration key: {}", key));
+                }
+            }
+            for (k, value) in values {
+                if key.as_ref().is_some_and(|key| *key != k) {
+                    continue;
+                }
+                match config::SECRET_KEYS.contains(&k.as_str()) && !value.is_empty() {
+                    true => println!("{} = (set)", k),
+                    false => println!("{} = {}", k, value),
+                }
+            }
+        }
+        ConfigCommand::Set { key, value } => {
+            let values = HashMap::from([(key, value)]);
+            match config::set_values(conn, &values) {
+                Ok(_) => {}
+                Err(ConfigError::Invalid(errors)) => {
+                    let (key, message) = errors.into_iter().next().unwrap_or_default();
+                    return Err(anyhow!("Invalid value for {}: {}", key, message));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_user(command: UserCommand, conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+    match command {
+        UserCommand::Passwd { username } => {
+            println!("Set password for the web UI user '{}'.", username);
+            let password = super::prompt_new_password()?;
+            auth::set_password(conn, &username, &password)?;
+            println!("Password changed");
+        }
+    }
+    Ok(())
+}
+
+fn run_db(command: DbCommand, dbp: crate::DBPool, db_path: &Path) -> Result<()> {
+    match command {
+        DbCommand::Migrate => {
+            database::db_upgrade(dbp.clone())?;
+            let conn = dbp.get()?;
+            println!("Database schema version is {}", db_get_version(&conn)?);
+        }
+        DbCommand::Version => {
+            let conn = dbp.get()?;
+            println!("Database schema version is {}", db_get_version(&conn)?);
+            println!("This version of Tubarr expects {}", db_expected_version());
+        }
+        DbCommand::Check => {
+            let conn = dbp.get()?;
+            let problems = database::check(&conn)?;
+            if !problems.is_empty() {
+                for problem in &problems {
+                    println!("{}", problem);
+                }
+                return Err(anyhow!("{} problems found", problems.len()));
+            }
+            println!("Database is OK");
+        }
+        DbCommand::Backup { path } => {
+            let conn = dbp.get()?;
+            database::backup_to(&conn, &path)?;
+            println!("Database backed up to {}", path.display());
+        }
+        DbCommand::Restore { .. } => unreachable!(),
+        DbCommand::Export { path } => {
+            let conn = dbp.get()?;
+            let export = database::export(&conn)?;
+            let file = fs::File::create(&path)
+                .with_context(|| format!("Failed to create {}", path.display()))?;
+            serde_json::to_writer_pretty(file, &export)?;
+            println!(
+                "Exported {} channels, {} videos and {} tasks to {}",
+                export.channels.len(),
+                export.videos.len(),
+                export.tasks.len(),
+                path.display()
+            );
+        }
+        DbCommand::Import { path } => {
+            let file = fs::File::open(&path)
+                .with_context(|| format!("Failed to open {}", path.display()))?;
+            let export: database::Export = serde_json::from_reader(io::BufReader::new(file))
+                .with_context(|| format!("Invalid export file {}", path.display()))?;
+
+            let _lock = database::ServerLock::acquire(db_path)?;
+            let mut conn = dbp.get()?;
+            if db_get_version(&conn)? != db_expected_version() {
+                return Err(anyhow!("Run 'tubarr db migrate' before importing."));
+            }
+            for (key, value) in &export.configuration {
+                if let Err(e) = config::validate(key, value) {
+                    eprintln!("Warning: {} = {:?} is not valid here: {}", key, value, e);
+                }
+            }
+            let counts = database::import(&mut conn, &export)?;
+            println!(
+                "Imported {} channels, {} videos and {} tasks",
+                counts.channels, counts.videos, counts.tasks
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Actions from the command line are recorded as made by an admin user.
+fn admin_id(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Vec<u8>> {
+    let admin = auth::get_any_admin(conn)?.ok_or_else(|| anyhow!("No enabled admin user"))?;
+    Ok(admin.id)
+}
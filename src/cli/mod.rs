@@ -0,0 +1,277 @@
+//! Command-line arguments, environment variables and the config file.
+//!
+//! Startup options are read from, highest precedence first:
+//! 1. Command-line flags, eg. `--path-media /srv/media`
+//! 2. Environment variables, eg. `TUBARR_PATH_MEDIA=/srv/media`
+//! 3. TOML config file given with `--config` or `TUBARR_CONFIG`, otherwise tubarr.toml in
+//!    the config folder (see `folders`) if it exists
+//! 4. Values already in the database (set at the first-time setup or in the web UI)
+//! 5. Built-in defaults
+//!
+//! Configuration values given at startup are validated and saved to the database, so they
+//! also replace whatever was set in the web UI.
+//!
+//! With a subcommand, eg. `tubarr task list`, the command is run and the server is not started.
+
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Parser};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::net::IpAddr;
+use std::path::{Path, PathBuf};
+
+use crate::webui;
+
+mod commands;
+pub use commands::*;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "ARR app for Youtube")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// TOML config file
+    #[arg(long, global = true, env = "TUBARR_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Folder for the database, instead of the standard one
+    #[arg(long, global = true, env = "TUBARR_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// SQLite database file, instead of db.sqlite in the data folder
+    #[arg(long, global = true, env = "TUBARR_DB")]
+    pub db: Option<PathBuf>,
+
+    /// Address for the web UI to listen on
+    #[arg(long, env = "TUBARR_ADDRESS")]
+    pub address: Option<IpAddr>,
+
+    /// Port for the web UI to listen on
+    #[arg(long, env = "TUBARR_PORT")]
+    pub port: Option<u16>,
+
+    /// TLS certificate chain (PEM). Serves HTTPS together with --tls-key.
+    #[arg(long, env = "TUBARR_TLS_CERT")]
+    pub tls_cert: Option<PathBuf>,
+
+    /// TLS private key (PEM)
+    #[arg(long, env = "TUBARR_TLS_KEY")]
+    pub tls_key: Option<PathBuf>,
+
+    /// URL path to serve the web UI under, eg. /tubarr behind a reverse proxy
+    #[arg(long, env = "TUBARR_BASE_PATH")]
+    pub base_path: Option<String>,
+
+    /// Never prompt. First-time setup keeps the defaults and given values.
+    #[arg(long, env = "TUBARR_NON_INTERACTIVE")]
+    pub non_interactive: bool,
+
+    /// Password for the admin user, used only when it has not been set yet
+    #[arg(long, env = "TUBARR_ADMIN_PASSWORD", hide_env_values = true)]
+    pub admin_password: Option<String>,
+
+    #[command(flatten)]
+    pub settings: Settings,
+}
+
+/// Configuration values (app_configuration table).
+#[derive(Debug, Default, Args)]
+#[command(next_help_heading = "Configuration")]
+pub struct Settings {
+    /// Folder for unfinished downloads
+    #[arg(long, env = "TUBARR_PATH_TEMP")]
+    pub path_temp: Option<String>,
+
+    /// Folder for downloaded media
+    #[arg(long, env = "TUBARR_PATH_MEDIA")]
+    pub path_media: Option<String>,
+
+    /// Subtitle languages, comma separated patterns
+    #[arg(long, env = "TUBARR_SUB_LANG")]
+    pub sub_lang: Option<String>,
+
+    /// How many times failed downloads are retried
+    #[arg(long, env = "TUBARR_RETRY_LIMIT")]
+    pub retry_limit: Option<String>,
+
+    /// Folder for daily database backups, empty disables them
+    #[arg(long, env = "TUBARR_BACKUP_PATH")]
+    pub backup_path: Option<String>,
+
+    /// How many database backups are kept
+    #[arg(long, env = "TUBARR_BACKUP_KEEP")]
+    pub backup_keep: Option<String>,
+
+    /// Role for users created by proxy or OIDC login
+    #[arg(long, env = "TUBARR_AUTH_DEFAULT_ROLE")]
+    pub auth_default_role: Option<String>,
+
+    /// Header with the username from trusted reverse proxy
+    #[arg(long, env = "TUBARR_AUTH_PROXY_HEADER")]
+    pub auth_proxy_header: Option<String>,
+
+    /// Trusted reverse proxy addresses, IPs or CIDR ranges
+    #[arg(long, env = "TUBARR_AUTH_PROXY_TRUSTED")]
+    pub auth_proxy_trusted: Option<String>,
+
+    /// OpenID Connect issuer URL, must be https://
+    #[arg(long, env = "TUBARR_OIDC_ISSUER")]
+    pub oidc_issuer: Option<String>,
+
+    /// OpenID Connect client ID
+    #[arg(long, env = "TUBARR_OIDC_CLIENT_ID")]
+    pub oidc_client_id: Option<String>,
+
+    /// OpenID Connect client secret
+    #[arg(long, env = "TUBARR_OIDC_CLIENT_SECRET", hide_env_values = true)]
+    pub oidc_client_secret: Option<String>,
+
+    /// OpenID Connect redirect URL
+    #[arg(long, env = "TUBARR_OIDC_REDIRECT_URL")]
+    pub oidc_redirect_url: Option<String>,
+}
+
+impl Settings {
+    /// Given values by key.
+    fn values(&self) -> HashMap<String, String> {
+        [
+            ("path_temp", &self.path_temp),
+            ("path_media", &self.path_media),
+            ("sub_lang", &self.sub_lang),
+            ("retry_limit", &self.retry_limit),
+            ("backup_path", &self.backup_path),
+            ("backup_keep", &self.backup_keep),
+            ("auth_default_role", &self.auth_default_role),
+            ("auth_proxy_header", &self.auth_proxy_header),
+            ("auth_proxy_trusted", &self.auth_proxy_trusted),
+            ("oidc_issuer", &self.oidc_issuer),
+            ("oidc_client_id", &self.oidc_client_id),
+            ("oidc_client_secret", &self.oidc_client_secret),
+            ("oidc_redirect_url", &self.oidc_redirect_url),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| value.clone().map(|value| (key.to_string(), value)))
+        .collect()
+    }
+}
+
+/// Config file contents. Configuration values go to the [settings] table.
+///
+/// ```toml
+/// data_dir = "/srv/tubarr"
+/// address = "0.0.0.0"
+/// port = 8000
+/// base_path = "/tubarr"
+/// non_interactive = true
+///
+/// [settings]
+/// path_media = "/srv/media"
+/// retry_limit = 3
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    data_dir: Option<PathBuf>,
+    db: Option<PathBuf>,
+    address: Option<IpAddr>,
+    port: Option<u16>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    base_path: Option<String>,
+    non_interactive: Option<bool>,
+    admin_password: Option<String>,
+    #[serde(default)]
+    settings: HashMap<String, toml::Value>,
+}
+
+/// Startup options with every source merged.
+#[derive(Debug)]
+pub struct Options {
+    pub command: Option<Command>,
+    pub data_dir: Option<PathBuf>,
+    pub db: Option<PathBuf>,
+    pub address: Option<IpAddr>,
+    pub port: Option<u16>,
+    /// Certificate chain and private key.
+    pub tls: Option<(PathBuf, PathBuf)>,
+    /// Normalized, see `webui::normalize_base_path`.
+    pub base_path: String,
+    pub non_interactive: bool,
+    pub admin_password: Option<String>,
+    /// Configuration values to save to the database.
+    pub settings: HashMap<String, String>,
+}
+
+impl Cli {
+    /// Merge with the config file. Default config file is used only if it exists.
+    pub fn into_options(self, default_config: Option<&Path>) -> Result<Options> {
+        let file = match (&self.config, default_config) {
+            (Some(path), _) => read_config_file(path)?,
+            (None, Some(path)) if path.is_file() => read_config_file(path)?,
+            (None, _) => ConfigFile::default(),
+        };
+
+        let mut settings = HashMap::with_capacity(file.settings.len());
+        for (key, value) in file.settings {
+            let value = match value {
+                toml::Value::String(value) => value,
+                toml::Value::Integer(value) => value.to_string(),
+                toml::Value::Boolean(value) => value.to_string(),
+                _ => return Err(anyhow!("Config file setting '{}' must be a string", key)),
+            };
+            settings.insert(key, value);
+        }
+        settings.extend(self.settings.values());
+
+        let tls = match (
+            self.tls_cert.or(file.tls_cert),
+            self.tls_key.or(file.tls_key),
+        ) {
+            (Some(cert), Some(key)) => Some((cert, key)),
+            (None, None) => None,
+            _ => return Err(anyhow!("TLS needs both certificate and key")),
+        };
+        let base_path = self.base_path.or(file.base_path).unwrap_or_default();
+        let base_path = webui::normalize_base_path(&base_path)?;
+
+        Ok(Options {
+            command: self.command,
+            data_dir: self.data_dir.or(file.data_dir),
+            db: self.db.or(file.db),
+            address: self.address.or(file.address),
+            port: self.port.or(file.port),
+            tls,
+            base_path,
+            non_interactive: self.non_interactive || file.non_interactive.unwrap_or(false),
+            admin_password: self.admin_password.or(file.admin_password),
+            settings,
+        })
+    }
+}
+
+fn read_config_file(path: &Path) -> Result<ConfigFile> {
+    let contents = fs::read_to_string(path)
+        .with_context(|| format!("Could not read config file {}", path.display()))?;
+    toml::from_str(&contents).with_context(|| format!("Invalid config file {}", path.display()))
+}
+
+/// Ask new password twice from the terminal.
+pub fn prompt_new_password() -> Result<String> {
+    loop {
+        let password = rpassword::prompt_password("Password: ")?;
+        if password.is_empty() {
+            println!("Password can not be empty.");
+            continue;
+        }
+
+        let again = rpassword::prompt_password("Password again: ")?;
+        if password != again {
+            println!("Passwords do not match.");
+            continue;
+        }
+
+        return Ok(password);
+    }
+}
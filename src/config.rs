@@ -0,0 +1,236 @@
+//! Application configuration (app_configuration table).
+//!
+//! Changes go through `set_values`, which validates every value before writing any of them.
+//! Every change bumps the generation in the database. Long running parts, like the
+//! taskrunner, compare `generation()` to reload changed values without a restart, also when
+//! the change was made by another process.
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use regex::Regex;
+use rusqlite::{params, Connection};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::net::IpAddr;
+use std::path::Path;
+use thiserror::Error;
+
+/// Keys holding secrets. These are never shown back to the user.
+pub const SECRET_KEYS: &[&str] = &["oidc_client_secret"];
+
+/// Keys holding folders, checked with `validate_folder`.
+const FOLDER_KEYS: &[&str] = &["path_temp", "path_media", "backup_path"];
+
+/// Keys not meant to be edited by the user.
+const INTERNAL_KEYS: &[&str] = &["first_time_setup"];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    /// Validation errors by key.
+    #[error("Invalid configuration")]
+    Invalid(BTreeMap<String, String>),
+    #[error(transparent)]
+    Database(#[from] rusqlite::Error),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+#[derive(Debug, Error)]
+pub enum ValidationError {
+    #[error("Unknown configuration key")]
+    UnknownKey,
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// Current configuration generation. Changes every time the configuration is saved.
+pub fn generation(conn: &Connection) -> anyhow::Result<i64> {
+    let generation = conn.query_row("SELECT generation FROM config_generation", [], |row| {
+        row.get(0)
+    })?;
+    Ok(generation)
+}
+
+/// Mark the configuration changed. Call in the transaction that saves the change.
+pub fn bump_generation(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE config_generation SET generation = generation + 1",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Load every configuration value.
+pub fn get_values(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> anyhow::Result<HashMap<String, String>> {
+    let mut stmt = conn.prepare("SELECT key, value FROM app_configuration")?;
+    let values = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<HashMap<String, String>>>()?;
+    Ok(values)
+}
+
+/// Validate and save the given values, others are left as they are.
+/// Nothing is saved if any value is invalid.
+pub fn set_values(
+    conn: &mut PooledConnection<SqliteConnectionManager>,
+    values: &HashMap<String, String>,
+) -> Result<(), ConfigError> {
+    let known = get_values(conn)?;
+
+    let errors: BTreeMap<String, String> = values
+        .iter()
+        .filter_map(|(key, value)| {
+            let result = match known.contains_key(key) && !INTERNAL_KEYS.contains(&key.as_str()) {
+                true => validate(key, value),
+                false => Err(ValidationError::UnknownKey),
+            };
+            result.err().map(|e| (key.clone(), e.to_string()))
+        })
+        .collect();
+    if !errors.is_empty() {
+        return Err(ConfigError::Invalid(errors));
+    }
+
+    let tx = conn.transaction()?;
+    for (key, value) in values {
+        tx.execute(
+            "UPDATE app_configuration SET value = ?1 WHERE key = ?2",
+            params![value.trim(), key],
+        )?;
+    }
+    bump_generation(&tx)?;
+    tx.commit()?;
+
+    Ok(())
+}
+
+/// Check single value. Keys without rules accept anything.
+pub fn validate(key: &str, value: &str) -> Result<(), ValidationError> {
+    let value = value.trim();
+    match key {
+        "path_temp" | "path_media" => validate_folder(value),
+        // Empty disables backups
+        "backup_path" if value.is_empty() => Ok(()),
+        "backup_path" => validate_folder(value),
+        "backup_keep" => match value.parse::<u32>() {
+            Ok(keep) if keep > 0 => Ok(()),
+            _ => Err(invalid("Must be a positive number")),
+        },
+        "sub_lang" => validate_sub_lang(value),
+        "retry_limit" => match value.parse::<u32>() {
+            Ok(_) => Ok(()),
+            Err(_) => Err(invalid("Must be zero or a positive number")),
+        },
+        "auth_default_role" => match value {
+            "ADMIN" | "REQUESTER" => Ok(()),
+            _ => Err(invalid("Must be ADMIN or REQUESTER")),
+        },
+        "auth_proxy_header" => {
+            let is_token = value
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+            match is_token {
+                true => Ok(()),
+                false => Err(invalid("Not a valid header name")),
+            }
+        }
+        "auth_proxy_trusted" => validate_ip_list(value),
+        // ID tokens are trusted for coming over TLS from the issuer, see auth::oidc
+        "oidc_issuer" => {
+            if value.is_empty() || value.starts_with("https://") {
+                Ok(())
+            } else {
+                Err(invalid("Must be https:// URL"))
+            }
+        }
+        "oidc_redirect_url" => {
+            if value.is_empty() || value.starts_with("http://") || value.starts_with("https://") {
+                Ok(())
+            } else {
+                Err(invalid("Must be http:// or https:// URL"))
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Create the folder of a folder key if it's missing, so that the defaults work on a fresh
+/// host. Returns true if the folder was created. Other keys and relative paths are left for
+/// `validate` to complain about.
+pub fn create_missing_folder(key: &str, value: &str) -> std::io::Result<bool> {
+    let path = Path::new(value.trim());
+    if !FOLDER_KEYS.contains(&key) || !path.is_absolute() || path.exists() {
+        return Ok(false);
+    }
+    fs::create_dir_all(path)?;
+    Ok(true)
+}
+
+/// Existing, writable folder given with absolute path.
+fn validate_folder(value: &str) -> Result<(), ValidationError> {
+    let path = Path::new(value);
+    if !path.is_absolute() {
+        return Err(invalid("Path must be absolute"));
+    }
+    if !path.is_dir() {
+        return Err(invalid("Folder does not exist"));
+    }
+    check_writable(path).map_err(|e| invalid(&format!("Folder is not writable: {}", e)))
+}
+
+/// Subtitle languages for yt-dlp: comma separated regexes, "-" in front excludes.
+fn validate_sub_lang(value: &str) -> Result<(), ValidationError> {
+    if value.is_empty() {
+        return Err(invalid("At least one language is needed"));
+    }
+    for lang in value.split(',') {
+        let pattern = lang.trim().trim_start_matches('-');
+        if pattern.is_empty() {
+            return Err(invalid("Empty language in the list"));
+        }
+        if let Err(e) = Regex::new(pattern) {
+            return Err(invalid(&format!(
+                "Invalid language pattern '{}': {}",
+                pattern, e
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Comma separated IPs and CIDR ranges.
+fn validate_ip_list(value: &str) -> Result<(), ValidationError> {
+    for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let (ip, prefix) = match entry.split_once('/') {
+            Some((ip, prefix)) => (ip, Some(prefix)),
+            None => (entry, None),
+        };
+        let max_prefix = match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(_)) => 32,
+            Ok(IpAddr::V6(_)) => 128,
+            Err(_) => return Err(invalid(&format!("Invalid address '{}'", entry))),
+        };
+        if let Some(prefix) = prefix {
+            if !prefix
+                .parse::<u32>()
+                .is_ok_and(|prefix| prefix <= max_prefix)
+            {
+                return Err(invalid(&format!("Invalid range '{}'", entry)));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Can files be created in the folder.
+pub fn check_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".tubarr-probe-{}", std::process::id()));
+    fs::write(&probe, b"")?;
+    fs::remove_file(&probe)
+}
+
+fn invalid(reason: &str) -> ValidationError {
+    ValidationError::Invalid(reason.to_string())
+}
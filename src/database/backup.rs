@@ -0,0 +1,142 @@
+//! Database backups with SQLite's online backup API. Safe to take while Tubarr is running.
+//!
+//! Restoring is not: the server holds `ServerLock` while it runs, and restore and import
+//! take it too, so they refuse to run while the server is up.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use rusqlite::{Connection, DatabaseName, OpenFlags};
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use super::{db_expected_version, db_get_version};
+
+/// Exclusive lock on "<database>.lock", released when dropped or when the process exits.
+pub struct ServerLock {
+    _file: File,
+}
+
+impl ServerLock {
+    pub fn acquire(db_path: &Path) -> Result<Self> {
+        let path = db_path.with_extension("sqlite.lock");
+        let file = File::create(&path)
+            .with_context(|| format!("Failed to create lock file {}", path.display()))?;
+        match file.try_lock() {
+            Ok(()) => Ok(ServerLock { _file: file }),
+            Err(TryLockError::WouldBlock) => Err(anyhow!(
+                "Database {} is in use by a running Tubarr server. Stop the server first.",
+                db_path.display()
+            )),
+            Err(TryLockError::Error(e)) => {
+                Err(e).with_context(|| format!("Failed to lock {}", path.display()))
+            }
+        }
+    }
+}
+
+/// Rotated backups are named tubarr-<UTC timestamp>.sqlite
+const BACKUP_PREFIX: &str = "tubarr-";
+const BACKUP_SUFFIX: &str = ".sqlite";
+
+/// Pages copied per step. Other connections can write between the steps.
+const PAGES_PER_STEP: i32 = 128;
+
+/// Write consistent copy of the database to the file. The copy is written next to the
+/// target first and renamed in place when complete.
+pub fn backup_to(conn: &Connection, path: &Path) -> Result<()> {
+    let partial = path.with_extension("partial");
+    let _ = fs::remove_file(&partial);
+
+    let mut target = Connection::open(&partial)
+        .with_context(|| format!("Failed to create {}", partial.display()))?;
+    rusqlite::backup::Backup::new(conn, &mut target)?
+        .run_to_completion(PAGES_PER_STEP, Duration::from_millis(10), None)
+        .context("Database backup failed")?;
+    drop(target);
+
+    fs::rename(&partial, path)
+        .with_context(|| format!("Failed to move backup to {}", path.display()))?;
+    Ok(())
+}
+
+/// Write new backup to the folder, and remove old ones so that `keep` newest are left.
+/// Returns the path of the new backup.
+pub fn backup_rotated(conn: &Connection, dir: &Path, keep: usize) -> Result<PathBuf> {
+    let name = format!(
+        "{}{}{}",
+        BACKUP_PREFIX,
+        Utc::now().format("%Y%m%d-%H%M%S"),
+        BACKUP_SUFFIX
+    );
+    let path = dir.join(name);
+    backup_to(conn, &path)?;
+
+    let mut backups: Vec<PathBuf> = fs::read_dir(dir)
+        .with_context(|| format!("Failed to list {}", dir.display()))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| {
+                    name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX)
+                })
+        })
+        .collect();
+
+    // Timestamps in the names sort oldest first
+    backups.sort();
+    let remove = backups.len().saturating_sub(keep.max(1));
+    for old in &backups[..remove] {
+        debug!("Removing old backup {}", old.display());
+        if let Err(e) = fs::remove_file(old) {
+            warn!("Failed to remove old backup {}: {}", old.display(), e);
+        }
+    }
+
+    Ok(path)
+}
+
+/// Replace the database with a backup. The backup must pass integrity check and must not
+/// be from a newer version of Tubarr. Current database is saved next to it with
+/// .before-restore extension. Returns schema version of the restored database, older ones
+/// are upgraded as usual on the next start.
+pub fn restore_from(db_path: &Path, backup: &Path) -> Result<u32> {
+    let source = Connection::open_with_flags(backup, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("Failed to open {}", backup.display()))?;
+
+    let check: String = source
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .context("Failed to check backup")?;
+    if check != "ok" {
+        return Err(anyhow!("Backup failed integrity check: {}", check));
+    }
+
+    let version = db_get_version(&source).context("Backup is not a Tubarr database")?;
+    if version > db_expected_version() {
+        return Err(anyhow!(
+            "Backup schema version {} is newer than this version of Tubarr supports ({})",
+            version,
+            db_expected_version()
+        ));
+    }
+    drop(source);
+
+    let mut target = Connection::open(db_path)?;
+    if db_path.metadata().is_ok_and(|meta| meta.len() > 0) {
+        backup_to(&target, &db_path.with_extension("sqlite.before-restore"))
+            .context("Failed to save current database before restore")?;
+    }
+
+    target
+        .restore(
+            DatabaseName::Main,
+            backup,
+            None::<fn(rusqlite::backup::Progress)>,
+        )
+        .context("Restore failed")?;
+
+    Ok(version)
+}
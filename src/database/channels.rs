@@ -0,0 +1,295 @@
+//! Followed channels (channels table).
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{Page, PageRequest, SortColumn};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Channel {
+    pub id: i64,
+    pub domain: String,
+    /// Without the scheme, eg. youtube.com/channel/<channel_id>
+    pub url: String,
+    /// ID on the site, eg. UC...
+    pub channel_id: String,
+    pub channel_name: String,
+    /// Used in the web UI paths, see `normalize_channel_name`.
+    pub channel_name_normalized: Option<String>,
+    pub updated_at: String,
+}
+
+const COLUMNS: &str =
+    "id, domain, url, channel_id, channel_name, channel_name_normalized, updated_at";
+
+pub const CHANNEL_SORT_COLUMNS: [SortColumn; 4] = [
+    ("id", "id"),
+    ("channel_name", "channel_name COLLATE NOCASE"),
+    ("domain", "domain"),
+    ("updated_at", "updated_at"),
+];
+
+impl Channel {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Channel {
+            id: row.get(0)?,
+            domain: row.get(1)?,
+            url: row.get(2)?,
+            channel_id: row.get(3)?,
+            channel_name: row.get(4)?,
+            channel_name_normalized: row.get(5)?,
+            updated_at: row.get(6)?,
+        })
+    }
+}
+
+/// Channel name for the web UI paths: quotes removed, spaces to hyphens, lowercase.
+pub fn normalize_channel_name(channel_name: &str) -> String {
+    channel_name
+        .replace(['\'', '"'], "")
+        .replace(' ', "-")
+        .to_lowercase()
+}
+
+pub fn get_channels(conn: &Connection) -> Result<Vec<Channel>> {
+    let mut stmt = conn.prepare(&format!("SELECT {} FROM channels ORDER BY id", COLUMNS))?;
+    let channels = stmt
+        .query_map([], Channel::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(channels)
+}
+
+/// One page of channels, optionally only from the domain.
+pub fn list_channels(
+    conn: &Connection,
+    domain: Option<&str>,
+    page: &PageRequest,
+) -> Result<Page<Channel>> {
+    let total = conn.query_row(
+        "SELECT COUNT(*) FROM channels WHERE ?1 IS NULL OR domain = ?1",
+        params![domain],
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM channels WHERE ?1 IS NULL OR domain = ?1 {}",
+        COLUMNS,
+        page.sql()
+    ))?;
+    let channels = stmt
+        .query_map(params![domain], Channel::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(page.page(channels, total))
+}
+
+pub fn get_channel_by_id(conn: &Connection, id: i64) -> Result<Option<Channel>> {
+    let channel = conn
+        .query_row(
+            &format!("SELECT {} FROM channels WHERE id = ?1", COLUMNS),
+            params![id],
+            Channel::from_row,
+        )
+        .optional()?;
+    Ok(channel)
+}
+
+/// Channel by its URL, with or without the scheme and www.
+pub fn get_channel_by_url(conn: &Connection, url: &str) -> Result<Option<Channel>> {
+    let url = url.trim().trim_end_matches('/');
+    let url = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let url = url.strip_prefix("www.").unwrap_or(url);
+
+    let channel = conn
+        .query_row(
+            &format!("SELECT {} FROM channels WHERE url = ?1", COLUMNS),
+            params![url],
+            Channel::from_row,
+        )
+        .optional()?;
+    Ok(channel)
+}
+
+/// Channel by its ID on the site.
+pub fn get_channel(conn: &Connection, domain: &str, channel_id: &str) -> Result<Option<Channel>> {
+    let channel = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM channels WHERE domain = ?1 AND channel_id = ?2",
+                COLUMNS
+            ),
+            params![domain, channel_id],
+            Channel::from_row,
+        )
+        .optional()?;
+    Ok(channel)
+}
+
+/// Channel by its normalized name, as used in the web UI paths.
+pub fn get_channel_by_name(
+    conn: &Connection,
+    domain: &str,
+    channel_name_normalized: &str,
+) -> Result<Option<Channel>> {
+    let channel = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM channels WHERE domain = ?1 AND channel_name_normalized = LOWER(?2)",
+                COLUMNS
+            ),
+            params![domain, channel_name_normalized],
+            Channel::from_row,
+        )
+        .optional()?;
+    Ok(channel)
+}
+
+/// Start following channel. Returns the row ID.
+pub fn insert_channel(
+    conn: &Connection,
+    domain: &str,
+    url: &str,
+    channel_id: &str,
+    channel_name: &str,
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO channels (domain, url, channel_id, channel_name, channel_name_normalized)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            domain,
+            url,
+            channel_id,
+            channel_name,
+            normalize_channel_name(channel_name)
+        ],
+    )
+    .context("Failed to insert channel")?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Stop following channel and forget its videos. Downloaded files are kept.
+/// Returns the number of removed videos, or None if there was no such channel.
+pub fn delete_channel(conn: &Connection, id: i64) -> Result<Option<usize>> {
+    let tx = conn.unchecked_transaction()?;
+    // Foreign keys are not enforced, clean up the videos as well
+    let videos = tx.execute("DELETE FROM videos WHERE channel_id = ?1", params![id])?;
+    let removed = tx.execute("DELETE FROM channels WHERE id = ?1", params![id])?;
+    if removed == 0 {
+        return Ok(None);
+    }
+    tx.commit()?;
+    Ok(Some(videos))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{sort_column, test_connection};
+
+    #[test]
+    fn normalizes_names() {
+        assert_eq!(
+            normalize_channel_name("Tom's \"Big\" Channel"),
+            "toms-big-channel"
+        );
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let conn = test_connection();
+        let id = insert_channel(
+            &conn,
+            "youtube.com",
+            "youtube.com/channel/UC1",
+            "UC1",
+            "My Channel",
+        )
+        .unwrap();
+
+        let channel = get_channel(&conn, "youtube.com", "UC1").unwrap().unwrap();
+        assert_eq!(channel.id, id);
+        assert_eq!(channel.channel_name, "My Channel");
+        assert_eq!(
+            channel.channel_name_normalized.as_deref(),
+            Some("my-channel")
+        );
+
+        let by_name = get_channel_by_name(&conn, "youtube.com", "My-Channel").unwrap();
+        assert_eq!(by_name.map(|c| c.id), Some(id));
+        let by_url = get_channel_by_url(&conn, "https://www.youtube.com/channel/UC1/").unwrap();
+        assert_eq!(by_url.map(|c| c.id), Some(id));
+        assert_eq!(
+            get_channel_by_id(&conn, id).unwrap().map(|c| c.id),
+            Some(id)
+        );
+        assert!(get_channel_by_id(&conn, id + 1).unwrap().is_none());
+        assert!(get_channel(&conn, "youtube.com", "UC2").unwrap().is_none());
+        assert_eq!(get_channels(&conn).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn lists_channels_sorted_by_name() {
+        let conn = test_connection();
+        insert_channel(
+            &conn,
+            "youtube.com",
+            "youtube.com/channel/UC1",
+            "UC1",
+            "beta",
+        )
+        .unwrap();
+        insert_channel(
+            &conn,
+            "youtube.com",
+            "youtube.com/channel/UC2",
+            "UC2",
+            "Alpha",
+        )
+        .unwrap();
+        insert_channel(&conn, "vimeo.com", "vimeo.com/c", "c", "Gamma").unwrap();
+
+        let page = PageRequest {
+            sort: sort_column(&CHANNEL_SORT_COLUMNS, "channel_name").unwrap(),
+            ..PageRequest::first(&CHANNEL_SORT_COLUMNS)
+        };
+        let names = |page: Page<Channel>| {
+            page.items
+                .into_iter()
+                .map(|c| c.channel_name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(list_channels(&conn, None, &page).unwrap()),
+            ["Alpha", "beta", "Gamma"]
+        );
+        let youtube = list_channels(&conn, Some("youtube.com"), &page).unwrap();
+        assert_eq!(youtube.total, 2);
+        assert_eq!(names(youtube), ["Alpha", "beta"]);
+    }
+
+    #[test]
+    fn duplicate_channel_is_an_error() {
+        let conn = test_connection();
+        insert_channel(&conn, "youtube.com", "youtube.com/channel/UC1", "UC1", "A").unwrap();
+        assert!(
+            insert_channel(&conn, "youtube.com", "youtube.com/channel/UC1", "UC1", "A").is_err()
+        );
+    }
+
+    #[test]
+    fn delete_removes_videos() {
+        let conn = test_connection();
+        let id =
+            insert_channel(&conn, "youtube.com", "youtube.com/channel/UC1", "UC1", "A").unwrap();
+        conn.execute(
+            "INSERT INTO videos (channel_id, domain, url, name, video_id, is_requested, is_downloaded)
+                VALUES (?1, 'youtube.com', 'https://y/1', 'Video', 'v1', 0, 0)",
+            params![id],
+        )
+        .unwrap();
+
+        assert_eq!(delete_channel(&conn, id).unwrap(), Some(1));
+        assert_eq!(delete_channel(&conn, id).unwrap(), None);
+        assert!(get_channels(&conn).unwrap().is_empty());
+    }
+}
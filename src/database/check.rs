@@ -0,0 +1,152 @@
+//! Database integrity and schema drift checks.
+//!
+//! The expected schema is built by running every upgrade on an in-memory database, and the
+//! tables, columns, indexes and triggers are compared against it.
+
+use anyhow::Result;
+use rusqlite::Connection;
+use std::collections::BTreeMap;
+
+use super::{db_expected_version, db_get_version, scripts};
+
+/// Column name, type, not null, default value and primary key.
+type Column = (String, String, bool, Option<String>, u32);
+type Columns = Vec<Column>;
+
+#[derive(Debug, Default)]
+struct Schema {
+    tables: BTreeMap<String, Columns>,
+    /// Indexes, triggers and views by name, with their type.
+    objects: BTreeMap<String, String>,
+}
+
+/// Problems found in the database. Empty when everything is fine.
+pub fn check(conn: &Connection) -> Result<Vec<String>> {
+    let mut problems = Vec::new();
+
+    let mut stmt = conn.prepare("PRAGMA integrity_check")?;
+    let integrity = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+    if integrity != ["ok"] {
+        problems.extend(integrity.into_iter().map(|e| format!("Integrity: {}", e)));
+    }
+
+    let version = db_get_version(conn)?;
+    if version != db_expected_version() {
+        problems.push(format!(
+            "Schema version is {}, expected {}. Run 'tubarr db migrate' first.",
+            version,
+            db_expected_version()
+        ));
+        return Ok(problems);
+    }
+
+    let expected = Connection::open_in_memory()?;
+    scripts::upgrade_from(0, &expected)?;
+    problems.extend(compare(&read_schema(&expected)?, &read_schema(conn)?));
+
+    Ok(problems)
+}
+
+fn read_schema(conn: &Connection) -> Result<Schema> {
+    let mut schema = Schema::default();
+
+    let mut stmt = conn.prepare(
+        "SELECT name, type FROM sqlite_master
+            WHERE name NOT LIKE 'sqlite_%'
+            ORDER BY name",
+    )?;
+    let objects = stmt
+        .query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    for (name, kind) in objects {
+        if kind != "table" {
+            schema.objects.insert(name, kind);
+            continue;
+        }
+
+        let mut stmt = conn.prepare(
+            "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info(?1) ORDER BY cid",
+        )?;
+        let columns = stmt
+            .query_map([&name], |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get(3)?,
+                    row.get(4)?,
+                ))
+            })?
+            .collect::<rusqlite::Result<Columns>>()?;
+        schema.tables.insert(name, columns);
+    }
+
+    Ok(schema)
+}
+
+fn compare(expected: &Schema, actual: &Schema) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    for (table, columns) in &expected.tables {
+        let Some(actual_columns) = actual.tables.get(table) else {
+            problems.push(format!("Missing table: {}", table));
+            continue;
+        };
+        for column in columns {
+            match actual_columns.iter().find(|c| c.0 == column.0) {
+                None => problems.push(format!("Missing column: {}.{}", table, column.0)),
+                Some(actual) if actual != column => problems.push(format!(
+                    "Changed column: {}.{} is {}, expected {}",
+                    table,
+                    column.0,
+                    describe(actual),
+                    describe(column)
+                )),
+                Some(_) => {}
+            }
+        }
+        for column in actual_columns {
+            if !columns.iter().any(|c| c.0 == column.0) {
+                problems.push(format!("Unexpected column: {}.{}", table, column.0));
+            }
+        }
+    }
+    for table in actual.tables.keys() {
+        if !expected.tables.contains_key(table) {
+            problems.push(format!("Unexpected table: {}", table));
+        }
+    }
+
+    for (name, kind) in &expected.objects {
+        if actual.objects.get(name) != Some(kind) {
+            problems.push(format!("Missing {}: {}", kind, name));
+        }
+    }
+    for (name, kind) in &actual.objects {
+        if !expected.objects.contains_key(name) {
+            problems.push(format!("Unexpected {}: {}", kind, name));
+        }
+    }
+
+    problems
+}
+
+fn describe(column: &Column) -> String {
+    let (_, kind, not_null, default, pk) = column;
+    let mut description = kind.clone();
+    if *not_null {
+        description.push_str(" NOT NULL");
+    }
+    if let Some(default) = default {
+        description.push_str(&format!(" DEFAULT {}", default));
+    }
+    if *pk > 0 {
+        description.push_str(" PRIMARY KEY");
+    }
+    description
+}
@@ -0,0 +1,184 @@
+//! JSON export and import of channels, videos, tasks and configuration, for moving Tubarr
+//! to a new host. Users, sessions and API keys are not included.
+//!
+//! Rows are exported by column name, so exports from older schema versions can be imported.
+//! Columns missing from the file get their defaults.
+
+use anyhow::{anyhow, Context, Result};
+use chrono::Utc;
+use rusqlite::types::ValueRef;
+use rusqlite::{params_from_iter, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+
+use super::{db_expected_version, db_get_version};
+use crate::config;
+
+/// Version of the export file format.
+const FORMAT: u32 = 1;
+
+/// Exported tables, in import order.
+const TABLES: [&str; 3] = ["channels", "videos", "tasks"];
+
+type Row = Map<String, Value>;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Export {
+    pub format: u32,
+    pub schema_version: u32,
+    pub exported_at: String,
+    /// Includes secrets, keep the file safe.
+    pub configuration: BTreeMap<String, String>,
+    pub channels: Vec<Row>,
+    pub videos: Vec<Row>,
+    pub tasks: Vec<Row>,
+}
+
+#[derive(Debug)]
+pub struct ImportCounts {
+    pub channels: usize,
+    pub videos: usize,
+    pub tasks: usize,
+}
+
+impl Export {
+    fn rows(&self, table: &str) -> &[Row] {
+        match table {
+            "channels" => &self.channels,
+            "videos" => &self.videos,
+            _ => &self.tasks,
+        }
+    }
+}
+
+pub fn export(conn: &Connection) -> Result<Export> {
+    let mut stmt =
+        conn.prepare("SELECT key, value FROM app_configuration WHERE key != 'first_time_setup'")?;
+    let configuration = stmt
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<BTreeMap<String, String>>>()?;
+
+    Ok(Export {
+        format: FORMAT,
+        schema_version: db_get_version(conn)?,
+        exported_at: Utc::now().naive_utc().to_string(),
+        configuration,
+        channels: export_table(conn, "channels")?,
+        videos: export_table(conn, "videos")?,
+        tasks: export_table(conn, "tasks")?,
+    })
+}
+
+/// Replace channels, videos and tasks with the exported ones, and set the exported
+/// configuration values. Everything is done in one transaction.
+pub fn import(conn: &mut Connection, export: &Export) -> Result<ImportCounts> {
+    if export.format != FORMAT {
+        return Err(anyhow!("Unsupported export format {}", export.format));
+    }
+    if export.schema_version > db_expected_version() {
+        return Err(anyhow!(
+            "Export schema version {} is newer than this version of Tubarr supports ({})",
+            export.schema_version,
+            db_expected_version()
+        ));
+    }
+
+    let tx = conn.transaction()?;
+
+    // Videos refer to channels, remove them first
+    for table in TABLES.iter().rev() {
+        tx.execute(&format!("DELETE FROM {}", table), [])?;
+    }
+    for table in TABLES {
+        import_table(&tx, table, export.rows(table))
+            .with_context(|| format!("Failed to import {}", table))?;
+    }
+
+    // Tasks that were running on the old host are started again
+    tx.execute(
+        "UPDATE tasks SET task_state = 'WAIT' WHERE task_state = 'WIP'",
+        [],
+    )?;
+
+    for (key, value) in &export.configuration {
+        tx.execute(
+            "UPDATE app_configuration SET value = ?1
+                WHERE key = ?2 AND key != 'first_time_setup'",
+            [value, key],
+        )?;
+    }
+
+    config::bump_generation(&tx)?;
+    tx.commit()?;
+
+    Ok(ImportCounts {
+        channels: export.channels.len(),
+        videos: export.videos.len(),
+        tasks: export.tasks.len(),
+    })
+}
+
+/// Every row as column name to value. BLOB columns (user IDs) are left out.
+fn export_table(conn: &Connection, table: &str) -> Result<Vec<Row>> {
+    let mut stmt = conn.prepare(&format!("SELECT * FROM {} ORDER BY id", table))?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+
+    let mut rows = stmt.query([])?;
+    let mut exported = Vec::new();
+    while let Some(row) = rows.next()? {
+        let mut map = Row::new();
+        for (i, column) in columns.iter().enumerate() {
+            let value = match row.get_ref(i)? {
+                ValueRef::Null => Value::Null,
+                ValueRef::Integer(value) => Value::from(value),
+                ValueRef::Real(value) => Value::from(value),
+                ValueRef::Text(value) => Value::from(String::from_utf8_lossy(value).to_string()),
+                ValueRef::Blob(_) => continue,
+            };
+            map.insert(column.clone(), value);
+        }
+        exported.push(map);
+    }
+
+    Ok(exported)
+}
+
+fn import_table(conn: &Connection, table: &str, rows: &[Row]) -> Result<()> {
+    let mut stmt = conn.prepare(&format!("SELECT name FROM pragma_table_info('{}')", table))?;
+    let known = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<String>>>()?;
+
+    for row in rows {
+        let columns: Vec<&String> = row.keys().filter(|column| known.contains(column)).collect();
+        if columns.is_empty() {
+            continue;
+        }
+
+        let values = columns.iter().map(|column| match &row[column.as_str()] {
+            Value::Null => rusqlite::types::Value::Null,
+            Value::Bool(value) => rusqlite::types::Value::Integer(*value as i64),
+            Value::Number(value) => match value.as_i64() {
+                Some(value) => rusqlite::types::Value::Integer(value),
+                None => rusqlite::types::Value::Real(value.as_f64().unwrap_or_default()),
+            },
+            Value::String(value) => rusqlite::types::Value::Text(value.clone()),
+            value => rusqlite::types::Value::Text(value.to_string()),
+        });
+
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            table,
+            columns
+                .iter()
+                .map(|column| format!("\"{}\"", column))
+                .collect::<Vec<_>>()
+                .join(", "),
+            vec!["?"; columns.len()].join(", ")
+        );
+        conn.execute(&sql, params_from_iter(values))?;
+    }
+
+    Ok(())
+}
@@ -1,44 +1,149 @@
-use std::{fs, path::PathBuf, sync::Arc};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
 use anyhow::{anyhow, Context, Ok, Result};
-use r2d2::{self, Pool, PooledConnection};
+use r2d2::{self, Pool};
 use r2d2_sqlite::{self, SqliteConnectionManager};
-use rusqlite::{params, OptionalExtension};
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
 use tracing::{debug, error, event, info, trace, warn};
 
+mod backup;
+mod channels;
+mod check;
+mod export;
+mod paging;
 mod scripts;
+mod search;
+mod tasks;
+mod videos;
 
-pub fn init_from(filepath: &PathBuf) -> Arc<r2d2::Pool<SqliteConnectionManager>> {
+pub use backup::*;
+pub use channels::*;
+pub use check::*;
+pub use export::*;
+pub use paging::*;
+pub use search::*;
+pub use tasks::*;
+pub use videos::*;
+
+pub fn init_from(filepath: &PathBuf) -> Result<Arc<r2d2::Pool<SqliteConnectionManager>>> {
     info!("Loading database from: {:#?}", filepath);
 
-    // Initialize the database connection
-    let sqlite_conman = SqliteConnectionManager::file(filepath);
-    let sqlite_pool = r2d2::Pool::new(sqlite_conman).expect("Failed to start SQLITE pool.");
-    let pool = Arc::new(sqlite_pool);
+    let pool = open(filepath);
 
     // Upgrade the database
-    let _ = db_upgrade(pool.clone());
+    db_upgrade(pool.clone())?;
 
-    return pool;
+    Ok(pool)
 }
 
-fn db_upgrade(pool: Arc<Pool<SqliteConnectionManager>>) -> Result<()> {
-    let conn = pool.get().expect("Could not connect to SQLITE.");
+/// Copy database from the old location to the new one, if there's nothing at the new one.
+/// The copy is a consistent snapshot even if the old database is in use. Old file is left
+/// as it is. Returns true if the database was copied.
+pub fn migrate_legacy(legacy: &Path, filepath: &Path) -> Result<bool> {
+    if filepath.exists() || !legacy.is_file() || legacy == filepath {
+        return Ok(false);
+    }
+    info!(
+        "Copying database from {} to {}",
+        legacy.display(),
+        filepath.display()
+    );
+
+    // Copy next to the target first, so that a half-written file is never used
+    let partial = filepath.with_extension("sqlite.partial");
+    let _ = fs::remove_file(&partial);
+
+    let conn = Connection::open_with_flags(legacy, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .context("Failed to open old database")?;
+    conn.execute(
+        "VACUUM INTO ?1",
+        params![partial.to_string_lossy().to_string()],
+    )
+    .context("Failed to copy old database")?;
+    drop(conn);
+
+    let check: String = Connection::open(&partial)?
+        .query_row("PRAGMA integrity_check", [], |row| row.get(0))
+        .context("Failed to check copied database")?;
+    if check != "ok" {
+        let _ = fs::remove_file(&partial);
+        return Err(anyhow!("Copied database failed integrity check: {}", check));
+    }
 
-    // Get DB version
-    let db_version = db_get_version(&conn);
+    fs::rename(&partial, filepath).context("Failed to move copied database in place")?;
+    warn!(
+        "Database copied to {}. The old file {} is no longer used and can be removed.",
+        filepath.display(),
+        legacy.display()
+    );
+    Ok(true)
+}
+
+/// Open the database as it is, without upgrading it.
+pub fn open(filepath: &PathBuf) -> Arc<r2d2::Pool<SqliteConnectionManager>> {
+    let sqlite_conman = SqliteConnectionManager::file(filepath);
+    let sqlite_pool = r2d2::Pool::new(sqlite_conman).expect("Failed to start SQLITE pool.");
+    Arc::new(sqlite_pool)
+}
 
-    let idx = match db_version {
-        Result::Ok(idx) => idx,
-        Result::Err(_) => 0,
+/// Upgrade the database to the version of this binary. The database is backed up next to
+/// itself before the first upgrade. Databases from a newer version are refused.
+pub fn db_upgrade(pool: Arc<Pool<SqliteConnectionManager>>) -> Result<()> {
+    let conn = pool.get().context("Could not connect to SQLITE.")?;
+
+    let db_version = match has_table(&conn, "db_version")? {
+        true => db_get_version(&conn)?,
+        // Tables without version information are not something we created
+        false if has_tables(&conn)? => {
+            return Err(anyhow!("Database has tables but no version information"));
+        }
+        false => 0,
     };
 
-    scripts::upgrade_from(idx as usize, &conn).expect("SQLITE Upgrade failed.");
+    let expected = db_expected_version();
+    if db_version > expected {
+        return Err(anyhow!(
+            "Database schema version {} is newer than this version of Tubarr supports ({}). \
+             Upgrade Tubarr or restore a backup.",
+            db_version,
+            expected
+        ));
+    }
+
+    if db_version > 0 && db_version < expected {
+        if let Some(path) = conn.path().filter(|path| !path.is_empty()) {
+            let backup = PathBuf::from(format!("{}.pre-migration-v{}", path, db_version));
+            info!("Backing up database to {} before upgrade", backup.display());
+            backup_to(&conn, &backup).context("Backup before database upgrade failed")?;
+        }
+    }
+
+    scripts::upgrade_from(db_version as usize, &conn)
+}
+
+fn has_table(conn: &Connection, name: &str) -> Result<bool> {
+    let exists = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        params![name],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
+}
 
-    Ok(())
+fn has_tables(conn: &Connection) -> Result<bool> {
+    let exists = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table')",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(exists)
 }
 
-fn db_get_version(conn: &PooledConnection<SqliteConnectionManager>) -> Result<u32> {
+pub fn db_get_version(conn: &Connection) -> Result<u32> {
     // SQL query to fetch the latest version number by date
     let mut stmt = conn
         .prepare("SELECT version_number FROM db_version ORDER BY version_number DESC LIMIT 1")
@@ -53,3 +158,16 @@ fn db_get_version(conn: &PooledConnection<SqliteConnectionManager>) -> Result<u3
 
     Ok(version_number)
 }
+
+/// Schema version this binary upgrades the database to.
+pub fn db_expected_version() -> u32 {
+    scripts::upgrades_as_list().len() as u32
+}
+
+/// In-memory database with the current schema.
+#[cfg(test)]
+pub fn test_connection() -> Connection {
+    let conn = Connection::open_in_memory().expect("Failed to open in-memory database");
+    scripts::upgrade_from(0, &conn).expect("Failed to create schema");
+    conn
+}
@@ -0,0 +1,85 @@
+//! Offset pagination and sorting for the list queries.
+
+use serde::Serialize;
+use utoipa::ToSchema;
+
+pub const PAGE_DEFAULT_LIMIT: u32 = 100;
+pub const PAGE_MAX_LIMIT: u32 = 1000;
+
+/// Sort key as given in the API, and the SQL expression it sorts by.
+pub type SortColumn = (&'static str, &'static str);
+
+#[derive(Debug, Clone, Copy)]
+pub struct PageRequest {
+    pub offset: u32,
+    pub limit: u32,
+    /// SQL expression to sort by, from one of the `*_SORT_COLUMNS` lists.
+    pub sort: &'static str,
+    pub descending: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Rows matching the filters, on all pages.
+    pub total: u64,
+    pub offset: u32,
+    pub limit: u32,
+}
+
+impl PageRequest {
+    /// First page sorted by the first of the columns.
+    pub fn first(columns: &[SortColumn]) -> Self {
+        PageRequest {
+            offset: 0,
+            limit: PAGE_DEFAULT_LIMIT,
+            sort: columns[0].1,
+            descending: false,
+        }
+    }
+
+    /// Page with nothing on it.
+    pub fn empty<T>(&self) -> Page<T> {
+        self.page(Vec::new(), 0)
+    }
+
+    /// ORDER BY, LIMIT and OFFSET clauses. Row ID breaks ties, so that pages don't overlap.
+    pub(super) fn sql(&self) -> String {
+        let order = if self.descending { "DESC" } else { "ASC" };
+        format!(
+            "ORDER BY {sort} {order}, id {order} LIMIT {limit} OFFSET {offset}",
+            sort = self.sort,
+            order = order,
+            limit = self.limit.clamp(1, PAGE_MAX_LIMIT),
+            offset = self.offset,
+        )
+    }
+
+    pub(super) fn page<T>(&self, items: Vec<T>, total: u64) -> Page<T> {
+        Page {
+            items,
+            total,
+            offset: self.offset,
+            limit: self.limit.clamp(1, PAGE_MAX_LIMIT),
+        }
+    }
+}
+
+impl<T> Page<T> {
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            total: self.total,
+            offset: self.offset,
+            limit: self.limit,
+        }
+    }
+}
+
+/// SQL expression for the sort key, if it's one of the columns.
+pub fn sort_column(columns: &[SortColumn], name: &str) -> Option<&'static str> {
+    columns
+        .iter()
+        .find(|(key, _)| *key == name)
+        .map(|(_, expression)| *expression)
+}
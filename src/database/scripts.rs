@@ -1,12 +1,11 @@
-use anyhow::{Context, Ok, Result};
-use r2d2::PooledConnection;
-use r2d2_sqlite::SqliteConnectionManager;
+use anyhow::{anyhow, Context, Ok, Result};
 use regex::Regex;
-use rusqlite::params;
+use rusqlite::{params, Connection};
 use tracing::{debug, error, event, info, info_span, span, trace, warn, Level};
 
-/// Apply upgrades from specific index
-pub fn upgrade_from(idx: usize, conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+/// Apply upgrades from specific index. Each upgrade and its version row are committed in
+/// one transaction, so a failed upgrade leaves the database at the previous version.
+pub fn upgrade_from(idx: usize, conn: &Connection) -> Result<()> {
     // Get the list of upgrade functions
     let upgrades = upgrades_as_list();
 
@@ -33,18 +32,29 @@ pub fn upgrade_from(idx: usize, conn: &PooledConnection<SqliteConnectionManager>
         );
         let _enter = span.enter();
         info!("DB Upgrade {}/{} starting...", i + 1, upgrades.len());
-        upgrade(conn)?; // Apply the upgrade
+        let tx = conn.unchecked_transaction()?;
+        upgrade(&tx).with_context(|| format!("DB upgrade {} failed, rolled back", i + 1))?;
+
+        // Every upgrade must record its own version
+        let version: u32 =
+            tx.query_row("SELECT MAX(version_number) FROM db_version", [], |row| {
+                row.get(0)
+            })?;
+        if version as usize != i + 1 {
+            return Err(anyhow!(
+                "DB upgrade {} recorded version {}, rolled back",
+                i + 1,
+                version
+            ));
+        }
+        tx.commit()?;
         info!("DB Upgrade {}/{} completed.", i + 1, upgrades.len());
     }
 
     Ok(())
 }
 
-fn insert_version(
-    ver: usize,
-    desc: &str,
-    conn: &PooledConnection<SqliteConnectionManager>,
-) -> Result<()> {
+fn insert_version(ver: usize, desc: &str, conn: &Connection) -> Result<()> {
     // Insert the first version (e.g., version 1)
     conn.execute(
         "INSERT INTO db_version (version_number, description, date) VALUES (?1, ?2, datetime('now'))",
@@ -55,7 +65,7 @@ fn insert_version(
 }
 
 /// Return a static list of upgrade functions
-pub fn upgrades_as_list() -> Vec<fn(&PooledConnection<SqliteConnectionManager>) -> Result<()>> {
+pub fn upgrades_as_list() -> Vec<fn(&Connection) -> Result<()>> {
     vec![
         upgrade_1_db_versions,
         upgrade_2_users_tables,
@@ -67,11 +77,25 @@ pub fn upgrades_as_list() -> Vec<fn(&PooledConnection<SqliteConnectionManager>)
         upgrade_8_tasks_persistent,
         upgrade_9_channel_fetch_bg,
         upgrade_10_fix_channel_names,
+        upgrade_11_tasks_persistent_scheduling,
+        upgrade_12_notification_targets,
+        upgrade_13_media_servers,
+        upgrade_14_tasks_persistent_last_success,
+        upgrade_15_sessions,
+        upgrade_16_api_keys,
+        upgrade_17_roles_and_requests,
+        upgrade_18_external_auth,
+        upgrade_19_db_backup,
+        upgrade_20_videos_search,
+        upgrade_21_tasks_last_error,
+        upgrade_22_videos_files,
+        upgrade_23_config_generation,
+        upgrade_24_api_key_v1_endpoints,
     ]
 }
 
 /// Upgrade: Create db_version table and insert initial version
-pub fn upgrade_1_db_versions(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_1_db_versions(conn: &Connection) -> Result<()> {
     // Create the db_version table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS db_version (
@@ -90,7 +114,7 @@ pub fn upgrade_1_db_versions(conn: &PooledConnection<SqliteConnectionManager>) -
 
 /// Upgrade: Create users and users_local tables
 /// User IDs are 16-byte BLOBs of UUID-v4
-pub fn upgrade_2_users_tables(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_2_users_tables(conn: &Connection) -> Result<()> {
     // Create users table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS users (
@@ -149,7 +173,7 @@ pub fn upgrade_2_users_tables(conn: &PooledConnection<SqliteConnectionManager>)
 }
 
 /// Upgrade: Create app_configuration table to store key-value pairs
-pub fn upgrade_3_app_configuration(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_3_app_configuration(conn: &Connection) -> Result<()> {
     // Create app_configuration table
     conn.execute(
         "CREATE TABLE IF NOT EXISTS app_configuration (
@@ -179,7 +203,7 @@ pub fn upgrade_3_app_configuration(conn: &PooledConnection<SqliteConnectionManag
 /// When ever task_state is updated, updated_at must be updated as well.
 /// Separate background tasks will clean old tasks and restart stuck tasks based on task_state and updated_at.
 /// Exceeding retry-limit will cause the task to be set "FAIL".
-pub fn upgrade_4_tasks(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_4_tasks(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS tasks (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -201,7 +225,7 @@ pub fn upgrade_4_tasks(conn: &PooledConnection<SqliteConnectionManager>) -> Resu
 
 /// Upgrade: Create channels table for tracking known channels
 /// Only youtube is supported at this point.
-pub fn upgrade_5_channels(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_5_channels(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS channels (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -227,7 +251,7 @@ pub fn upgrade_5_channels(conn: &PooledConnection<SqliteConnectionManager>) -> R
 /// estimated release_dates. Then separate background process should
 /// get more precise dates for those. Aggregation into seasons will be
 /// by release_date, or estimate if that's null. Season is just release year.
-pub fn upgrade_6_videos(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_6_videos(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS videos (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -254,9 +278,7 @@ pub fn upgrade_6_videos(conn: &PooledConnection<SqliteConnectionManager>) -> Res
 }
 
 /// Upgrade: Add channel_name_normalized column to channels table for case-insensitive matching
-pub fn upgrade_7_channels_normalized_name(
-    conn: &PooledConnection<SqliteConnectionManager>,
-) -> Result<()> {
+pub fn upgrade_7_channels_normalized_name(conn: &Connection) -> Result<()> {
     // Add channel_name_normalized column
     conn.execute(
         "ALTER TABLE channels ADD COLUMN channel_name_normalized TEXT;",
@@ -294,7 +316,7 @@ pub fn upgrade_7_channels_normalized_name(
 }
 
 /// Upgrade: Create persistent tasks table for timed tasks
-pub fn upgrade_8_tasks_persistent(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_8_tasks_persistent(conn: &Connection) -> Result<()> {
     conn.execute(
         "CREATE TABLE IF NOT EXISTS tasks_persistent (
             id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -316,7 +338,7 @@ pub fn upgrade_8_tasks_persistent(conn: &PooledConnection<SqliteConnectionManage
 }
 
 // Upgrade: Add background task for fetching new videos for channels
-pub fn upgrade_9_channel_fetch_bg(conn: &PooledConnection<SqliteConnectionManager>) -> Result<()> {
+pub fn upgrade_9_channel_fetch_bg(conn: &Connection) -> Result<()> {
     let insert_job = "INSERT INTO tasks_persistent (task_name, delay_sec) VALUES (?1, ?2)";
     conn.execute(insert_job, params!["BG-CHANNEL-FETCH", 28800])?; // Every 8 hours
 
@@ -327,9 +349,7 @@ pub fn upgrade_9_channel_fetch_bg(conn: &PooledConnection<SqliteConnectionManage
 
 /// Upgrade: Fix channel_name normalization, remove triggers.
 // Based on testing I just can not make these triggers work. So it's up to the caller to insert normalized name as well.
-pub fn upgrade_10_fix_channel_names(
-    conn: &PooledConnection<SqliteConnectionManager>,
-) -> Result<()> {
+pub fn upgrade_10_fix_channel_names(conn: &Connection) -> Result<()> {
     // Re-normalize existing data using updated normalization logic
     let mut stmt = conn
         .prepare("SELECT id, channel_name FROM channels;")
@@ -375,6 +395,430 @@ pub fn upgrade_10_fix_channel_names(
     Ok(())
 }
 
+/// Upgrade: Add scheduling and run bookkeeping columns to tasks_persistent.
+/// If cron is set, it is used instead of delay_sec. run_now triggers the task on next loop.
+pub fn upgrade_11_tasks_persistent_scheduling(conn: &Connection) -> Result<()> {
+    let columns = [
+        "enabled INTEGER NOT NULL DEFAULT 1",
+        "cron TEXT",
+        "run_now INTEGER NOT NULL DEFAULT 0",
+        "last_duration_ms INTEGER",
+        "last_result TEXT", /* OK, ERR */
+        "last_error TEXT",
+    ];
+    for column in columns {
+        conn.execute(
+            &format!("ALTER TABLE tasks_persistent ADD COLUMN {};", column),
+            [],
+        )
+        .with_context(|| format!("Failed to add tasks_persistent column: {}", column))?;
+    }
+
+    // Set DB version
+    insert_version(11, "Scheduling for persistent background tasks", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Create notification_targets table for outgoing notifications
+/// Event filters are per target. token is used by presets which require authentication.
+pub fn upgrade_12_notification_targets(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS notification_targets (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL, /* WEBHOOK, DISCORD, SLACK, NTFY, GOTIFY */
+            url TEXT NOT NULL,
+            token TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            on_download_done INTEGER NOT NULL DEFAULT 1,
+            on_download_fail INTEGER NOT NULL DEFAULT 1,
+            on_new_video INTEGER NOT NULL DEFAULT 0,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )
+    .context("Failed to create notification_targets table")?;
+
+    // Set DB version
+    insert_version(12, "Create notification targets", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Create media_servers table for library refresh integrations
+/// path_remote is the path_media folder as seen by the media server, if it differs.
+/// library_id is needed by Plex to scope the refresh to a folder.
+pub fn upgrade_13_media_servers(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS media_servers (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            name TEXT NOT NULL,
+            kind TEXT NOT NULL, /* JELLYFIN, EMBY, PLEX */
+            url TEXT NOT NULL,
+            token TEXT NOT NULL,
+            library_id TEXT,
+            path_remote TEXT,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )",
+        [],
+    )
+    .context("Failed to create media_servers table")?;
+
+    // Set DB version
+    insert_version(13, "Create media servers", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Record last successful run of persistent tasks, last_exec is the last attempt.
+pub fn upgrade_14_tasks_persistent_last_success(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "ALTER TABLE tasks_persistent ADD COLUMN last_success DATETIME;",
+        [],
+    )
+    .context("Failed to add last_success column")?;
+
+    // Set DB version
+    insert_version(14, "Persistent task last success", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Create sessions table for web UI logins
+/// Only SHA-256 hashes of the session tokens are stored.
+pub fn upgrade_15_sessions(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS sessions (
+            token_hash TEXT PRIMARY KEY NOT NULL,
+            user_id BLOB NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            expires_at DATETIME NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )
+    .context("Failed to create sessions table")?;
+
+    // Set DB version
+    insert_version(15, "Login sessions", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Create api_keys table
+/// Only SHA-256 hashes of the keys are stored, prefix is kept for telling keys apart.
+/// Scope is FULL, READ or ENDPOINTS. For ENDPOINTS, allowed routes are listed in endpoints,
+/// one "METHOD /api/path" per line.
+pub fn upgrade_16_api_keys(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS api_keys (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id BLOB NOT NULL,
+            name TEXT NOT NULL,
+            key_prefix TEXT NOT NULL,
+            key_hash TEXT NOT NULL UNIQUE,
+            scope TEXT NOT NULL DEFAULT 'FULL',
+            endpoints TEXT,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            last_used DATETIME,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )
+    .context("Failed to create api_keys table")?;
+
+    // Set DB version
+    insert_version(16, "API keys", conn)?;
+    Ok(())
+}
+
+/// Upgrade: User roles, and video requests waiting for approval
+/// Role is ADMIN or REQUESTER. Protected users (the initial admin) become admins.
+/// Request state is PENDING, APPROVED or DENIED. Approved requests point to their task.
+pub fn upgrade_17_roles_and_requests(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "ALTER TABLE users ADD COLUMN role TEXT NOT NULL DEFAULT 'REQUESTER';",
+        [],
+    )
+    .context("Failed to add role column")?;
+
+    conn.execute("UPDATE users SET role = 'ADMIN' WHERE protected = 1;", [])
+        .context("Failed to set admin role")?;
+
+    conn.execute("ALTER TABLE tasks ADD COLUMN requested_by BLOB;", [])
+        .context("Failed to add requested_by column")?;
+
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS video_requests (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            url TEXT NOT NULL,
+            user_id BLOB NOT NULL,
+            state TEXT NOT NULL DEFAULT 'PENDING',
+            task_id INTEGER,
+            decided_by BLOB,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )
+    .context("Failed to create video_requests table")?;
+
+    // Set DB version
+    insert_version(17, "User roles and video requests", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Reverse proxy header and OpenID Connect login
+/// OIDC users are mapped by issuer and subject. Both kinds of users get users_local row
+/// without usable password, so they show up by username like local users.
+pub fn upgrade_18_external_auth(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS users_oidc (
+            issuer TEXT NOT NULL,
+            subject TEXT NOT NULL,
+            user_id BLOB NOT NULL,
+            created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+            PRIMARY KEY (issuer, subject),
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )",
+        [],
+    )
+    .context("Failed to create users_oidc table")?;
+
+    // Empty values mean the login method is disabled
+    let insert_kv = "INSERT OR IGNORE INTO app_configuration (key, value) VALUES (?1, ?2)";
+    conn.execute(insert_kv, params!["auth_proxy_header", ""])?;
+    conn.execute(insert_kv, params!["auth_proxy_trusted", ""])?;
+    conn.execute(insert_kv, params!["auth_default_role", "REQUESTER"])?;
+    conn.execute(insert_kv, params!["oidc_issuer", ""])?;
+    conn.execute(insert_kv, params!["oidc_client_id", ""])?;
+    conn.execute(insert_kv, params!["oidc_client_secret", ""])?;
+    conn.execute(insert_kv, params!["oidc_redirect_url", ""])?;
+
+    // Set DB version
+    insert_version(18, "External login methods", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Add background task for database backups.
+/// Backups are written only when backup_path is set. backup_keep newest ones are kept.
+pub fn upgrade_19_db_backup(conn: &Connection) -> Result<()> {
+    let insert_job = "INSERT INTO tasks_persistent (task_name, delay_sec) VALUES (?1, ?2)";
+    conn.execute(insert_job, params!["DB-BACKUP", 86400])?; // Every day
+
+    let insert_kv = "INSERT OR IGNORE INTO app_configuration (key, value) VALUES (?1, ?2)";
+    conn.execute(insert_kv, params!["backup_path", ""])?;
+    conn.execute(insert_kv, params!["backup_keep", "7"])?;
+
+    // Set DB version
+    insert_version(19, "Persistent task: Database backup", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Add description and tags to videos, and full-text search index over them.
+/// The index is kept in sync by triggers, so every insert, update and delete is covered.
+pub fn upgrade_20_videos_search(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE videos ADD COLUMN description TEXT", [])
+        .context("Failed to add description column")?;
+    conn.execute("ALTER TABLE videos ADD COLUMN tags TEXT", [])
+        .context("Failed to add tags column")?;
+
+    conn.execute_batch(
+        "CREATE VIRTUAL TABLE videos_fts USING fts5(
+            name, description, tags,
+            content = 'videos', content_rowid = 'id',
+            tokenize = 'unicode61 remove_diacritics 2'
+        );
+
+        CREATE TRIGGER videos_fts_insert AFTER INSERT ON videos BEGIN
+            INSERT INTO videos_fts (rowid, name, description, tags)
+                VALUES (new.id, new.name, new.description, new.tags);
+        END;
+
+        CREATE TRIGGER videos_fts_delete AFTER DELETE ON videos BEGIN
+            INSERT INTO videos_fts (videos_fts, rowid, name, description, tags)
+                VALUES ('delete', old.id, old.name, old.description, old.tags);
+        END;
+
+        CREATE TRIGGER videos_fts_update AFTER UPDATE ON videos BEGIN
+            INSERT INTO videos_fts (videos_fts, rowid, name, description, tags)
+                VALUES ('delete', old.id, old.name, old.description, old.tags);
+            INSERT INTO videos_fts (rowid, name, description, tags)
+                VALUES (new.id, new.name, new.description, new.tags);
+        END;
+
+        INSERT INTO videos_fts (videos_fts) VALUES ('rebuild');",
+    )
+    .context("Failed to create videos search index")?;
+
+    // Set DB version
+    insert_version(20, "Full-text search for videos", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Add last_error to tasks, so failed tasks show why they failed.
+pub fn upgrade_21_tasks_last_error(conn: &Connection) -> Result<()> {
+    conn.execute("ALTER TABLE tasks ADD COLUMN last_error TEXT", [])
+        .context("Failed to add tasks column: last_error")?;
+
+    // Set DB version
+    insert_version(21, "Error details for tasks", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Add downloaded file, its codecs and watched flag to videos.
+/// Size and subtitles are read from the files, so they stay right when files are moved away.
+pub fn upgrade_22_videos_files(conn: &Connection) -> Result<()> {
+    let columns = [
+        "file_path TEXT",
+        "video_codec TEXT",
+        "audio_codec TEXT",
+        "is_watched INTEGER NOT NULL DEFAULT 0",
+    ];
+    for column in columns {
+        conn.execute(&format!("ALTER TABLE videos ADD COLUMN {};", column), [])
+            .with_context(|| format!("Failed to add videos column: {}", column))?;
+    }
+
+    // Set DB version
+    insert_version(22, "Downloaded files of videos", conn)?;
+    Ok(())
+}
+
+/// Upgrade: Counter of configuration changes, so that the server notices changes saved by
+/// other processes, like 'tubarr config set'.
+pub fn upgrade_23_config_generation(conn: &Connection) -> Result<()> {
+    conn.execute(
+        "CREATE TABLE config_generation (generation INTEGER NOT NULL)",
+        [],
+    )
+    .context("Failed to create config_generation table")?;
+    conn.execute("INSERT INTO config_generation (generation) VALUES (0)", [])?;
+
+    // Set DB version
+    insert_version(23, "Configuration generation", conn)?;
+    Ok(())
+}
+
+/// Routes of the removed unversioned API, and the v1 routes replacing them.
+const LEGACY_ENDPOINTS: [(&str, &str); 37] = [
+    ("POST /api/task", "POST /api/v1/tasks"),
+    ("GET /api/tasks", "GET /api/v1/tasks"),
+    ("POST /api/channel", "POST /api/v1/channels"),
+    (
+        "POST /api/channel/fetch",
+        "POST /api/v1/channels/<id>/fetch",
+    ),
+    ("GET /api/channels", "GET /api/v1/channels"),
+    (
+        "POST /api/channels/import/preview",
+        "POST /api/v1/channels/import/preview",
+    ),
+    ("POST /api/channels/import", "POST /api/v1/channels/import"),
+    (
+        "GET /api/channels/export.opml",
+        "GET /api/v1/channels/export.opml",
+    ),
+    (
+        "GET /api/videos/<domain>/<channel>",
+        "GET /api/v1/channels/<id>/videos",
+    ),
+    ("POST /api/video", "POST /api/v1/videos"),
+    ("GET /api/search", "GET /api/v1/search"),
+    ("GET /api/requests", "GET /api/v1/requests"),
+    (
+        "POST /api/request/<id>/approve",
+        "POST /api/v1/requests/<id>/approve",
+    ),
+    (
+        "POST /api/request/<id>/deny",
+        "POST /api/v1/requests/<id>/deny",
+    ),
+    ("GET /api/config", "GET /api/v1/config"),
+    ("PUT /api/config", "PUT /api/v1/config"),
+    ("GET /api/scheduler", "GET /api/v1/scheduler"),
+    ("POST /api/scheduler/<id>", "PATCH /api/v1/scheduler/<id>"),
+    (
+        "POST /api/scheduler/<id>/run",
+        "POST /api/v1/scheduler/<id>/run",
+    ),
+    ("GET /api/notifications", "GET /api/v1/notifications"),
+    ("POST /api/notification", "POST /api/v1/notifications"),
+    (
+        "POST /api/notification/<id>",
+        "PUT /api/v1/notifications/<id>",
+    ),
+    (
+        "DELETE /api/notification/<id>",
+        "DELETE /api/v1/notifications/<id>",
+    ),
+    (
+        "POST /api/notification/<id>/test",
+        "POST /api/v1/notifications/<id>/test",
+    ),
+    ("GET /api/mediaservers", "GET /api/v1/mediaservers"),
+    ("POST /api/mediaserver", "POST /api/v1/mediaservers"),
+    (
+        "POST /api/mediaserver/<id>",
+        "PUT /api/v1/mediaservers/<id>",
+    ),
+    (
+        "DELETE /api/mediaserver/<id>",
+        "DELETE /api/v1/mediaservers/<id>",
+    ),
+    (
+        "POST /api/mediaserver/<id>/test",
+        "POST /api/v1/mediaservers/<id>/test",
+    ),
+    ("GET /api/user", "GET /api/v1/user"),
+    ("GET /api/users", "GET /api/v1/users"),
+    ("POST /api/user", "POST /api/v1/users"),
+    ("POST /api/user/<id>", "PUT /api/v1/users/<id>"),
+    ("DELETE /api/user/<id>", "DELETE /api/v1/users/<id>"),
+    ("GET /api/apikeys", "GET /api/v1/apikeys"),
+    ("POST /api/apikey", "POST /api/v1/apikeys"),
+    ("DELETE /api/apikey/<id>", "DELETE /api/v1/apikeys/<id>"),
+];
+
+/// Upgrade: Point endpoint limited API keys to the v1 routes
+/// The unversioned duplicates of the v1 routes were removed. Endpoints that have no v1
+/// counterpart are kept as they are.
+pub fn upgrade_24_api_key_v1_endpoints(conn: &Connection) -> Result<()> {
+    let mut stmt = conn
+        .prepare("SELECT id, endpoints FROM api_keys WHERE endpoints IS NOT NULL;")
+        .context("Failed to query API key endpoints")?;
+    let rows = stmt
+        .query_map([], |row| {
+            let id: i64 = row.get(0)?;
+            let endpoints: String = row.get(1)?;
+            anyhow::Result::Ok((id, endpoints))
+        })
+        .context("Failed to query API key endpoints")?;
+
+    for row in rows {
+        let (id, endpoints) = row?;
+        let endpoints = endpoints
+            .lines()
+            .map(|endpoint| {
+                LEGACY_ENDPOINTS
+                    .iter()
+                    .find(|(legacy, _)| *legacy == endpoint)
+                    .map_or(endpoint, |(_, v1)| v1)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        conn.execute(
+            "UPDATE api_keys SET endpoints = ?1 WHERE id = ?2;",
+            params![endpoints, id],
+        )
+        .context("Failed to update API key endpoints")?;
+    }
+
+    // Set DB version
+    insert_version(24, "API key endpoints to v1", conn)?;
+    Ok(())
+}
+
 fn normalize_channel_name(channel_name: &str) -> String {
     let re = Regex::new(r#"['\"]"#).unwrap(); // This works for removing quotes
 
@@ -0,0 +1,202 @@
+//! Full-text search over videos (videos_fts table). The index is kept in sync with the
+//! videos table by triggers, see `upgrade_20_videos_search`.
+
+use anyhow::{anyhow, Result};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::Video;
+
+/// Most results returned by one search.
+pub const SEARCH_MAX_LIMIT: u32 = 200;
+
+#[derive(Debug, Default)]
+pub struct SearchFilter {
+    /// Words to look for in titles, descriptions and tags. Every word must match, as a prefix.
+    pub query: String,
+    /// Row ID of the channel.
+    pub channel_id: Option<i64>,
+    /// Released on or after.
+    pub from: Option<NaiveDate>,
+    /// Released on or before.
+    pub to: Option<NaiveDate>,
+    pub downloaded: Option<bool>,
+    pub limit: u32,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub video: Video,
+    pub channel_name: Option<String>,
+    pub channel_name_normalized: Option<String>,
+}
+
+/// Videos matching the filter, best matches first.
+pub fn search_videos(conn: &Connection, filter: &SearchFilter) -> Result<Vec<SearchResult>> {
+    let query = fts_query(&filter.query).ok_or_else(|| anyhow!("Search query is empty"))?;
+
+    let mut stmt = conn.prepare(
+        "SELECT v.id, v.channel_id, v.domain, v.url, v.name, v.video_id, v.is_requested,
+                v.is_downloaded, v.release_date, v.release_date_estimate, v.updated_at,
+                v.is_watched, c.channel_name, c.channel_name_normalized
+            FROM videos_fts
+            JOIN videos v ON v.id = videos_fts.rowid
+            LEFT JOIN channels c ON c.id = v.channel_id
+            WHERE videos_fts MATCH ?1
+                AND (?2 IS NULL OR v.channel_id = ?2)
+                AND (?3 IS NULL OR COALESCE(v.release_date, v.release_date_estimate) >= ?3)
+                AND (?4 IS NULL OR COALESCE(v.release_date, v.release_date_estimate) < date(?4, '+1 day'))
+                AND (?5 IS NULL OR v.is_downloaded = ?5)
+            ORDER BY videos_fts.rank
+            LIMIT ?6",
+    )?;
+    let results = stmt
+        .query_map(
+            params![
+                query,
+                filter.channel_id,
+                filter.from,
+                filter.to,
+                filter.downloaded,
+                filter.limit.clamp(1, SEARCH_MAX_LIMIT),
+            ],
+            |row| {
+                Ok(SearchResult {
+                    video: Video::from_row(row)?,
+                    channel_name: row.get(12)?,
+                    channel_name_normalized: row.get(13)?,
+                })
+            },
+        )?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(results)
+}
+
+/// User input as FTS5 query: every word quoted as prefix term, so that the FTS5 query
+/// syntax (AND, NEAR, column filters) is not available from the search box.
+fn fts_query(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|word| word.replace('"', ""))
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{}\"*", word))
+        .collect();
+    match terms.is_empty() {
+        true => None,
+        false => Some(terms.join(" ")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{
+        delete_channel, insert_channel, save_downloaded_video, test_connection, upsert_video,
+        NewVideo,
+    };
+
+    fn add_video(conn: &Connection, channel_id: Option<i64>, video_id: &str, name: &str) {
+        let url = format!("https://y/{}", video_id);
+        upsert_video(
+            conn,
+            &NewVideo {
+                channel_id,
+                domain: "youtube.com",
+                url: &url,
+                name,
+                video_id,
+                release_date: None,
+                release_date_estimate: NaiveDate::from_ymd_opt(2024, 5, 17),
+            },
+        )
+        .unwrap();
+    }
+
+    fn search(conn: &Connection, query: &str) -> Vec<String> {
+        let filter = SearchFilter {
+            query: query.to_string(),
+            limit: 50,
+            ..Default::default()
+        };
+        search_videos(conn, &filter)
+            .unwrap()
+            .into_iter()
+            .map(|result| result.video.video_id)
+            .collect()
+    }
+
+    #[test]
+    fn matches_word_prefixes_and_ignores_diacritics() {
+        let conn = test_connection();
+        add_video(&conn, None, "v1", "Building a Café Racer");
+        add_video(&conn, None, "v2", "Cafeteria tour");
+        add_video(&conn, None, "v3", "Something else");
+
+        assert_eq!(search(&conn, "build racer"), ["v1"]);
+        let mut cafe = search(&conn, "cafe");
+        cafe.sort();
+        assert_eq!(cafe, ["v1", "v2"]);
+        assert!(search(&conn, "\"racer\" OR NEAR(").is_empty());
+        assert!(search_videos(&conn, &SearchFilter::default()).is_err());
+    }
+
+    #[test]
+    fn index_follows_video_changes() {
+        let conn = test_connection();
+        let channel =
+            insert_channel(&conn, "youtube.com", "youtube.com/channel/UC1", "UC1", "A").unwrap();
+        add_video(&conn, Some(channel), "v1", "Placeholder title");
+        assert_eq!(search(&conn, "placeholder"), ["v1"]);
+
+        save_downloaded_video(
+            &conn,
+            &NewVideo {
+                channel_id: Some(channel),
+                domain: "youtube.com",
+                url: "https://y/v1",
+                name: "Real title",
+                video_id: "v1",
+                release_date: NaiveDate::from_ymd_opt(2024, 5, 18),
+                release_date_estimate: None,
+            },
+        )
+        .unwrap();
+        assert!(search(&conn, "placeholder").is_empty());
+        assert_eq!(search(&conn, "real"), ["v1"]);
+
+        delete_channel(&conn, channel).unwrap();
+        assert!(search(&conn, "real").is_empty());
+    }
+
+    #[test]
+    fn filters_by_channel_date_and_state() {
+        let conn = test_connection();
+        let channel =
+            insert_channel(&conn, "youtube.com", "youtube.com/channel/UC1", "UC1", "A").unwrap();
+        add_video(&conn, Some(channel), "v1", "Episode one");
+        add_video(&conn, None, "v2", "Episode two");
+
+        let mut filter = SearchFilter {
+            query: "episode".to_string(),
+            channel_id: Some(channel),
+            limit: 50,
+            ..Default::default()
+        };
+        let results = search_videos(&conn, &filter).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].channel_name.as_deref(), Some("A"));
+
+        filter.channel_id = None;
+        filter.to = NaiveDate::from_ymd_opt(2024, 5, 17);
+        assert_eq!(search_videos(&conn, &filter).unwrap().len(), 2);
+        filter.from = NaiveDate::from_ymd_opt(2024, 5, 18);
+        assert!(search_videos(&conn, &filter).unwrap().is_empty());
+
+        filter.from = None;
+        filter.downloaded = Some(true);
+        assert!(search_videos(&conn, &filter).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,479 @@
+//! One-off tasks (tasks table), picked up by the taskrunner.
+//!
+//! Task states: WAIT, WIP, ERR, DONE, FAIL. Cancelled tasks are set FAIL, so they get
+//! cleaned up like any other finished task.
+
+use anyhow::{Context, Result};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use url::Url;
+use utoipa::ToSchema;
+
+use super::{Page, PageRequest, SortColumn};
+use crate::events;
+
+pub const TASK_SORT_COLUMNS: [SortColumn; 5] = [
+    ("id", "id"),
+    ("task_type", "task_type"),
+    ("task_state", "task_state"),
+    ("created_at", "created_at"),
+    ("updated_at", "updated_at"),
+];
+
+#[derive(Debug, Default)]
+pub struct TaskFilter {
+    pub task_state: Option<String>,
+    pub task_type: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct Task {
+    pub id: i64,
+    pub task_type: String,
+    pub task_data: String,
+    pub task_state: String,
+    pub retry_count: i32,
+    pub created_at: String,
+    pub updated_at: String,
+    /// Why the task failed last time. Cleared when the task is retried.
+    pub last_error: Option<String>,
+}
+
+impl Task {
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Task {
+            id: row.get(0)?,
+            task_type: row.get(1)?,
+            task_data: row.get(2)?,
+            task_state: row.get(3)?,
+            retry_count: row.get(4)?,
+            created_at: row.get(5)?,
+            updated_at: row.get(6)?,
+            last_error: row.get(7)?,
+        })
+    }
+}
+
+/// Create new task in WAIT state. Returns the task ID.
+pub fn enqueue_task<T: Serialize>(
+    conn: &Connection,
+    task_type: &str,
+    task_data: &T,
+    requested_by: &[u8],
+) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO tasks (task_type, task_data, task_state, requested_by) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            task_type,
+            serde_json::to_string(task_data)?,
+            "WAIT",
+            requested_by
+        ],
+    )
+    .context("Could not insert task into db")?;
+    let task_id = conn.last_insert_rowid();
+
+    events::publish(events::Event::TaskCreated {
+        task_id,
+        task_type: task_type.to_string(),
+    });
+
+    Ok(task_id)
+}
+
+/// Create task with the URL as its only data, like VIDEO-DOWNLOAD and CHANNEL-ADD.
+pub fn enqueue_url_task(
+    conn: &Connection,
+    task_type: &str,
+    url: &str,
+    requested_by: &[u8],
+) -> Result<i64> {
+    let mut data = HashMap::with_capacity(1);
+    data.insert("url", url);
+    enqueue_task(conn, task_type, &data, requested_by)
+}
+
+/// Task of the type for the URL that is waiting or running, if there is one.
+pub fn find_pending_url_task(conn: &Connection, task_type: &str, url: &str) -> Result<Option<i64>> {
+    let task_id = conn
+        .query_row(
+            "SELECT id FROM tasks
+                WHERE task_type = ?1 AND task_state IN ('WAIT', 'WIP')
+                AND json_extract(task_data, '$.url') = ?2
+                ORDER BY id LIMIT 1",
+            params![task_type, url],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(task_id)
+}
+
+/// List tasks, oldest first. Optionally only the ones in the state.
+pub fn get_tasks(conn: &Connection, state: Option<&str>) -> Result<Vec<Task>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, task_type, task_data, task_state, retry_count, created_at, updated_at,
+                last_error
+            FROM tasks
+            WHERE ?1 IS NULL OR task_state = ?1
+            ORDER BY id",
+    )?;
+
+    let tasks = stmt
+        .query_map(params![state], Task::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(tasks)
+}
+
+/// One page of tasks matching the filter.
+pub fn list_tasks(
+    conn: &Connection,
+    filter: &TaskFilter,
+    page: &PageRequest,
+) -> Result<Page<Task>> {
+    let condition = "(?1 IS NULL OR task_state = ?1) AND (?2 IS NULL OR task_type = ?2)";
+    let filter_params = params![filter.task_state, filter.task_type];
+
+    let total = conn.query_row(
+        &format!("SELECT COUNT(*) FROM tasks WHERE {}", condition),
+        filter_params,
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, task_type, task_data, task_state, retry_count, created_at, updated_at,
+                last_error
+            FROM tasks
+            WHERE {}
+            {}",
+        condition,
+        page.sql()
+    ))?;
+    let tasks = stmt
+        .query_map(filter_params, Task::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(page.page(tasks, total))
+}
+
+/// VIDEO-DOWNLOAD tasks of the video, newest first. The URL may have been given in another
+/// form, so tasks with the video ID as a query value or path segment of their URL are
+/// included too, eg. "watch?v=<id>", "youtu.be/<id>" or "shorts/<id>".
+pub fn get_video_tasks(conn: &Connection, url: &str, video_id: &str) -> Result<Vec<Task>> {
+    // instr() narrows down the candidates, the URL is checked below
+    let mut stmt = conn.prepare(
+        "SELECT id, task_type, task_data, task_state, retry_count, created_at, updated_at,
+                last_error
+            FROM tasks
+            WHERE task_type = 'VIDEO-DOWNLOAD'
+                AND (json_extract(task_data, '$.url') = ?1
+                    OR instr(json_extract(task_data, '$.url'), ?2) > 0)
+            ORDER BY id DESC",
+    )?;
+    let tasks = stmt
+        .query_map(params![url, video_id], Task::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(tasks
+        .into_iter()
+        .filter(|task| {
+            let task_url = serde_json::from_str::<HashMap<String, String>>(&task.task_data)
+                .ok()
+                .and_then(|mut data| data.remove("url"))
+                .unwrap_or_default();
+            task_url == url || url_has_video_id(&task_url, video_id)
+        })
+        .collect())
+}
+
+/// Is the video ID a whole query value or path segment of the URL.
+fn url_has_video_id(url: &str, video_id: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let in_query = url.query_pairs().any(|(_, value)| value == video_id);
+    let in_path = url
+        .path_segments()
+        .is_some_and(|mut segments| segments.any(|segment| segment == video_id));
+    in_query || in_path
+}
+
+pub fn get_task(conn: &Connection, task_id: i64) -> Result<Option<Task>> {
+    let task = conn
+        .query_row(
+            "SELECT id, task_type, task_data, task_state, retry_count, created_at, updated_at,
+                    last_error
+                FROM tasks
+                WHERE id = ?1",
+            params![task_id],
+            Task::from_row,
+        )
+        .optional()?;
+    Ok(task)
+}
+
+/// Set task state as the taskrunner progresses. Returns false if there was no such task.
+pub fn update_task_state(conn: &Connection, task_id: i64, task_state: &str) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE tasks SET task_state = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
+            params![task_state, task_id],
+        )
+        .context("Failed to update task state")?;
+
+    if updated > 0 {
+        events::publish(events::Event::TaskState {
+            task_id,
+            task_state: task_state.to_string(),
+        });
+    }
+    Ok(updated > 0)
+}
+
+/// Set task ERR with the reason. Returns false if there was no such task.
+pub fn fail_task(conn: &Connection, task_id: i64, error: &str) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE tasks
+                SET task_state = 'ERR', last_error = ?1, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?2",
+            params![error, task_id],
+        )
+        .context("Failed to update task state")?;
+
+    if updated > 0 {
+        events::publish(events::Event::TaskState {
+            task_id,
+            task_state: "ERR".to_string(),
+        });
+    }
+    Ok(updated > 0)
+}
+
+/// Remove task that is not running. Returns false if there was no such task, or it's WIP.
+pub fn delete_task(conn: &Connection, task_id: i64) -> Result<bool> {
+    let deleted = conn
+        .execute(
+            "DELETE FROM tasks WHERE id = ?1 AND task_state != 'WIP'",
+            params![task_id],
+        )
+        .context("Failed to delete task")?;
+    Ok(deleted > 0)
+}
+
+/// Remove DONE and FAIL tasks last updated before the age. Returns how many were removed.
+pub fn delete_finished_tasks(conn: &Connection, older_than_hours: u32) -> Result<usize> {
+    let deleted = conn
+        .execute(
+            "DELETE FROM tasks
+                WHERE (task_state = 'DONE' OR task_state = 'FAIL')
+                AND updated_at <= datetime('now', ?1)",
+            params![format!("-{} hours", older_than_hours)],
+        )
+        .context("Failed to delete old tasks")?;
+    Ok(deleted)
+}
+
+/// Put failed task back to the queue with fresh retries. Returns false if there was no
+/// such ERR or FAIL task.
+pub fn retry_task(conn: &Connection, task_id: i64) -> Result<bool> {
+    set_state(conn, task_id, "WAIT", ["ERR", "FAIL"])
+}
+
+/// Cancel task that is not running yet. Returns false if there was no such WAIT or ERR task.
+pub fn cancel_task(conn: &Connection, task_id: i64) -> Result<bool> {
+    set_state(conn, task_id, "FAIL", ["WAIT", "ERR"])
+}
+
+fn set_state(
+    conn: &Connection,
+    task_id: i64,
+    new_state: &str,
+    from_states: [&str; 2],
+) -> Result<bool> {
+    let updated = conn.execute(
+        "UPDATE tasks
+            SET task_state = ?1, retry_count = 0, updated_at = CURRENT_TIMESTAMP,
+                last_error = CASE WHEN ?1 = 'WAIT' THEN NULL ELSE last_error END
+            WHERE id = ?2 AND task_state IN (?3, ?4)",
+        params![new_state, task_id, from_states[0], from_states[1]],
+    )?;
+
+    if updated > 0 {
+        events::publish(events::Event::TaskState {
+            task_id,
+            task_state: new_state.to_string(),
+        });
+    }
+    Ok(updated > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_connection;
+
+    #[test]
+    fn enqueue_and_get() {
+        let conn = test_connection();
+        let id = enqueue_url_task(&conn, "CHANNEL-ADD", "https://y/c", &[1; 16]).unwrap();
+
+        let task = get_task(&conn, id).unwrap().unwrap();
+        assert_eq!(task.task_type, "CHANNEL-ADD");
+        assert_eq!(task.task_state, "WAIT");
+        assert_eq!(task.task_data, r#"{"url":"https://y/c"}"#);
+        assert!(get_task(&conn, id + 1).unwrap().is_none());
+
+        assert_eq!(
+            find_pending_url_task(&conn, "CHANNEL-ADD", "https://y/c").unwrap(),
+            Some(id)
+        );
+        update_task_state(&conn, id, "DONE").unwrap();
+        assert!(find_pending_url_task(&conn, "CHANNEL-ADD", "https://y/c")
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn filters_by_state() {
+        let conn = test_connection();
+        let first = enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://y/1", &[1; 16]).unwrap();
+        enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://y/2", &[1; 16]).unwrap();
+        assert!(update_task_state(&conn, first, "WIP").unwrap());
+
+        assert_eq!(get_tasks(&conn, None).unwrap().len(), 2);
+        let waiting = get_tasks(&conn, Some("WAIT")).unwrap();
+        assert_eq!(waiting.len(), 1);
+        assert_ne!(waiting[0].id, first);
+        assert!(!update_task_state(&conn, 999, "WIP").unwrap());
+    }
+
+    #[test]
+    fn lists_tasks_in_pages() {
+        let conn = test_connection();
+        for i in 0..5 {
+            let url = format!("https://y/{}", i);
+            enqueue_url_task(&conn, "VIDEO-DOWNLOAD", &url, &[1; 16]).unwrap();
+        }
+        enqueue_url_task(&conn, "CHANNEL-ADD", "https://y/c", &[1; 16]).unwrap();
+
+        let filter = TaskFilter {
+            task_type: Some("VIDEO-DOWNLOAD".to_string()),
+            ..Default::default()
+        };
+        let mut page = PageRequest {
+            limit: 2,
+            descending: true,
+            ..PageRequest::first(&TASK_SORT_COLUMNS)
+        };
+        let first = list_tasks(&conn, &filter, &page).unwrap();
+        assert_eq!(first.total, 5);
+        assert_eq!(first.items.iter().map(|t| t.id).collect::<Vec<_>>(), [5, 4]);
+
+        page.offset = 4;
+        let last = list_tasks(&conn, &filter, &page).unwrap();
+        assert_eq!(last.items.iter().map(|t| t.id).collect::<Vec<_>>(), [1]);
+    }
+
+    #[test]
+    fn retry_and_cancel_only_from_allowed_states() {
+        let conn = test_connection();
+        let id = enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://y/1", &[1; 16]).unwrap();
+
+        assert!(!retry_task(&conn, id).unwrap());
+        assert!(cancel_task(&conn, id).unwrap());
+        assert_eq!(get_task(&conn, id).unwrap().unwrap().task_state, "FAIL");
+        assert!(!cancel_task(&conn, id).unwrap());
+        assert!(retry_task(&conn, id).unwrap());
+        assert_eq!(get_task(&conn, id).unwrap().unwrap().task_state, "WAIT");
+    }
+
+    #[test]
+    fn failed_task_keeps_error_until_retried() {
+        let conn = test_connection();
+        let id = enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://y/1", &[1; 16]).unwrap();
+        assert!(fail_task(&conn, id, "yt-dlp failed").unwrap());
+
+        let task = get_task(&conn, id).unwrap().unwrap();
+        assert_eq!(task.task_state, "ERR");
+        assert_eq!(task.last_error.as_deref(), Some("yt-dlp failed"));
+        assert!(cancel_task(&conn, id).unwrap());
+        assert!(get_task(&conn, id).unwrap().unwrap().last_error.is_some());
+        assert!(retry_task(&conn, id).unwrap());
+        assert!(get_task(&conn, id).unwrap().unwrap().last_error.is_none());
+    }
+
+    #[test]
+    fn finds_tasks_of_video() {
+        let conn = test_connection();
+        let url = "https://www.youtube.com/watch?v=abc";
+        let first = enqueue_url_task(&conn, "VIDEO-DOWNLOAD", url, &[1; 16]).unwrap();
+        let short =
+            enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://youtu.be/abc", &[1; 16]).unwrap();
+        enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://youtu.be/xyz", &[1; 16]).unwrap();
+        enqueue_url_task(&conn, "CHANNEL-ADD", "https://y/abc", &[1; 16]).unwrap();
+
+        let tasks = get_video_tasks(&conn, url, "abc").unwrap();
+        assert_eq!(
+            tasks.iter().map(|t| t.id).collect::<Vec<_>>(),
+            [short, first]
+        );
+    }
+
+    #[test]
+    fn running_task_is_not_deleted() {
+        let conn = test_connection();
+        let id = enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://y/1", &[1; 16]).unwrap();
+        update_task_state(&conn, id, "WIP").unwrap();
+        assert!(!delete_task(&conn, id).unwrap());
+
+        update_task_state(&conn, id, "DONE").unwrap();
+        assert!(delete_task(&conn, id).unwrap());
+        assert!(get_task(&conn, id).unwrap().is_none());
+        assert!(!delete_task(&conn, id).unwrap());
+    }
+
+    #[test]
+    fn deletes_only_old_finished_tasks() {
+        let conn = test_connection();
+        let done = enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://y/1", &[1; 16]).unwrap();
+        let waiting = enqueue_url_task(&conn, "VIDEO-DOWNLOAD", "https://y/2", &[1; 16]).unwrap();
+        update_task_state(&conn, done, "DONE").unwrap();
+        assert_eq!(delete_finished_tasks(&conn, 24).unwrap(), 0);
+
+        conn.execute(
+            "UPDATE tasks SET updated_at = datetime('now', '-2 days')",
+            [],
+        )
+        .unwrap();
+        assert_eq!(delete_finished_tasks(&conn, 24).unwrap(), 1);
+        assert!(get_task(&conn, done).unwrap().is_none());
+        assert!(get_task(&conn, waiting).unwrap().is_some());
+    }
+
+    #[test]
+    fn video_tasks_match_whole_video_id() {
+        let conn = test_connection();
+        let urls = [
+            "https://www.youtube.com/watch?v=abc",
+            "https://youtu.be/abc",
+            "https://www.youtube.com/shorts/abc?feature=share",
+            "https://www.youtube.com/watch?v=abcd",
+            "https://www.youtube.com/watch?v=xabc&t=10",
+            "https://youtu.be/abcd",
+            "https://example.com/abc-video",
+        ];
+        let ids: Vec<i64> = urls
+            .iter()
+            .map(|url| enqueue_url_task(&conn, "VIDEO-DOWNLOAD", url, &[1; 16]).unwrap())
+            .collect();
+
+        let tasks = get_video_tasks(&conn, urls[0], "abc").unwrap();
+        let found: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+        assert_eq!(found, [ids[2], ids[1], ids[0]]);
+
+        let tasks = get_video_tasks(&conn, urls[3], "abcd").unwrap();
+        let found: Vec<i64> = tasks.iter().map(|task| task.id).collect();
+        assert_eq!(found, [ids[5], ids[3]]);
+    }
+}
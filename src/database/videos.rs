@@ -0,0 +1,468 @@
+//! Known videos (videos table), discovered from channels or downloaded.
+
+use anyhow::{Context, Result};
+use chrono::NaiveDate;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use super::{Page, PageRequest, SortColumn};
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+#[schema(as = VideoRow)]
+pub struct Video {
+    pub id: i64,
+    /// Row ID of the channel, if the channel is followed.
+    pub channel_id: Option<i64>,
+    pub domain: String,
+    pub url: String,
+    pub name: String,
+    /// ID on the site.
+    pub video_id: String,
+    pub is_requested: bool,
+    pub is_downloaded: bool,
+    pub release_date: Option<String>,
+    /// Approximate date from the channel listing, when the exact one is not known.
+    pub release_date_estimate: Option<String>,
+    pub updated_at: String,
+    pub is_watched: bool,
+}
+
+/// What was saved about the video when it was downloaded.
+#[derive(Debug, Default, Serialize, ToSchema)]
+pub struct DownloadInfo {
+    /// Media file under path_media. Thumbnail and subtitles are next to it.
+    pub file_path: Option<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    pub description: Option<String>,
+    /// Comma separated.
+    pub tags: Option<String>,
+}
+
+/// Video to insert or update.
+#[derive(Debug)]
+pub struct NewVideo<'a> {
+    pub channel_id: Option<i64>,
+    pub domain: &'a str,
+    pub url: &'a str,
+    pub name: &'a str,
+    pub video_id: &'a str,
+    pub release_date: Option<NaiveDate>,
+    pub release_date_estimate: Option<NaiveDate>,
+}
+
+const COLUMNS: &str = "id, channel_id, domain, url, name, video_id, is_requested, is_downloaded,
+    release_date, release_date_estimate, updated_at, is_watched";
+
+/// Same as `Video::season`, in SQL.
+const SEASON: &str = "CASE
+        WHEN substr(COALESCE(release_date, release_date_estimate), 1, 4) GLOB '[0-9][0-9][0-9][0-9]'
+        THEN substr(COALESCE(release_date, release_date_estimate), 1, 4)
+        ELSE 'unknown'
+    END";
+
+pub const VIDEO_SORT_COLUMNS: [SortColumn; 4] = [
+    ("id", "id"),
+    ("name", "name COLLATE NOCASE"),
+    (
+        "release_date",
+        "COALESCE(release_date, release_date_estimate)",
+    ),
+    ("updated_at", "updated_at"),
+];
+
+#[derive(Debug, Default)]
+pub struct VideoFilter {
+    pub is_requested: Option<bool>,
+    pub is_downloaded: Option<bool>,
+    /// Year of release, or "unknown".
+    pub season: Option<String>,
+}
+
+impl Video {
+    pub(super) fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Self> {
+        Ok(Video {
+            id: row.get(0)?,
+            channel_id: row.get(1)?,
+            domain: row.get(2)?,
+            url: row.get(3)?,
+            name: row.get(4)?,
+            video_id: row.get(5)?,
+            is_requested: row.get(6)?,
+            is_downloaded: row.get(7)?,
+            release_date: row.get(8)?,
+            release_date_estimate: row.get(9)?,
+            updated_at: row.get(10)?,
+            is_watched: row.get(11)?,
+        })
+    }
+
+    /// Release date, or the estimate if it's not known.
+    pub fn release_date_or_estimate(&self) -> Option<&str> {
+        self.release_date
+            .as_deref()
+            .or(self.release_date_estimate.as_deref())
+    }
+
+    /// Year of release, or "unknown".
+    pub fn season(&self) -> String {
+        self.release_date_or_estimate()
+            .and_then(|date| date.get(..4))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or("unknown")
+            .to_string()
+    }
+}
+
+/// One page of the channel's videos matching the filter.
+pub fn list_channel_videos(
+    conn: &Connection,
+    channel_id: i64,
+    filter: &VideoFilter,
+    page: &PageRequest,
+) -> Result<Page<Video>> {
+    let condition = format!(
+        "channel_id = ?1
+            AND (?2 IS NULL OR is_requested = ?2)
+            AND (?3 IS NULL OR is_downloaded = ?3)
+            AND (?4 IS NULL OR {} = ?4)",
+        SEASON
+    );
+    let filter_params = params![
+        channel_id,
+        filter.is_requested,
+        filter.is_downloaded,
+        filter.season
+    ];
+
+    let total = conn.query_row(
+        &format!("SELECT COUNT(*) FROM videos WHERE {}", condition),
+        filter_params,
+        |row| row.get(0),
+    )?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM videos WHERE {} {}",
+        COLUMNS,
+        condition,
+        page.sql()
+    ))?;
+    let videos = stmt
+        .query_map(filter_params, Video::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(page.page(videos, total))
+}
+
+pub fn get_video(conn: &Connection, domain: &str, video_id: &str) -> Result<Option<Video>> {
+    let video = conn
+        .query_row(
+            &format!(
+                "SELECT {} FROM videos WHERE domain = ?1 AND video_id = ?2",
+                COLUMNS
+            ),
+            params![domain, video_id],
+            Video::from_row,
+        )
+        .optional()?;
+    Ok(video)
+}
+
+pub fn get_video_by_url(conn: &Connection, url: &str) -> Result<Option<Video>> {
+    let video = conn
+        .query_row(
+            &format!("SELECT {} FROM videos WHERE url = ?1", COLUMNS),
+            params![url],
+            Video::from_row,
+        )
+        .optional()?;
+    Ok(video)
+}
+
+/// Record video found on a channel. Known videos only get their updated_at refreshed.
+/// Returns true if the video was not known before.
+pub fn upsert_video(conn: &Connection, video: &NewVideo) -> Result<bool> {
+    let tx = conn.unchecked_transaction()?;
+    let is_new = get_video(&tx, video.domain, video.video_id)?.is_none();
+    tx.execute(
+        "INSERT INTO videos (
+                channel_id, domain, url, name, video_id,
+                is_requested, is_downloaded, release_date, release_date_estimate
+            ) VALUES (?1, ?2, ?3, ?4, ?5, 0, 0, ?6, ?7)
+            ON CONFLICT(domain, video_id) DO UPDATE SET
+                updated_at = CURRENT_TIMESTAMP",
+        params![
+            video.channel_id,
+            video.domain,
+            video.url,
+            video.name,
+            video.video_id,
+            video.release_date,
+            video.release_date_estimate,
+        ],
+    )
+    .context("Failed to upsert video")?;
+    tx.commit()?;
+    Ok(is_new)
+}
+
+/// Record downloaded video, replacing what was known about it. The row ID is kept.
+pub fn save_downloaded_video(conn: &Connection, video: &NewVideo) -> Result<()> {
+    conn.execute(
+        "INSERT INTO videos (
+                channel_id, domain, url, name, video_id,
+                is_requested, is_downloaded, release_date, release_date_estimate
+            ) VALUES (?1, ?2, ?3, ?4, ?5, 1, 1, ?6, ?7)
+            ON CONFLICT(domain, video_id) DO UPDATE SET
+                channel_id = COALESCE(excluded.channel_id, channel_id),
+                url = excluded.url,
+                name = excluded.name,
+                is_requested = 1,
+                is_downloaded = 1,
+                release_date = excluded.release_date,
+                release_date_estimate = excluded.release_date_estimate,
+                updated_at = CURRENT_TIMESTAMP",
+        params![
+            video.channel_id,
+            video.domain,
+            video.url,
+            video.name,
+            video.video_id,
+            video.release_date.map(|date| date.and_hms_opt(0, 0, 0)),
+            video
+                .release_date_estimate
+                .map(|date| date.and_hms_opt(0, 0, 0)),
+        ],
+    )
+    .context("Failed to save downloaded video")?;
+    Ok(())
+}
+
+/// Save what the download told about the video. Returns false if there was no such video.
+pub fn save_download_info(
+    conn: &Connection,
+    domain: &str,
+    video_id: &str,
+    info: &DownloadInfo,
+) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE videos
+                SET file_path = ?1, video_codec = ?2, audio_codec = ?3,
+                    description = ?4, tags = ?5, updated_at = CURRENT_TIMESTAMP
+                WHERE domain = ?6 AND video_id = ?7",
+            params![
+                info.file_path,
+                info.video_codec,
+                info.audio_codec,
+                info.description,
+                info.tags,
+                domain,
+                video_id
+            ],
+        )
+        .context("Failed to save download info")?;
+    Ok(updated > 0)
+}
+
+pub fn get_download_info(conn: &Connection, id: i64) -> Result<Option<DownloadInfo>> {
+    let info = conn
+        .query_row(
+            "SELECT file_path, video_codec, audio_codec, description, tags
+                FROM videos WHERE id = ?1",
+            params![id],
+            |row| {
+                Ok(DownloadInfo {
+                    file_path: row.get(0)?,
+                    video_codec: row.get(1)?,
+                    audio_codec: row.get(2)?,
+                    description: row.get(3)?,
+                    tags: row.get(4)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(info)
+}
+
+/// Forget the files after they were deleted. The video stays requested, so that it's not
+/// offered for download again by accident.
+pub fn clear_video_files(conn: &Connection, id: i64) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE videos
+                SET is_downloaded = 0, file_path = NULL, updated_at = CURRENT_TIMESTAMP
+                WHERE id = ?1",
+            params![id],
+        )
+        .context("Failed to update video record")?;
+    Ok(updated > 0)
+}
+
+pub fn set_video_watched(conn: &Connection, id: i64, is_watched: bool) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE videos SET is_watched = ?1 WHERE id = ?2",
+            params![is_watched, id],
+        )
+        .context("Failed to update video record")?;
+    Ok(updated > 0)
+}
+
+/// Mark video requested, if it's known. Returns false if there was no such video.
+pub fn mark_video_requested(conn: &Connection, url: &str) -> Result<bool> {
+    let updated = conn
+        .execute(
+            "UPDATE videos SET is_requested = 1 WHERE url = ?1",
+            params![url],
+        )
+        .context("Failed to update video record")?;
+    Ok(updated > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::{insert_channel, sort_column, test_connection};
+
+    fn new_video<'a>(channel_id: Option<i64>, video_id: &'a str, url: &'a str) -> NewVideo<'a> {
+        NewVideo {
+            channel_id,
+            domain: "youtube.com",
+            url,
+            name: "Video",
+            video_id,
+            release_date: None,
+            release_date_estimate: NaiveDate::from_ymd_opt(2024, 5, 17),
+        }
+    }
+
+    #[test]
+    fn upsert_reports_new_videos_once() {
+        let conn = test_connection();
+        let channel =
+            insert_channel(&conn, "youtube.com", "youtube.com/channel/UC1", "UC1", "A").unwrap();
+
+        assert!(upsert_video(&conn, &new_video(Some(channel), "v1", "https://y/1")).unwrap());
+        assert!(!upsert_video(&conn, &new_video(Some(channel), "v1", "https://y/1")).unwrap());
+        assert!(upsert_video(&conn, &new_video(Some(channel), "v2", "https://y/2")).unwrap());
+
+        let page = PageRequest::first(&VIDEO_SORT_COLUMNS);
+        let videos = list_channel_videos(&conn, channel, &VideoFilter::default(), &page)
+            .unwrap()
+            .items;
+        assert_eq!(videos.len(), 2);
+        assert!(!videos[0].is_requested);
+        assert_eq!(videos[0].release_date_or_estimate(), Some("2024-05-17"));
+        assert_eq!(videos[0].season(), "2024");
+    }
+
+    #[test]
+    fn upsert_keeps_requested_and_downloaded() {
+        let conn = test_connection();
+        assert!(upsert_video(&conn, &new_video(None, "v1", "https://y/1")).unwrap());
+        assert!(mark_video_requested(&conn, "https://y/1").unwrap());
+        assert!(!upsert_video(&conn, &new_video(None, "v1", "https://y/1")).unwrap());
+
+        let video = get_video(&conn, "youtube.com", "v1").unwrap().unwrap();
+        assert!(video.is_requested);
+        assert!(!video.is_downloaded);
+    }
+
+    #[test]
+    fn downloaded_video_replaces_discovered() {
+        let conn = test_connection();
+        upsert_video(&conn, &new_video(None, "v1", "https://y/1")).unwrap();
+
+        let mut downloaded = new_video(None, "v1", "https://y/1");
+        downloaded.name = "Real title";
+        downloaded.release_date = NaiveDate::from_ymd_opt(2024, 5, 18);
+        save_downloaded_video(&conn, &downloaded).unwrap();
+
+        let video = get_video_by_url(&conn, "https://y/1").unwrap().unwrap();
+        assert_eq!(video.name, "Real title");
+        assert!(video.is_requested && video.is_downloaded);
+        assert_eq!(video.season(), "2024");
+    }
+
+    #[test]
+    fn download_info_and_watched() {
+        let conn = test_connection();
+        save_downloaded_video(&conn, &new_video(None, "v1", "https://y/1")).unwrap();
+        let info = DownloadInfo {
+            file_path: Some("/media/v1.mp4".to_string()),
+            video_codec: Some("avc1".to_string()),
+            tags: Some("a, b".to_string()),
+            ..Default::default()
+        };
+        assert!(save_download_info(&conn, "youtube.com", "v1", &info).unwrap());
+        assert!(!save_download_info(&conn, "youtube.com", "v2", &info).unwrap());
+
+        let video = get_video(&conn, "youtube.com", "v1").unwrap().unwrap();
+        let saved = get_download_info(&conn, video.id).unwrap().unwrap();
+        assert_eq!(saved.file_path.as_deref(), Some("/media/v1.mp4"));
+        assert_eq!(saved.video_codec.as_deref(), Some("avc1"));
+        assert!(!video.is_watched);
+
+        assert!(set_video_watched(&conn, video.id, true).unwrap());
+        assert!(clear_video_files(&conn, video.id).unwrap());
+        let video = get_video(&conn, "youtube.com", "v1").unwrap().unwrap();
+        assert!(video.is_watched && video.is_requested && !video.is_downloaded);
+        assert!(get_download_info(&conn, video.id)
+            .unwrap()
+            .unwrap()
+            .file_path
+            .is_none());
+    }
+
+    #[test]
+    fn lists_videos_by_season() {
+        let conn = test_connection();
+        let channel =
+            insert_channel(&conn, "youtube.com", "youtube.com/channel/UC1", "UC1", "A").unwrap();
+        let mut old = new_video(Some(channel), "v1", "https://y/1");
+        old.release_date_estimate = NaiveDate::from_ymd_opt(2023, 1, 1);
+        upsert_video(&conn, &old).unwrap();
+        upsert_video(&conn, &new_video(Some(channel), "v2", "https://y/2")).unwrap();
+        let mut undated = new_video(Some(channel), "v3", "https://y/3");
+        undated.release_date_estimate = None;
+        upsert_video(&conn, &undated).unwrap();
+        mark_video_requested(&conn, "https://y/2").unwrap();
+
+        let page = PageRequest {
+            sort: sort_column(&VIDEO_SORT_COLUMNS, "release_date").unwrap(),
+            descending: true,
+            ..PageRequest::first(&VIDEO_SORT_COLUMNS)
+        };
+        let all = list_channel_videos(&conn, channel, &VideoFilter::default(), &page).unwrap();
+        let ids: Vec<_> = all.items.iter().map(|v| v.video_id.as_str()).collect();
+        assert_eq!(ids, ["v2", "v1", "v3"]);
+
+        let filter = VideoFilter {
+            season: Some("unknown".to_string()),
+            ..Default::default()
+        };
+        let unknown = list_channel_videos(&conn, channel, &filter, &page).unwrap();
+        assert_eq!(unknown.total, 1);
+        assert_eq!(unknown.items[0].video_id, "v3");
+
+        let filter = VideoFilter {
+            is_requested: Some(false),
+            ..Default::default()
+        };
+        assert_eq!(
+            list_channel_videos(&conn, channel, &filter, &page)
+                .unwrap()
+                .total,
+            2
+        );
+    }
+
+    #[test]
+    fn unknown_video_is_not_marked() {
+        let conn = test_connection();
+        assert!(!mark_video_requested(&conn, "https://y/unknown").unwrap());
+        assert!(get_video_by_url(&conn, "https://y/unknown")
+            .unwrap()
+            .is_none());
+    }
+}
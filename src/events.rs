@@ -0,0 +1,50 @@
+//! In-process broadcast of live events, streamed to the web UI as server-sent events.
+//!
+//! Publishing never blocks. Events are dropped if nobody is listening.
+
+use rocket::tokio::sync::broadcast;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+/// Events buffered per subscriber before it starts lagging.
+const CAPACITY: usize = 256;
+
+static EVENTS: LazyLock<broadcast::Sender<Event>> =
+    LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    TaskCreated {
+        task_id: i64,
+        task_type: String,
+    },
+    TaskState {
+        task_id: i64,
+        task_state: String,
+    },
+    Progress {
+        task_id: i64,
+        percent: f32,
+    },
+    VideoDiscovered {
+        channel_id: i64,
+        video_id: String,
+        title: String,
+        url: String,
+    },
+    ChannelAdded {
+        domain: String,
+        channel_id: String,
+        channel_name: String,
+    },
+}
+
+pub fn publish(event: Event) {
+    // Error only means there are no subscribers
+    let _ = EVENTS.send(event);
+}
+
+pub fn subscribe() -> broadcast::Receiver<Event> {
+    EVENTS.subscribe()
+}
@@ -1,6 +1,13 @@
-//! Standard configuration paths
+//! Standard configuration and data paths
+//!
+//! On Linux, system installs (running as root) use /etc/<name> and /var/lib/<name>.
+//! User installs follow XDG: $XDG_CONFIG_HOME/<name> and $XDG_DATA_HOME/<name>, defaulting
+//! to ~/.config/<name> and ~/.local/share/<name>.
 
-use std::path::PathBuf;
+use std::env;
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, MetadataExt};
+use std::path::{Path, PathBuf};
 
 use thiserror::Error;
 
@@ -12,10 +19,19 @@ pub struct AppDetails {
     pub tld: String,
 }
 
+impl AppDetails {
+    /// Reverse domain name, eg. fi.amnis.tubarr
+    pub fn identifier(&self) -> String {
+        format!("{}.{}.{}", self.tld, self.organization, self.name)
+    }
+}
+
 #[derive(Debug, Error)]
 pub enum ConfigError {
     #[error("Unsupported platform for system configuration path")]
     UnsupportedPlatform,
+    #[error("Home directory not found, set HOME or give the folder explicitly")]
+    NoHomeDirectory,
 }
 
 // Returns system path for application configuration
@@ -26,3 +42,67 @@ pub fn system_configuration(app: &AppDetails) -> Result<PathBuf, ConfigError> {
         Err(ConfigError::UnsupportedPlatform)
     }
 }
+
+/// Folder for the config file.
+pub fn config_dir(app: &AppDetails) -> Result<PathBuf, ConfigError> {
+    if cfg!(target_os = "macos") {
+        return application_support(app);
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(ConfigError::UnsupportedPlatform);
+    }
+
+    if is_system_install() {
+        return system_configuration(app);
+    }
+    Ok(xdg_dir("XDG_CONFIG_HOME", ".config")?.join(&app.name))
+}
+
+/// Folder for the database and other application data.
+pub fn data_dir(app: &AppDetails) -> Result<PathBuf, ConfigError> {
+    if cfg!(target_os = "macos") {
+        return application_support(app);
+    }
+    if !cfg!(target_os = "linux") {
+        return Err(ConfigError::UnsupportedPlatform);
+    }
+
+    if is_system_install() {
+        return Ok(PathBuf::from("/var/lib").join(&app.name));
+    }
+    Ok(xdg_dir("XDG_DATA_HOME", ".local/share")?.join(&app.name))
+}
+
+/// Create the folder and its parents. New folders are readable only by the owner.
+pub fn create_private_dir(path: &Path) -> std::io::Result<()> {
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(path)
+}
+
+/// Running as root.
+fn is_system_install() -> bool {
+    fs::metadata("/proc/self").is_ok_and(|meta| meta.uid() == 0)
+}
+
+/// XDG base directory from the environment. Relative paths are invalid and ignored.
+fn xdg_dir(var: &str, default_in_home: &str) -> Result<PathBuf, ConfigError> {
+    match env::var_os(var).map(PathBuf::from) {
+        Some(path) if path.is_absolute() => Ok(path),
+        _ => Ok(home_dir()?.join(default_in_home)),
+    }
+}
+
+fn application_support(app: &AppDetails) -> Result<PathBuf, ConfigError> {
+    Ok(home_dir()?
+        .join("Library/Application Support")
+        .join(app.identifier()))
+}
+
+fn home_dir() -> Result<PathBuf, ConfigError> {
+    env::var_os("HOME")
+        .map(PathBuf::from)
+        .filter(|home| home.is_absolute())
+        .ok_or(ConfigError::NoHomeDirectory)
+}
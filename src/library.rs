@@ -0,0 +1,185 @@
+//! Downloaded files of videos under path_media. yt-dlp writes the thumbnail and subtitles
+//! next to the media file, named the same up to the extension.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use utoipa::ToSchema;
+
+/// Extensions yt-dlp uses for the video itself.
+const MEDIA_EXTENSIONS: [&str; 7] = ["mp4", "mkv", "webm", "mov", "m4a", "mp3", "opus"];
+const THUMBNAIL_EXTENSIONS: [&str; 3] = ["jpg", "webp", "png"];
+const SUBTITLE_EXTENSIONS: [&str; 2] = ["srt", "vtt"];
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VideoFiles {
+    pub path: String,
+    /// Size of the media file in bytes.
+    pub size: u64,
+    pub has_thumbnail: bool,
+    pub subtitles: Vec<Subtitle>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Subtitle {
+    /// Language code from the file name, eg. "en" or "en-US".
+    pub language: String,
+    pub path: String,
+}
+
+/// The parts of yt-dlp .info.json that Tubarr keeps.
+#[derive(Debug, Deserialize)]
+pub struct InfoJson {
+    pub description: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub vcodec: Option<String>,
+    pub acodec: Option<String>,
+}
+
+impl InfoJson {
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents =
+            fs::read(path).with_context(|| format!("Could not read {}", path.display()))?;
+        serde_json::from_slice(&contents)
+            .with_context(|| format!("Could not parse {}", path.display()))
+    }
+
+    /// yt-dlp says "none" for a missing stream.
+    pub fn video_codec(&self) -> Option<String> {
+        self.vcodec.clone().filter(|codec| codec != "none")
+    }
+
+    pub fn audio_codec(&self) -> Option<String> {
+        self.acodec.clone().filter(|codec| codec != "none")
+    }
+}
+
+/// Media file named "<stem>.<extension>" in the folder.
+pub fn find_media_file(folder: &Path, stem: &str) -> Option<PathBuf> {
+    fs::read_dir(folder)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| file_stem(path).is_some_and(|name| name == stem))
+        .find(|path| has_extension(path, &MEDIA_EXTENSIONS))
+}
+
+/// Files of the video. None if the media file is gone.
+pub fn inspect(file_path: &Path) -> Option<VideoFiles> {
+    let size = fs::metadata(file_path).ok()?.len();
+    let stem = file_stem(file_path)?;
+
+    let mut subtitles: Vec<Subtitle> = siblings(file_path)
+        .into_iter()
+        .filter(|path| has_extension(path, &SUBTITLE_EXTENSIONS))
+        .map(|path| {
+            // "<stem>.en.srt", or "<stem>.srt" without language
+            let name = path
+                .file_stem()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            let language = name.strip_prefix(&stem).unwrap_or_default();
+            Subtitle {
+                language: language.trim_start_matches('.').to_string(),
+                path: path.display().to_string(),
+            }
+        })
+        .collect();
+    subtitles.sort_by(|a, b| a.language.cmp(&b.language));
+
+    Some(VideoFiles {
+        path: file_path.display().to_string(),
+        size,
+        has_thumbnail: thumbnail(file_path).is_some(),
+        subtitles,
+    })
+}
+
+pub fn thumbnail(file_path: &Path) -> Option<PathBuf> {
+    THUMBNAIL_EXTENSIONS
+        .iter()
+        .map(|extension| file_path.with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+/// Delete the media file, and the thumbnail, subtitles and other files next to it.
+/// Returns the deleted files.
+pub fn remove(file_path: &Path) -> Result<Vec<PathBuf>> {
+    let files = siblings(file_path);
+    for path in &files {
+        fs::remove_file(path).with_context(|| format!("Could not delete {}", path.display()))?;
+    }
+    Ok(files)
+}
+
+/// Files named "<stem>.*" in the folder of the media file, the media file included.
+fn siblings(file_path: &Path) -> Vec<PathBuf> {
+    let (Some(folder), Some(stem)) = (file_path.parent(), file_stem(file_path)) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.", stem);
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(&prefix))
+        })
+        .collect()
+}
+
+fn file_stem(path: &Path) -> Option<String> {
+    path.file_stem()
+        .and_then(|stem| stem.to_str())
+        .map(str::to_string)
+}
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.contains(&extension.to_lowercase().as_str()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_inspects_and_removes_files() {
+        let folder = std::env::temp_dir().join(format!("tubarr-library-{}", std::process::id()));
+        fs::create_dir_all(&folder).unwrap();
+        for name in [
+            "Chan - Title - abc.mp4",
+            "Chan - Title - abc.jpg",
+            "Chan - Title - abc.en.srt",
+            "Chan - Title - abc.fi.srt",
+            "Chan - Title - abcd.mp4",
+        ] {
+            fs::write(folder.join(name), "1234").unwrap();
+        }
+
+        let media = find_media_file(&folder, "Chan - Title - abc").unwrap();
+        assert_eq!(media, folder.join("Chan - Title - abc.mp4"));
+        assert!(find_media_file(&folder, "Chan - Title").is_none());
+
+        let files = inspect(&media).unwrap();
+        assert_eq!(files.size, 4);
+        assert!(files.has_thumbnail);
+        let languages: Vec<_> = files
+            .subtitles
+            .iter()
+            .map(|s| s.language.as_str())
+            .collect();
+        assert_eq!(languages, ["en", "fi"]);
+
+        assert_eq!(remove(&media).unwrap().len(), 4);
+        assert!(inspect(&media).is_none());
+        assert!(folder.join("Chan - Title - abcd.mp4").exists());
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
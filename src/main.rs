@@ -6,10 +6,14 @@
 //! Requires local installation of yt-dlp and ffmpeg.
 
 use anyhow::Result;
+use clap::Parser;
 use core::time;
 use r2d2::Pool;
 use r2d2_sqlite::SqliteConnectionManager;
+use rand::distributions::{Alphanumeric, DistString};
 use rusqlite::params;
+use std::collections::HashMap;
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
 use std::{
     env, io,
     path::PathBuf,
@@ -18,9 +22,20 @@ use std::{
 };
 use std::{io::Write, sync::OnceLock};
 use tracing::{debug, error, event, info, trace, warn};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
 
+mod auth;
+mod cli;
+mod config;
 mod database;
+mod events;
 mod folders;
+mod library;
+mod mediaserver;
+mod metrics;
+mod notify;
+mod requests;
+mod subscriptions;
 mod taskrunner;
 mod webui;
 
@@ -33,14 +48,6 @@ static APP_DETAILS: OnceLock<folders::AppDetails> = OnceLock::new();
 // ^-- GLOBALS
 
 fn main() {
-    // Initialize tracing (logging)
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::DEBUG)
-        .with_level(true)
-        .with_ansi(true)
-        .compact()
-        .init();
-
     // Initialize globals
     APP_DETAILS
         .set(folders::AppDetails {
@@ -49,13 +56,75 @@ fn main() {
             tld: String::from("fi"),
         })
         .unwrap();
+    let app = APP_DETAILS.get().unwrap();
+
+    // Argument handling, before logging so that --help stays clean
+    let default_config = folders::config_dir(app)
+        .ok()
+        .map(|dir| dir.join("tubarr.toml"));
+    let options = match cli::Cli::parse().into_options(default_config.as_deref()) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{:#}", e);
+            exit_with_error(2);
+            return;
+        }
+    };
+
+    // Initialize tracing (logging). Commands only report problems, and keep stdout clean.
+    let (max_level, writer) = match options.command {
+        Some(_) => (tracing::Level::WARN, BoxMakeWriter::new(io::stderr)),
+        None => (tracing::Level::DEBUG, BoxMakeWriter::new(io::stdout)),
+    };
+    tracing_subscriber::fmt()
+        .with_max_level(max_level)
+        .with_writer(writer)
+        .with_level(true)
+        .with_ansi(true)
+        .compact()
+        .init();
 
-    let mut sqlite_path = folders::system_configuration(&APP_DETAILS.get().unwrap()).unwrap();
-    sqlite_path.push("db.sqlite");
+    let sqlite_path = match get_database_path(app, options.data_dir, options.db) {
+        Ok(path) => path,
+        Err(e) => {
+            error!("{:#}", e);
+            exit_with_error(-1);
+            return;
+        }
+    };
     DB_PATH.set(sqlite_path).unwrap();
 
+    // Administration subcommand instead of the server
+    if let Some(command) = options.command {
+        match cli::run(command, DB_PATH.get().unwrap()) {
+            Ok(_) => exit_with_ok(),
+            Err(e) => {
+                eprintln!("Error: {:#}", e);
+                exit_with_error(1);
+            }
+        }
+        return;
+    }
+
+    // Held until exit, keeps 'db restore' and 'db import' from replacing the database
+    let _server_lock = match database::ServerLock::acquire(DB_PATH.get().unwrap()) {
+        Ok(lock) => lock,
+        Err(e) => {
+            error!("{:#}", e);
+            exit_with_error(-1);
+            return;
+        }
+    };
+
     // Initialize the database
-    let dbp = database::init_from(DB_PATH.get().unwrap());
+    let dbp = match database::init_from(DB_PATH.get().unwrap()) {
+        Ok(dbp) => dbp,
+        Err(e) => {
+            error!("{:#}", e);
+            exit_with_error(-1);
+            return;
+        }
+    };
 
     // Set up CTRL+C handling, for clean shutdown
     ctrlc::set_handler(move || {
@@ -64,16 +133,58 @@ fn main() {
     })
     .expect("Error setting Ctrl-C handler");
 
+    // Configuration given at startup
+    if !options.settings.is_empty() {
+        if let Err(e) = set_configuration(dbp.clone(), &options.settings) {
+            error!("{:#}", e);
+            exit_with_error(-1);
+        }
+    }
+
     // First_time_setup
     if is_first_time_setup(dbp.clone()) {
-        match get_configuration_from_user(dbp.clone()) {
-            Ok(_) => set_first_time_setup(dbp.clone()),
-            Err(_) => exit_with_error(-1),
+        if options.non_interactive {
+            info!("First time setup skipped, using configuration defaults");
+            set_first_time_setup(dbp.clone());
+        } else {
+            match get_configuration_from_user(dbp.clone()) {
+                Ok(_) => set_first_time_setup(dbp.clone()),
+                Err(_) => exit_with_error(-1),
+            };
+        }
+    }
+
+    // Admin password is needed to log in to the web UI
+    if !is_admin_password_set(dbp.clone()) {
+        let result = match (&options.admin_password, options.non_interactive) {
+            (Some(password), _) => set_admin_password(dbp.clone(), password),
+            (None, true) => {
+                let password = Alphanumeric.sample_string(&mut rand::thread_rng(), 20);
+                set_admin_password(dbp.clone(), &password)
+                    .and_then(|_| write_generated_password(&password))
+                    .map(|path| {
+                        warn!(
+                            "Generated password for the web UI user '{}' is in {}. \
+                            Delete the file after logging in.",
+                            auth::ADMIN_USERNAME,
+                            path.display()
+                        )
+                    })
+            }
+            (None, false) => get_admin_password_from_user(dbp.clone()),
         };
+        if let Err(e) = result {
+            error!("Failed to set admin password: {:?}", e);
+            exit_with_error(-1);
+        }
     }
 
-    // Argument handling
-    let args: Vec<String> = env::args().collect();
+    let server = webui::ServerConfig {
+        address: options.address,
+        port: options.port,
+        tls: options.tls,
+        base_path: options.base_path,
+    };
 
     // Start subprograms
     let mut subprogs = Vec::new();
@@ -82,7 +193,10 @@ fn main() {
     subprogs.push(thread::spawn(move || taskrunner::run(dbp_clone)));
 
     let dbp_clone = dbp.clone();
-    subprogs.push(thread::spawn(move || webui::run(dbp_clone)));
+    subprogs.push(thread::spawn(move || webui::run(dbp_clone, server)));
+
+    let dbp_clone = dbp.clone();
+    subprogs.push(thread::spawn(move || mediaserver::run(dbp_clone)));
 
     // Main infinite loop
     loop {
@@ -104,6 +218,35 @@ fn main() {
     exit_with_ok();
 }
 
+/// Database file to use. Creates the data folder, and moves the database from the old
+/// /etc/<name> location when the standard data folder is used for the first time.
+fn get_database_path(
+    app: &folders::AppDetails,
+    data_dir: Option<PathBuf>,
+    db: Option<PathBuf>,
+) -> Result<PathBuf> {
+    if let Some(db) = db {
+        return Ok(db);
+    }
+
+    let is_standard = data_dir.is_none();
+    let data_dir = match data_dir {
+        Some(dir) => dir,
+        None => folders::data_dir(app)?,
+    };
+    folders::create_private_dir(&data_dir).map_err(|e| {
+        anyhow::anyhow!("Could not create data folder {}: {}", data_dir.display(), e)
+    })?;
+    let db = data_dir.join("db.sqlite");
+
+    if is_standard {
+        if let Ok(legacy) = folders::system_configuration(app) {
+            database::migrate_legacy(&legacy.join("db.sqlite"), &db)?;
+        }
+    }
+    Ok(db)
+}
+
 /// Exit the program with no errors.
 fn exit_with_ok() {
     std::process::exit(0);
@@ -138,9 +281,13 @@ fn get_configuration_from_user(dbp: DBPool) -> Result<()> {
         .get()
         .expect("Failed to get database connection from pool");
 
-    // Retrieve all configuration rows except for "first_time_setup"
-    let mut stmt =
-        conn.prepare("SELECT key, value FROM app_configuration WHERE key != 'first_time_setup'")?;
+    // Only the settings needed to download. The rest, secrets included, are set in the web
+    // UI, config file or with 'tubarr config set'.
+    let mut stmt = conn.prepare(
+        "SELECT key, value FROM app_configuration
+            WHERE key IN ('path_temp', 'path_media', 'sub_lang', 'retry_limit')
+            ORDER BY rowid",
+    )?;
     let config_entries = stmt.query_map([], |row| {
         let key: String = row.get(0)?;
         let value: String = row.get(1)?;
@@ -155,21 +302,34 @@ fn get_configuration_from_user(dbp: DBPool) -> Result<()> {
     for entry in config_entries {
         let (key, default_value) = entry?;
 
-        // Prompt user with the current value as the default
-        print!("'{}', default [{}]: ", key, default_value);
-        io::stdout().flush().expect("Failed to flush stdout");
-
-        // Read user input
-        let mut input = String::new();
-        io::stdin()
-            .read_line(&mut input)
-            .expect("Failed to read input");
-
-        // Use the default value if the input is empty; otherwise, use the user's input
-        let value = if input.trim().is_empty() {
-            default_value.clone()
-        } else {
-            input.trim().to_string()
+        let value = loop {
+            // Prompt user with the current value as the default
+            print!("'{}', default [{}]: ", key, default_value);
+            io::stdout().flush().expect("Failed to flush stdout");
+
+            // Read user input
+            let mut input = String::new();
+            io::stdin()
+                .read_line(&mut input)
+                .expect("Failed to read input");
+
+            // Use the default value if the input is empty; otherwise, use the user's input
+            let value = if input.trim().is_empty() {
+                default_value.clone()
+            } else {
+                input.trim().to_string()
+            };
+
+            // yt-dlp used to create the folders, do it here before checking them
+            match config::create_missing_folder(&key, &value) {
+                Ok(true) => println!("Created folder {}", value),
+                Ok(false) => {}
+                Err(e) => println!("Could not create folder {}: {}", value, e),
+            }
+            match config::validate(&key, &value) {
+                Ok(_) => break value,
+                Err(e) => println!("Invalid value: {}", e),
+            }
         };
 
         // Add the configuration entry to the list of updates
@@ -187,6 +347,89 @@ fn get_configuration_from_user(dbp: DBPool) -> Result<()> {
     Ok(())
 }
 
+/// Check if the admin password has been set, or if there's only the initial placeholder.
+fn is_admin_password_set(dbp: DBPool) -> bool {
+    let conn = dbp
+        .get()
+        .expect("Failed to get database connection from pool");
+
+    auth::admin_password_is_set(&conn).unwrap_or(false)
+}
+
+/// Validate and save configuration values given at startup.
+fn set_configuration(dbp: DBPool, values: &HashMap<String, String>) -> Result<()> {
+    let mut conn = dbp
+        .get()
+        .expect("Failed to get database connection from pool");
+
+    for (key, value) in values {
+        match config::create_missing_folder(key, value) {
+            Ok(true) => info!("Created folder {}", value),
+            Ok(false) => {}
+            Err(e) => error!("Could not create folder {}: {}", value, e),
+        }
+    }
+    match config::set_values(&mut conn, values) {
+        Ok(_) => Ok(()),
+        Err(config::ConfigError::Invalid(errors)) => {
+            for (key, message) in errors {
+                error!("Invalid configuration value '{}': {}", key, message);
+            }
+            Err(anyhow::anyhow!("Invalid configuration"))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Store the hash of given admin password to database.
+fn set_admin_password(dbp: DBPool, password: &str) -> Result<()> {
+    if password.is_empty() {
+        return Err(anyhow::anyhow!("Password can not be empty"));
+    }
+
+    let conn = dbp
+        .get()
+        .expect("Failed to get database connection from pool");
+    auth::set_password(&conn, auth::ADMIN_USERNAME, password)
+}
+
+/// Save generated admin password next to the database, readable only by the owner. Logs are
+/// kept around and shipped elsewhere, so the password is not written there.
+fn write_generated_password(password: &str) -> Result<PathBuf> {
+    let folder = DB_PATH
+        .get()
+        .and_then(|db| db.parent())
+        .ok_or_else(|| anyhow::anyhow!("Database path is not set"))?;
+    let path = folder.join("admin-password.txt");
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&path)
+        .map_err(|e| anyhow::anyhow!("Could not write {}: {}", path.display(), e))?;
+    // Mode is only used for new files
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    writeln!(file, "{}", password)?;
+    Ok(path)
+}
+
+/// Ask admin password from user, store the hash to database.
+fn get_admin_password_from_user(dbp: DBPool) -> Result<()> {
+    let conn = dbp
+        .get()
+        .expect("Failed to get database connection from pool");
+
+    println!(
+        "Set password for the web UI user '{}'.",
+        auth::ADMIN_USERNAME
+    );
+
+    let password = cli::prompt_new_password()?;
+    auth::set_password(&conn, auth::ADMIN_USERNAME, &password)
+}
+
 /// Set the first_time_setup configuration key to "false".
 /// Skips if errors are found.
 fn set_first_time_setup(dbp: DBPool) {
@@ -0,0 +1,88 @@
+//! HTTP calls to supported media servers.
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::path::Path;
+use std::time::Duration;
+
+use super::MediaServer;
+
+/// Supported values for media_servers.kind
+pub const KINDS: [&str; 3] = ["JELLYFIN", "EMBY", "PLEX"];
+
+fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(15))
+        .build()
+}
+
+/// Check that the server answers and accepts the token.
+pub fn test_connection(server: &MediaServer) -> Result<()> {
+    let base = server.url.trim_end_matches('/');
+    let response = match server.kind.as_str() {
+        "JELLYFIN" | "EMBY" => agent()
+            .get(&format!("{}/System/Info", base))
+            .set("X-Emby-Token", &server.token)
+            .call(),
+        "PLEX" => agent()
+            .get(&format!("{}/library/sections", base))
+            .set("X-Plex-Token", &server.token)
+            .call(),
+        kind => return Err(anyhow!("Unknown media server kind: {}", kind)),
+    };
+
+    response
+        .map(|_| ())
+        .map_err(|e| anyhow!("Connection to {} failed: {}", server.name, e))
+}
+
+/// Ask the server to rescan given folders (as seen by the server).
+pub fn refresh(server: &MediaServer, folders: &[String]) -> Result<()> {
+    let base = server.url.trim_end_matches('/');
+    let response = match server.kind.as_str() {
+        // Jellyfin and Emby scan only the reported paths
+        "JELLYFIN" | "EMBY" => {
+            let updates: Vec<_> = folders
+                .iter()
+                .map(|folder| json!({ "Path": folder, "UpdateType": "Modified" }))
+                .collect();
+            agent()
+                .post(&format!("{}/Library/Media/Updated", base))
+                .set("X-Emby-Token", &server.token)
+                .send_json(json!({ "Updates": updates }))
+        }
+        // Plex can scan one path of one library section, otherwise everything
+        "PLEX" => match &server.library_id {
+            Some(library_id) => agent()
+                .get(&format!("{}/library/sections/{}/refresh", base, library_id))
+                .set("X-Plex-Token", &server.token)
+                .query("path", &common_folder(folders))
+                .call(),
+            None => agent()
+                .get(&format!("{}/library/sections/all/refresh", base))
+                .set("X-Plex-Token", &server.token)
+                .call(),
+        },
+        kind => return Err(anyhow!("Unknown media server kind: {}", kind)),
+    };
+
+    response
+        .map(|_| ())
+        .map_err(|e| anyhow!("Refresh of {} failed: {}", server.name, e))
+}
+
+/// Deepest folder containing all of the given folders.
+fn common_folder(folders: &[String]) -> String {
+    let mut common = match folders.first() {
+        Some(first) => Path::new(first).to_path_buf(),
+        None => return "/".to_string(),
+    };
+    for folder in folders.iter().skip(1) {
+        while !Path::new(folder).starts_with(&common) {
+            if !common.pop() {
+                break;
+            }
+        }
+    }
+    common.to_string_lossy().to_string()
+}
@@ -0,0 +1,206 @@
+//! Library refresh for media servers (Jellyfin, Emby, Plex).
+//!
+//! Completed downloads queue their folder here. Refresh is debounced: it's sent only after
+//! no new folders have been queued for a while and no downloads are waiting or running,
+//! so requesting a whole season causes a single scan.
+
+use anyhow::Result;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info};
+
+use crate::DBPool;
+use crate::FLAG_SHUTDOWN;
+
+mod clients;
+pub use clients::{test_connection, KINDS};
+
+/// Quiet period after the last queued folder before refreshing.
+const DEBOUNCE: Duration = Duration::from_secs(60);
+/// Refresh anyway when folders have been pending this long.
+const MAX_WAIT: Duration = Duration::from_secs(30 * 60);
+
+struct Pending {
+    folders: BTreeSet<PathBuf>,
+    first_queued: Instant,
+    last_queued: Instant,
+}
+
+static PENDING: Mutex<Option<Pending>> = Mutex::new(None);
+
+#[derive(Debug, Clone)]
+pub struct MediaServer {
+    pub name: String,
+    pub kind: String,
+    pub url: String,
+    pub token: String,
+    pub library_id: Option<String>,
+    pub path_remote: Option<String>,
+}
+
+/// Queue a folder under path_media for library refresh.
+pub fn queue_refresh(folder: &Path) {
+    let now = Instant::now();
+    let mut pending = PENDING.lock().unwrap();
+    let pending = pending.get_or_insert_with(|| Pending {
+        folders: BTreeSet::new(),
+        first_queued: now,
+        last_queued: now,
+    });
+    pending.folders.insert(folder.to_path_buf());
+    pending.last_queued = now;
+}
+
+pub fn run(dbp: DBPool) {
+    loop {
+        // Sleep so we don't trash the CPU
+        thread::sleep(Duration::from_secs(5));
+
+        if let Some(folders) = take_ready_folders(dbp.clone()) {
+            refresh_all(dbp.clone(), folders);
+        }
+
+        // Check for shutdown signal
+        if FLAG_SHUTDOWN.load(std::sync::atomic::Ordering::Relaxed) {
+            break;
+        }
+    }
+}
+
+/// Take the pending folders, if the debounce conditions are fulfilled.
+fn take_ready_folders(dbp: DBPool) -> Option<BTreeSet<PathBuf>> {
+    {
+        let pending = PENDING.lock().unwrap();
+        let pending = pending.as_ref()?;
+        if pending.first_queued.elapsed() < MAX_WAIT {
+            if pending.last_queued.elapsed() < DEBOUNCE {
+                return None;
+            }
+            if downloads_in_progress(dbp) {
+                return None;
+            }
+        }
+    }
+
+    PENDING
+        .lock()
+        .unwrap()
+        .take()
+        .map(|pending| pending.folders)
+}
+
+fn downloads_in_progress(dbp: DBPool) -> bool {
+    let conn = match dbp.get() {
+        Ok(conn) => conn,
+        Err(_) => return false,
+    };
+    conn.query_row(
+        "SELECT COUNT(*) FROM tasks WHERE task_type = 'VIDEO-DOWNLOAD' AND task_state IN ('WAIT', 'WIP')",
+        params![],
+        |row| row.get::<_, i64>(0).map(|count| count > 0),
+    )
+    .unwrap_or(false)
+}
+
+fn refresh_all(dbp: DBPool, folders: BTreeSet<PathBuf>) {
+    let conn = match dbp.get() {
+        Ok(conn) => conn,
+        Err(_) => {
+            error!("Error getting database connection.");
+            return;
+        }
+    };
+
+    let path_media: String = match conn.query_row(
+        "SELECT value FROM app_configuration WHERE key = 'path_media'",
+        params![],
+        |row| row.get(0),
+    ) {
+        Ok(path) => path,
+        Err(e) => {
+            error!("Could not get configuration path_media: {}", e);
+            return;
+        }
+    };
+
+    let servers = match get_media_servers(&conn) {
+        Ok(servers) => servers,
+        Err(e) => {
+            error!("Failed to load media servers: {:?}", e);
+            return;
+        }
+    };
+    drop(conn);
+
+    for server in servers {
+        let remote_folders: Vec<String> = folders
+            .iter()
+            .map(|folder| remote_path(&server, Path::new(&path_media), folder))
+            .collect();
+        debug!("Refreshing {} for {:?}", server.name, remote_folders);
+        match clients::refresh(&server, &remote_folders) {
+            Ok(_) => info!("Library refresh requested from {}", server.name),
+            Err(e) => error!("Library refresh of {} failed: {:?}", server.name, e),
+        }
+    }
+}
+
+/// Translate a local folder to the path the media server sees.
+fn remote_path(server: &MediaServer, path_media: &Path, folder: &Path) -> String {
+    match (&server.path_remote, folder.strip_prefix(path_media)) {
+        (Some(path_remote), Ok(relative)) => Path::new(path_remote)
+            .join(relative)
+            .to_string_lossy()
+            .to_string(),
+        _ => folder.to_string_lossy().to_string(),
+    }
+}
+
+/// Get a single media server by ID, regardless of enabled state.
+pub fn get_media_server(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: i64,
+) -> Result<MediaServer> {
+    let server = conn.query_row(
+        "SELECT name, kind, url, token, library_id, path_remote FROM media_servers WHERE id = ?1",
+        params![id],
+        |row| {
+            Ok(MediaServer {
+                name: row.get(0)?,
+                kind: row.get(1)?,
+                url: row.get(2)?,
+                token: row.get(3)?,
+                library_id: row.get(4)?,
+                path_remote: row.get(5)?,
+            })
+        },
+    )?;
+    Ok(server)
+}
+
+fn get_media_servers(conn: &PooledConnection<SqliteConnectionManager>) -> Result<Vec<MediaServer>> {
+    let mut stmt = conn.prepare(
+        "SELECT name, kind, url, token, library_id, path_remote FROM media_servers WHERE enabled = 1",
+    )?;
+    let servers = stmt
+        .query_map(params![], |row| {
+            Ok(MediaServer {
+                name: row.get(0)?,
+                kind: row.get(1)?,
+                url: row.get(2)?,
+                token: row.get(3)?,
+                library_id: row.get(4)?,
+                path_remote: row.get(5)?,
+            })
+        })?
+        .filter_map(|res| res.ok())
+        .collect();
+
+    Ok(servers)
+}
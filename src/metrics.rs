@@ -0,0 +1,285 @@
+//! Prometheus metrics, served in text format by the webui.
+//!
+//! Counters and histograms are collected in-process while the program runs.
+//! Gauges describing the database state are queried when scraped.
+
+use anyhow::Result;
+use rusqlite::{params, Connection};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::io;
+use std::process::Output;
+use std::sync::atomic::{AtomicI64, AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::DBPool;
+
+/// Worker threads currently running one-off tasks.
+pub static WORKERS_ACTIVE: AtomicI64 = AtomicI64::new(0);
+/// Bytes of downloaded files moved to path_media.
+pub static DOWNLOAD_BYTES: AtomicU64 = AtomicU64::new(0);
+/// Duration of VIDEO-DOWNLOAD tasks.
+pub static DOWNLOAD_DURATION: Histogram =
+    Histogram::new(&[10.0, 30.0, 60.0, 120.0, 300.0, 600.0, 1800.0, 3600.0]);
+/// Duration of fetching the video list of single channel.
+pub static CHANNEL_FETCH_DURATION: Histogram =
+    Histogram::new(&[1.0, 5.0, 15.0, 30.0, 60.0, 300.0, 900.0]);
+/// Failed yt-dlp executions by error kind.
+static YTDLP_FAILURES: Mutex<BTreeMap<&'static str, u64>> = Mutex::new(BTreeMap::new());
+
+pub struct Histogram {
+    buckets: &'static [f64],
+    data: Mutex<HistogramData>,
+}
+
+struct HistogramData {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    const fn new(buckets: &'static [f64]) -> Self {
+        Histogram {
+            buckets,
+            data: Mutex::new(HistogramData {
+                counts: Vec::new(),
+                sum: 0.0,
+                count: 0,
+            }),
+        }
+    }
+
+    pub fn observe(&self, value: f64) {
+        let mut data = self.data.lock().unwrap();
+        data.counts.resize(self.buckets.len(), 0);
+        for (idx, bound) in self.buckets.iter().enumerate() {
+            if value <= *bound {
+                data.counts[idx] += 1;
+            }
+        }
+        data.sum += value;
+        data.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        let data = self.data.lock().unwrap();
+        let _ = writeln!(out, "# HELP {} {}", name, help);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (idx, bound) in self.buckets.iter().enumerate() {
+            let count = data.counts.get(idx).copied().unwrap_or(0);
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, data.count);
+        let _ = writeln!(out, "{}_sum {}", name, data.sum);
+        let _ = writeln!(out, "{}_count {}", name, data.count);
+    }
+}
+
+/// Record the result of yt-dlp execution. Successful runs are ignored.
+pub fn observe_ytdlp(output: &io::Result<Output>) {
+    let kind = match output {
+        Err(_) => "spawn",
+        Ok(output) if output.status.success() => return,
+        Ok(output) => classify_ytdlp_error(&String::from_utf8_lossy(&output.stderr)),
+    };
+    *YTDLP_FAILURES.lock().unwrap().entry(kind).or_insert(0) += 1;
+}
+
+fn classify_ytdlp_error(stderr: &str) -> &'static str {
+    if stderr.contains("HTTP Error 429") {
+        "rate_limited"
+    } else if stderr.contains("Sign in to confirm") {
+        "sign_in_required"
+    } else if stderr.contains("Private video") || stderr.contains("members-only") {
+        "private"
+    } else if stderr.contains("Video unavailable") || stderr.contains("not available") {
+        "unavailable"
+    } else if stderr.contains("Unsupported URL") {
+        "unsupported_url"
+    } else if stderr.contains("Unable to download") || stderr.contains("timed out") {
+        "network"
+    } else if stderr.contains("ffmpeg") || stderr.contains("Postprocessing") {
+        "postprocessing"
+    } else {
+        "other"
+    }
+}
+
+/// Render every metric in Prometheus text format.
+pub fn render(dbp: &DBPool) -> Result<String> {
+    let mut out = String::with_capacity(4096);
+
+    // Database state, connection is returned to the pool before reading the pool state
+    let conn = dbp.get()?;
+    render_db_state(&mut out, &conn)?;
+    drop(conn);
+
+    // Workers
+    let _ = writeln!(
+        out,
+        "# HELP tubarr_workers_active Worker threads running one-off tasks."
+    );
+    let _ = writeln!(out, "# TYPE tubarr_workers_active gauge");
+    let _ = writeln!(
+        out,
+        "tubarr_workers_active {}",
+        WORKERS_ACTIVE.load(Ordering::Relaxed)
+    );
+
+    // Downloads
+    let _ = writeln!(
+        out,
+        "# HELP tubarr_download_bytes_total Bytes of downloaded files moved to the media folder."
+    );
+    let _ = writeln!(out, "# TYPE tubarr_download_bytes_total counter");
+    let _ = writeln!(
+        out,
+        "tubarr_download_bytes_total {}",
+        DOWNLOAD_BYTES.load(Ordering::Relaxed)
+    );
+    DOWNLOAD_DURATION.render(
+        &mut out,
+        "tubarr_download_duration_seconds",
+        "Duration of completed video downloads.",
+    );
+
+    // yt-dlp failures
+    let _ = writeln!(
+        out,
+        "# HELP tubarr_ytdlp_failures_total Failed yt-dlp executions by error kind."
+    );
+    let _ = writeln!(out, "# TYPE tubarr_ytdlp_failures_total counter");
+    for (kind, count) in YTDLP_FAILURES.lock().unwrap().iter() {
+        let _ = writeln!(
+            out,
+            "tubarr_ytdlp_failures_total{{kind=\"{}\"}} {}",
+            kind, count
+        );
+    }
+
+    // Channel fetches
+    CHANNEL_FETCH_DURATION.render(
+        &mut out,
+        "tubarr_channel_fetch_duration_seconds",
+        "Duration of fetching the video list of a channel.",
+    );
+
+    // Database pool
+    let state = dbp.state();
+    let _ = writeln!(
+        out,
+        "# HELP tubarr_db_pool_connections Database pool connections by state."
+    );
+    let _ = writeln!(out, "# TYPE tubarr_db_pool_connections gauge");
+    let _ = writeln!(
+        out,
+        "tubarr_db_pool_connections{{state=\"idle\"}} {}",
+        state.idle_connections
+    );
+    let _ = writeln!(
+        out,
+        "tubarr_db_pool_connections{{state=\"in_use\"}} {}",
+        state.connections - state.idle_connections
+    );
+    let _ = writeln!(
+        out,
+        "# HELP tubarr_db_pool_max_connections Maximum size of the database pool."
+    );
+    let _ = writeln!(out, "# TYPE tubarr_db_pool_max_connections gauge");
+    let _ = writeln!(out, "tubarr_db_pool_max_connections {}", dbp.max_size());
+
+    Ok(out)
+}
+
+/// Gauges queried from the database.
+fn render_db_state(out: &mut String, conn: &Connection) -> Result<()> {
+    // Queue depth
+    let _ = writeln!(out, "# HELP tubarr_tasks Tasks in queue by state and type.");
+    let _ = writeln!(out, "# TYPE tubarr_tasks gauge");
+    let mut stmt = conn.prepare(
+        "SELECT task_state, task_type, COUNT(*) FROM tasks GROUP BY task_state, task_type",
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (task_state, task_type, count) = row?;
+        let _ = writeln!(
+            out,
+            "tubarr_tasks{{task_state=\"{}\",task_type=\"{}\"}} {}",
+            escape(&task_state),
+            escape(&task_type),
+            count
+        );
+    }
+
+    // Videos per channel
+    let _ = writeln!(
+        out,
+        "# HELP tubarr_channel_videos Known videos per channel."
+    );
+    let _ = writeln!(out, "# TYPE tubarr_channel_videos gauge");
+    let mut stmt = conn.prepare(
+        "SELECT c.domain, c.channel_name, COUNT(v.id)
+         FROM channels c LEFT JOIN videos v ON v.channel_id = c.id
+         GROUP BY c.id",
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, i64>(2)?,
+        ))
+    })?;
+    for row in rows {
+        let (domain, channel_name, count) = row?;
+        let _ = writeln!(
+            out,
+            "tubarr_channel_videos{{domain=\"{}\",channel=\"{}\"}} {}",
+            escape(&domain),
+            escape(&channel_name),
+            count
+        );
+    }
+
+    // Persistent tasks
+    let _ = writeln!(
+        out,
+        "# HELP tubarr_persistent_task_last_success_timestamp_seconds Last successful run of persistent background task."
+    );
+    let _ = writeln!(
+        out,
+        "# TYPE tubarr_persistent_task_last_success_timestamp_seconds gauge"
+    );
+    let mut stmt = conn.prepare(
+        "SELECT task_name, CAST(strftime('%s', last_success) AS INTEGER)
+         FROM tasks_persistent WHERE last_success IS NOT NULL",
+    )?;
+    let rows = stmt.query_map(params![], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+    })?;
+    for row in rows {
+        let (task_name, timestamp) = row?;
+        let _ = writeln!(
+            out,
+            "tubarr_persistent_task_last_success_timestamp_seconds{{task_name=\"{}\"}} {}",
+            escape(&task_name),
+            timestamp
+        );
+    }
+
+    Ok(())
+}
+
+/// Escape label value
+fn escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
@@ -0,0 +1,197 @@
+//! Outgoing notifications for task events.
+//!
+//! Events are matched against the notification_targets table by their event filters.
+//! Every delivery runs in its own thread and is retried a few times, so a slow or
+//! failing endpoint never blocks the taskrunner.
+
+use anyhow::Result;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use std::thread;
+use std::time::Duration;
+use tracing::{debug, error, warn};
+
+use crate::DBPool;
+
+mod presets;
+pub use presets::{deliver, KINDS};
+
+/// Delays between delivery attempts. Length of this is the retry count.
+const RETRY_DELAYS_SEC: [u64; 3] = [10, 60, 300];
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    DownloadDone {
+        url: String,
+        title: Option<String>,
+    },
+    DownloadFailed {
+        url: String,
+        title: Option<String>,
+    },
+    NewVideo {
+        channel: String,
+        title: String,
+        url: String,
+    },
+    Test,
+}
+
+impl Event {
+    /// Event name, as used in generic webhook payloads.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Event::DownloadDone { .. } => "download_done",
+            Event::DownloadFailed { .. } => "download_failed",
+            Event::NewVideo { .. } => "new_video",
+            Event::Test => "test",
+        }
+    }
+
+    /// notification_targets column which enables this event. None for events sent to everyone.
+    fn filter_column(&self) -> Option<&'static str> {
+        match self {
+            Event::DownloadDone { .. } => Some("on_download_done"),
+            Event::DownloadFailed { .. } => Some("on_download_fail"),
+            Event::NewVideo { .. } => Some("on_new_video"),
+            Event::Test => None,
+        }
+    }
+
+    pub fn title(&self) -> String {
+        match self {
+            Event::DownloadDone { .. } => "Download completed".to_string(),
+            Event::DownloadFailed { .. } => "Download failed".to_string(),
+            Event::NewVideo { channel, .. } => format!("New video from {}", channel),
+            Event::Test => "Tubarr test notification".to_string(),
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Event::DownloadDone { url, title } | Event::DownloadFailed { url, title } => {
+                title.clone().unwrap_or_else(|| url.clone())
+            }
+            Event::NewVideo { title, .. } => title.clone(),
+            Event::Test => "Notifications are working.".to_string(),
+        }
+    }
+
+    pub fn url(&self) -> Option<&str> {
+        match self {
+            Event::DownloadDone { url, .. }
+            | Event::DownloadFailed { url, .. }
+            | Event::NewVideo { url, .. } => Some(url),
+            Event::Test => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Target {
+    pub name: String,
+    pub kind: String,
+    pub url: String,
+    pub token: Option<String>,
+}
+
+/// Send event to every enabled target which has subscribed to it.
+/// Returns immediately, delivery happens in the background.
+pub fn send(dbp: DBPool, event: Event) {
+    let targets = match dbp.get() {
+        Ok(conn) => match get_targets(&conn, &event) {
+            Ok(targets) => targets,
+            Err(e) => {
+                error!("Failed to load notification targets: {:?}", e);
+                return;
+            }
+        },
+        Err(_) => {
+            error!("Error getting database connection.");
+            return;
+        }
+    };
+
+    for target in targets {
+        let event = event.clone();
+        thread::spawn(move || deliver_with_retries(target, event));
+    }
+}
+
+/// Get a single target by ID, regardless of its filters.
+pub fn get_target(conn: &PooledConnection<SqliteConnectionManager>, id: i64) -> Result<Target> {
+    let target = conn.query_row(
+        "SELECT name, kind, url, token FROM notification_targets WHERE id = ?1",
+        [id],
+        |row| {
+            Ok(Target {
+                name: row.get(0)?,
+                kind: row.get(1)?,
+                url: row.get(2)?,
+                token: row.get(3)?,
+            })
+        },
+    )?;
+    Ok(target)
+}
+
+fn get_targets(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    event: &Event,
+) -> Result<Vec<Target>> {
+    let sql = match event.filter_column() {
+        Some(column) => format!(
+            "SELECT name, kind, url, token FROM notification_targets WHERE enabled = 1 AND {} = 1",
+            column
+        ),
+        None => {
+            "SELECT name, kind, url, token FROM notification_targets WHERE enabled = 1".to_string()
+        }
+    };
+
+    let mut stmt = conn.prepare(&sql)?;
+    let targets = stmt
+        .query_map([], |row| {
+            Ok(Target {
+                name: row.get(0)?,
+                kind: row.get(1)?,
+                url: row.get(2)?,
+                token: row.get(3)?,
+            })
+        })?
+        .filter_map(|res| res.ok())
+        .collect();
+
+    Ok(targets)
+}
+
+fn deliver_with_retries(target: Target, event: Event) {
+    let mut attempt = 0;
+    loop {
+        match deliver(&target, &event) {
+            Ok(_) => {
+                debug!("Notification {} sent to {}", event.name(), target.name);
+                return;
+            }
+            Err(e) if attempt < RETRY_DELAYS_SEC.len() => {
+                warn!(
+                    "Notification {} to {} failed, retrying: {:?}",
+                    event.name(),
+                    target.name,
+                    e
+                );
+                thread::sleep(Duration::from_secs(RETRY_DELAYS_SEC[attempt]));
+                attempt += 1;
+            }
+            Err(e) => {
+                error!(
+                    "Notification {} to {} failed, giving up: {:?}",
+                    event.name(),
+                    target.name,
+                    e
+                );
+                return;
+            }
+        }
+    }
+}
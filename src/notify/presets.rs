@@ -0,0 +1,90 @@
+//! Payload templates for supported notification targets.
+
+use anyhow::{anyhow, Result};
+use serde_json::json;
+use std::time::Duration;
+
+use super::{Event, Target};
+
+/// Supported values for notification_targets.kind
+pub const KINDS: [&str; 5] = ["WEBHOOK", "DISCORD", "SLACK", "NTFY", "GOTIFY"];
+
+/// Deliver single event to single target, no retries.
+pub fn deliver(target: &Target, event: &Event) -> Result<()> {
+    let agent = ureq::AgentBuilder::new()
+        .timeout(Duration::from_secs(15))
+        .build();
+
+    let title = event.title();
+    let message = event.message();
+
+    let response = match target.kind.as_str() {
+        // Generic JSON webhook
+        "WEBHOOK" => {
+            let mut request = agent.post(&target.url);
+            if let Some(token) = &target.token {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+            request.send_json(json!({
+                "event": event.name(),
+                "title": title,
+                "message": message,
+                "url": event.url(),
+            }))
+        }
+        // Discord channel webhook URL
+        "DISCORD" => agent.post(&target.url).send_json(json!({
+            "username": "Tubarr",
+            "content": format_with_link(&format!("**{}**\n{}", title, message), event),
+        })),
+        // Slack incoming webhook URL
+        "SLACK" => agent.post(&target.url).send_json(json!({
+            "text": format_with_link(&format!("*{}*\n{}", title, message), event),
+        })),
+        // ntfy topic URL, eg. https://ntfy.sh/mytopic. Published as JSON to the server root.
+        "NTFY" => {
+            let (server, topic) = target
+                .url
+                .trim_end_matches('/')
+                .rsplit_once('/')
+                .ok_or_else(|| anyhow!("ntfy URL must be in form https://server/topic"))?;
+            let mut request = agent.post(server);
+            if let Some(token) = &target.token {
+                request = request.set("Authorization", &format!("Bearer {}", token));
+            }
+            request.send_json(json!({
+                "topic": topic,
+                "title": title,
+                "message": message,
+                "click": event.url(),
+            }))
+        }
+        // Gotify server URL, token is the application token
+        "GOTIFY" => {
+            let token = target
+                .token
+                .as_deref()
+                .ok_or_else(|| anyhow!("Gotify target requires an application token"))?;
+            agent
+                .post(&format!("{}/message", target.url.trim_end_matches('/')))
+                .set("X-Gotify-Key", token)
+                .send_json(json!({
+                    "title": title,
+                    "message": format_with_link(&message, event),
+                    "priority": 5,
+                }))
+        }
+        kind => return Err(anyhow!("Unknown notification target kind: {}", kind)),
+    };
+
+    response
+        .map(|_| ())
+        .map_err(|e| anyhow!("Delivery to {} failed: {}", target.name, e))
+}
+
+fn format_with_link(text: &str, event: &Event) -> String {
+    match event.url() {
+        Some(url) => format!("{}\n{}", text, url),
+        None => text.to_string(),
+    }
+}
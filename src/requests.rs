@@ -0,0 +1,178 @@
+//! Video requests and their approval.
+//!
+//! Admin requests become VIDEO-DOWNLOAD tasks right away. Requests from other users wait
+//! as PENDING until an admin approves or denies them. Every request is recorded with the
+//! user who made it.
+
+use anyhow::{anyhow, Context, Result};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::auth::{Role, User};
+use crate::database;
+
+/// What happened to a new request.
+#[derive(Debug, PartialEq)]
+pub enum RequestOutcome {
+    /// Download task was created.
+    Queued { request_id: i64, task_id: i64 },
+    /// Waiting for admin approval.
+    Pending { request_id: i64 },
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct VideoRequest {
+    pub id: i64,
+    pub url: String,
+    pub username: Option<String>,
+    pub state: String,
+    pub task_id: Option<i64>,
+    pub decided_by: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Requests are shown as links, so only web URLs are taken.
+pub fn is_valid_url(url: &str) -> bool {
+    url::Url::parse(url).is_ok_and(|url| url.scheme() == "http" || url.scheme() == "https")
+}
+
+/// Request video download on behalf of the user.
+pub fn request_video(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    user: &User,
+    url: &str,
+) -> Result<RequestOutcome> {
+    if !is_valid_url(url) {
+        return Err(anyhow!("Not a http:// or https:// URL: {}", url));
+    }
+    if user.role != Role::Admin {
+        conn.execute(
+            "INSERT INTO video_requests (url, user_id, state) VALUES (?1, ?2, 'PENDING')",
+            params![url, user.id],
+        )
+        .context("Failed to insert video request")?;
+        return Ok(RequestOutcome::Pending {
+            request_id: conn.last_insert_rowid(),
+        });
+    }
+
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let task_id = enqueue_download(&tx, url, &user.id)?;
+    tx.execute(
+        "INSERT INTO video_requests (url, user_id, state, task_id, decided_by)
+            VALUES (?1, ?2, 'APPROVED', ?3, ?2)",
+        params![url, user.id, task_id],
+    )
+    .context("Failed to insert video request")?;
+    let request_id = tx.last_insert_rowid();
+    tx.commit()?;
+
+    Ok(RequestOutcome::Queued {
+        request_id,
+        task_id,
+    })
+}
+
+/// Approve pending request. Returns ID of the created download task, None if there was no
+/// such pending request.
+pub fn approve_request(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    admin: &User,
+    request_id: i64,
+) -> Result<Option<i64>> {
+    // Immediate transaction takes the write lock first, so two approvals of the same request
+    // can't both see it pending
+    let tx = Transaction::new_unchecked(conn, TransactionBehavior::Immediate)?;
+    let Some((url, requested_by)) = get_pending(&tx, request_id)? else {
+        return Ok(None);
+    };
+    let approved = tx.execute(
+        "UPDATE video_requests
+            SET state = 'APPROVED', decided_by = ?1, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?2 AND state = 'PENDING'",
+        params![admin.id, request_id],
+    )?;
+    if approved != 1 {
+        return Ok(None);
+    }
+
+    let task_id = enqueue_download(&tx, &url, &requested_by)?;
+    tx.execute(
+        "UPDATE video_requests SET task_id = ?1 WHERE id = ?2",
+        params![task_id, request_id],
+    )?;
+    tx.commit()?;
+
+    Ok(Some(task_id))
+}
+
+/// Deny pending request. Returns false if there was no such pending request.
+pub fn deny_request(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    admin: &User,
+    request_id: i64,
+) -> Result<bool> {
+    let denied = conn.execute(
+        "UPDATE video_requests
+            SET state = 'DENIED', decided_by = ?1, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?2 AND state = 'PENDING'",
+        params![admin.id, request_id],
+    )?;
+
+    Ok(denied > 0)
+}
+
+/// List requests, newest first. Optionally only the ones made by the user, or in the state.
+pub fn get_requests(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    user_id: Option<&[u8]>,
+    state: Option<&str>,
+) -> Result<Vec<VideoRequest>> {
+    let mut stmt = conn.prepare(
+        "SELECT r.id, r.url, ul.username, r.state, r.task_id, dl.username,
+                r.created_at, r.updated_at
+            FROM video_requests r
+            LEFT JOIN users_local ul ON ul.user_id = r.user_id
+            LEFT JOIN users_local dl ON dl.user_id = r.decided_by
+            WHERE (?1 IS NULL OR r.user_id = ?1) AND (?2 IS NULL OR r.state = ?2)
+            ORDER BY r.id DESC",
+    )?;
+
+    let requests = stmt
+        .query_map(params![user_id, state], |row| {
+            Ok(VideoRequest {
+                id: row.get(0)?,
+                url: row.get(1)?,
+                username: row.get(2)?,
+                state: row.get(3)?,
+                task_id: row.get(4)?,
+                decided_by: row.get(5)?,
+                created_at: row.get(6)?,
+                updated_at: row.get(7)?,
+            })
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    Ok(requests)
+}
+
+/// Create VIDEO-DOWNLOAD task for the URL, and mark the video requested if it's known.
+pub fn enqueue_download(conn: &Connection, url: &str, requested_by: &[u8]) -> Result<i64> {
+    database::mark_video_requested(conn, url)?;
+    database::enqueue_url_task(conn, "VIDEO-DOWNLOAD", url, requested_by)
+}
+
+fn get_pending(conn: &Connection, request_id: i64) -> Result<Option<(String, Vec<u8>)>> {
+    let pending = conn
+        .query_row(
+            "SELECT url, user_id FROM video_requests WHERE id = ?1 AND state = 'PENDING'",
+            params![request_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(pending)
+}
@@ -0,0 +1,277 @@
+//! Subscription lists: OPML files from feed readers and `subscriptions.csv` from Google
+//! Takeout are imported as CHANNEL-ADD tasks, and followed channels are exported as OPML.
+//!
+//! Only YouTube channels are supported. Entries without a channel ID (playlists, other
+//! sites) are skipped.
+
+use anyhow::{anyhow, Result};
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+use utoipa::ToSchema;
+
+use crate::database;
+
+static CHANNEL_ID: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^UC[A-Za-z0-9_-]{22}$").unwrap());
+/// Channel ID in feed or channel URL.
+static CHANNEL_ID_IN_URL: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:channel_id=|/channel/)(UC[A-Za-z0-9_-]{22})(?:$|[^A-Za-z0-9_-])").unwrap()
+});
+static OUTLINE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<outline\b[^>]*>").unwrap());
+static ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([A-Za-z_:]+)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Subscription {
+    pub channel_id: String,
+    pub title: String,
+    pub url: String,
+    /// Channel is already followed, or waiting in the queue.
+    pub followed: bool,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Preview {
+    pub subscriptions: Vec<Subscription>,
+    /// Entries that are not YouTube channels.
+    pub skipped: usize,
+}
+
+/// Parse OPML or Takeout CSV and check which channels are already followed.
+pub fn preview(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    contents: &str,
+) -> Result<Preview> {
+    let contents = contents.trim_start_matches('\u{feff}').trim_start();
+    let (entries, skipped) = match contents.starts_with('<') {
+        true => parse_opml(contents),
+        false => parse_csv(contents),
+    };
+
+    let mut seen = HashSet::new();
+    let mut subscriptions = Vec::with_capacity(entries.len());
+    for (channel_id, title) in entries {
+        if !seen.insert(channel_id.clone()) {
+            continue;
+        }
+        let url = channel_url(&channel_id);
+        subscriptions.push(Subscription {
+            followed: is_followed(conn, &channel_id, &url)?,
+            channel_id,
+            title,
+            url,
+        });
+    }
+
+    if subscriptions.is_empty() && skipped == 0 {
+        return Err(anyhow!(
+            "No subscriptions found, expected OPML or subscriptions.csv"
+        ));
+    }
+    Ok(Preview {
+        subscriptions,
+        skipped,
+    })
+}
+
+/// Queue CHANNEL-ADD for the channels that are not followed yet. Returns how many were queued.
+pub fn import(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    channel_ids: &[String],
+    requested_by: &[u8],
+) -> Result<usize> {
+    if let Some(invalid) = channel_ids.iter().find(|id| !is_channel_id(id)) {
+        return Err(anyhow!("Invalid channel ID: {}", invalid));
+    }
+
+    let mut queued = 0;
+    for channel_id in channel_ids.iter().collect::<HashSet<_>>() {
+        let url = channel_url(channel_id);
+        if is_followed(conn, channel_id, &url)? {
+            continue;
+        }
+        database::enqueue_url_task(conn, "CHANNEL-ADD", &url, requested_by)?;
+        queued += 1;
+    }
+    Ok(queued)
+}
+
+/// Followed channels as OPML, with YouTube RSS feeds.
+pub fn export_opml(conn: &PooledConnection<SqliteConnectionManager>) -> Result<String> {
+    let mut channels = database::get_channels(conn)?;
+    channels.sort_by(|a, b| a.channel_name.cmp(&b.channel_name));
+
+    let mut opml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <opml version=\"1.1\">\n\
+         \x20 <head><title>Tubarr subscriptions</title></head>\n\
+         \x20 <body>\n\
+         \x20   <outline text=\"YouTube Subscriptions\" title=\"YouTube Subscriptions\">\n",
+    );
+    for channel in channels {
+        let name = escape_xml(&channel.channel_name);
+        opml.push_str(&format!(
+            "      <outline text=\"{name}\" title=\"{name}\" type=\"rss\" \
+             xmlUrl=\"https://www.youtube.com/feeds/videos.xml?channel_id={id}\" \
+             htmlUrl=\"{url}\"/>\n",
+            name = name,
+            id = escape_xml(&channel.channel_id),
+            url = escape_xml(&channel_url(&channel.channel_id)),
+        ));
+    }
+    opml.push_str("    </outline>\n  </body>\n</opml>\n");
+    Ok(opml)
+}
+
+fn channel_url(channel_id: &str) -> String {
+    format!("https://www.youtube.com/channel/{}", channel_id)
+}
+
+fn is_channel_id(value: &str) -> bool {
+    CHANNEL_ID.is_match(value)
+}
+
+/// Followed, or already waiting in the queue from earlier import.
+fn is_followed(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    channel_id: &str,
+    url: &str,
+) -> Result<bool> {
+    Ok(
+        database::get_channel(conn, "youtube.com", channel_id)?.is_some()
+            || database::find_pending_url_task(conn, "CHANNEL-ADD", url)?.is_some(),
+    )
+}
+
+/// Channel IDs and titles from OPML outlines with feed or channel URL, and count of
+/// skipped feed outlines.
+fn parse_opml(contents: &str) -> (Vec<(String, String)>, usize) {
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+
+    for outline in OUTLINE.find_iter(contents) {
+        let mut title = None;
+        let mut channel_id = None;
+        let mut is_feed = false;
+        for attr in ATTRIBUTE.captures_iter(outline.as_str()) {
+            let value = unescape_xml(attr.get(2).or(attr.get(3)).map_or("", |v| v.as_str()));
+            match attr[1].to_ascii_lowercase().as_str() {
+                "title" => title = Some(value),
+                "text" if title.is_none() => title = Some(value),
+                "xmlurl" | "htmlurl" => {
+                    is_feed = true;
+                    if channel_id.is_none() {
+                        channel_id = CHANNEL_ID_IN_URL
+                            .captures(&value)
+                            .map(|captures| captures[1].to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        match channel_id {
+            Some(channel_id) => entries.push((channel_id, title.unwrap_or_default())),
+            // Folders have no URLs, they are not counted
+            None if is_feed => skipped += 1,
+            None => {}
+        }
+    }
+
+    (entries, skipped)
+}
+
+/// Takeout subscriptions.csv: Channel Id,Channel Url,Channel Title. Header names are
+/// localized, so the columns are read by position.
+fn parse_csv(contents: &str) -> (Vec<(String, String)>, usize) {
+    let mut entries = Vec::new();
+    let mut skipped = 0;
+
+    for line in contents.lines().skip(1) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(line);
+        let channel_id = fields.first().map_or("", |id| id.trim());
+        match is_channel_id(channel_id) {
+            true => entries.push((
+                channel_id.to_string(),
+                fields.get(2).map_or("", |title| title.trim()).to_string(),
+            )),
+            false => skipped += 1,
+        }
+    }
+
+    (entries, skipped)
+}
+
+/// Fields of one CSV line, with double quotes for fields containing commas.
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape_xml(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else { break };
+
+        let decoded = match &rest[1..end] {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            entity => entity
+                .strip_prefix("#x")
+                .map(|hex| u32::from_str_radix(hex, 16))
+                .or_else(|| entity.strip_prefix('#').map(str::parse))
+                .and_then(|code| code.ok())
+                .and_then(char::from_u32),
+        };
+        match decoded {
+            Some(c) => {
+                result.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
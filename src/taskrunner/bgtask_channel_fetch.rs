@@ -1,59 +1,34 @@
 use std::process::Command;
+use std::time::Instant;
 
+use anyhow::{Context, Result};
 use chrono::NaiveDate;
-use r2d2::PooledConnection;
-use r2d2_sqlite::SqliteConnectionManager;
-use rusqlite::params;
 use tracing::{debug, error, event, info, trace, warn};
 
-use super::update_bgtask_exec_time;
+use crate::database;
+use crate::events;
+use crate::metrics;
+use crate::notify;
 use crate::DBPool;
 
 /// Persistent background task for fetching new videos for channels
-pub fn bg_channel_fetch(task_id: isize, dbp: DBPool) {
-    debug!("Started background task: bg_channel_fetch");
-
-    // Connect to the database and delete old completed or failed tasks
-    let conn = match dbp.get() {
-        Ok(conn) => conn,
-        Err(_) => {
-            error!("Failed to get database connection");
-            return;
-        }
-    };
-
-    // Update this persistent task
-    update_bgtask_exec_time(task_id, &conn);
+pub fn bg_channel_fetch(dbp: DBPool) -> Result<()> {
+    // Connect to the database
+    let conn = dbp.get().context("Failed to get database connection")?;
 
     // Get every channel videos url to fetch
-    let mut stmt = match conn.prepare("SELECT url FROM channels") {
-        Ok(stmt) => stmt,
-        Err(err) => {
-            error!("Failed to prepare statement: {:?}", err);
-            return;
-        }
-    };
-
-    let urls_result: rusqlite::Result<Vec<String>> = stmt
-        .query_map([], |row| row.get(0))
-        .and_then(|mapped_rows| mapped_rows.collect());
-
-    let urls = match urls_result {
-        Ok(urls) => urls
-            .into_iter()
-            .map(|url| format!("{}/videos", url))
-            .collect::<Vec<String>>(),
-        Err(err) => {
-            error!("Failed to query channels: {:?}", err);
-            return;
-        }
-    };
+    let urls = database::get_channels(&conn)
+        .context("Failed to query channels")?
+        .into_iter()
+        .map(|channel| format!("{}/videos", channel.url))
+        .collect::<Vec<String>>();
 
     // Download the urls
     for url in &urls {
         debug!("bg_channel_fetch: {}", url);
 
         // Get videos information
+        let started = Instant::now();
         let output = Command::new("yt-dlp")
         .arg("--skip-download")
         .arg("--extractor-args")
@@ -66,6 +41,8 @@ pub fn bg_channel_fetch(task_id: isize, dbp: DBPool) {
         .arg("--lazy-playlist")
         .arg(&url)
         .output();
+        metrics::observe_ytdlp(&output);
+        metrics::CHANNEL_FETCH_DURATION.observe(started.elapsed().as_secs_f64());
 
         if output.is_err() {
             debug!("Failed yt-dlp fetch");
@@ -122,58 +99,54 @@ pub fn bg_channel_fetch(task_id: isize, dbp: DBPool) {
                 }
             };
 
-            // Check if the channel exists in the channels table
-            let mut channel_exists_stmt =
-                match conn.prepare("SELECT id FROM channels WHERE channel_id = ?") {
-                    Ok(stmt) => stmt,
-                    Err(e) => {
-                        error!("Failed to prepare channel check statement: {:?}", e);
-                        continue;
-                    }
-                };
-            let channel_exists_result = channel_exists_stmt
-                .query_row(rusqlite::params![channel_id], |row| row.get::<_, i32>(0));
-
-            let channel_id_db: i32 = match channel_exists_result {
-                Ok(id) => id,
-                Err(_) => {
+            let channel = match database::get_channel(&conn, "youtube.com", &channel_id) {
+                Ok(Some(channel)) => channel,
+                Ok(None) => {
                     error!("Channel doesn't exist: {}, skipping.", channel_id);
                     continue;
                 }
+                Err(e) => {
+                    error!("Failed to get channel: {:#}", e);
+                    continue;
+                }
             };
 
-            // Prepare SQL insertion
-            let sql = "INSERT INTO videos (
-                       channel_id, domain, url, name, video_id, 
-                       is_requested, is_downloaded, release_date, release_date_estimate
-                   ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-                   ON CONFLICT(domain, video_id) DO UPDATE SET 
-                       updated_at = CURRENT_TIMESTAMP";
-
-            // Execute the prepared SQL statement
-            let mut stmt = match conn.prepare(sql) {
-                Ok(s) => s,
+            // Only videos not seen before are notified
+            let video = database::NewVideo {
+                channel_id: Some(channel.id),
+                domain: "youtube.com",
+                url: &webpage_url,
+                name: &title,
+                video_id: &video_id,
+                release_date: None,
+                release_date_estimate: Some(release_date_estimate),
+            };
+            let is_new = match database::upsert_video(&conn, &video) {
+                Ok(is_new) => is_new,
                 Err(e) => {
-                    error!("Failed to prepare statement: {:?}", e);
+                    error!("Failed to save video: {:#}", e);
                     continue;
                 }
             };
 
-            if let Err(e) = stmt.execute(rusqlite::params![
-                channel_id_db,         // channel_id (from channels table)
-                "youtube.com",         // domain (fixed as "youtube.com")
-                webpage_url,           // url (now taken from row[2])
-                title,                 // name (video title from row[4])
-                video_id,              // video_id (from row[5])
-                0,                     // is_requested (default to 0)
-                0,                     // is_downloaded (default to 0)
-                None::<NaiveDate>,     // release_date set to NULL
-                release_date_estimate  // release_date_estimate (using same date)
-            ]) {
-                error!("Failed to execute insert: {:?}", e);
+            if is_new {
+                events::publish(events::Event::VideoDiscovered {
+                    channel_id: channel.id,
+                    video_id: video_id.clone(),
+                    title: title.clone(),
+                    url: webpage_url.clone(),
+                });
+                notify::send(
+                    dbp.clone(),
+                    notify::Event::NewVideo {
+                        channel: channel_name,
+                        title,
+                        url: webpage_url,
+                    },
+                );
             }
         }
     }
 
-    debug!("Completed background task: bg_channel_fetch");
+    Ok(())
 }
@@ -0,0 +1,27 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::{debug, info};
+
+use crate::config;
+use crate::database;
+use crate::DBPool;
+
+/// Persistent background task for writing rotated database backups to backup_path
+pub fn db_backup(dbp: DBPool) -> Result<()> {
+    let conn = dbp.get().context("Failed to get database connection")?;
+
+    let values = config::get_values(&conn)?;
+    let path = values.get("backup_path").map(String::as_str).unwrap_or("");
+    if path.is_empty() {
+        debug!("db_backup: backup_path not set, skipping");
+        return Ok(());
+    }
+    let keep = values
+        .get("backup_keep")
+        .and_then(|keep| keep.parse().ok())
+        .unwrap_or(7);
+
+    let backup = database::backup_rotated(&conn, Path::new(path), keep)?;
+    info!("Database backed up to {}", backup.display());
+    Ok(())
+}
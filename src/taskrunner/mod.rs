@@ -10,16 +10,14 @@
 
 use anyhow::Result;
 use bgtask_channel_fetch::bg_channel_fetch;
+use bgtask_db_backup::db_backup;
 use bgtask_db_clean::db_clean_tasks;
-use chrono::NaiveDateTime;
 use core::{str, time};
-use r2d2::PooledConnection;
-use r2d2_sqlite::SqliteConnectionManager;
 use rand::Rng;
-use rusqlite::params;
 use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
+use std::sync::atomic::AtomicI64;
 use std::sync::mpsc::channel;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
@@ -27,17 +25,27 @@ use std::thread;
 use tldextract::{TldExtractor, TldOption};
 use tracing::{debug, error, event, info, trace, warn};
 
+use super::config;
+use super::database;
+use super::metrics;
+use super::notify;
 use super::DBPool;
 use super::FLAG_SHUTDOWN;
 
 mod bgtask_channel_fetch;
+mod bgtask_db_backup;
 mod bgtask_db_clean;
+pub mod scheduler;
 mod task_channel;
 mod task_download;
 
+/// Unix timestamp of the last completed taskrunner loop. Zero before the first loop.
+pub static HEARTBEAT: AtomicI64 = AtomicI64::new(0);
+
 pub fn run(dbp: DBPool) {
     let (result_tx, result_rx): (Sender<TaskResult>, Receiver<TaskResult>) = channel();
-    let conf = match get_configuration(dbp.clone()) {
+    let mut conf_generation = config_generation(&dbp);
+    let mut conf = match get_configuration(dbp.clone()) {
         Ok(conf) => Arc::from(conf),
         Err(_) => {
             error!("Could not load configuration");
@@ -55,6 +63,19 @@ pub fn run(dbp: DBPool) {
             rand::thread_rng().gen_range(2..8),
         ));
 
+        // Pick up saved configuration changes, running workers keep the old values
+        let generation = config_generation(&dbp);
+        if generation != conf_generation {
+            conf_generation = generation;
+            match get_configuration(dbp.clone()) {
+                Ok(new_conf) => {
+                    info!("Configuration reloaded");
+                    conf = Arc::from(new_conf);
+                }
+                Err(e) => error!("Could not reload configuration: {:#}", e),
+            }
+        }
+
         // Check for one-off tasks
         if concurrency < concurrency_limit {
             if let Ok(new_tasks) = get_new_tasks(dbp.clone()) {
@@ -63,13 +84,13 @@ pub fn run(dbp: DBPool) {
                         "VIDEO-DOWNLOAD" => {
                             debug!("VIDEO-DOWNLOAD: {:?}", task);
                             concurrency += 1;
-                            mark_task_wip(dbp.clone(), task.task_id);
+                            mark_task(dbp.clone(), task.id as isize, "WIP");
                             let thrd_conf = conf.clone();
                             let thrd_tx = result_tx.clone();
                             let thrd_dbp = dbp.clone();
                             thread::spawn(move || {
                                 task_download::worker(
-                                    task.task_id,
+                                    task.id as isize,
                                     task.task_data,
                                     thrd_conf,
                                     thrd_tx,
@@ -80,13 +101,13 @@ pub fn run(dbp: DBPool) {
                         "CHANNEL-ADD" => {
                             debug!("CHANNEL-ADD: {:?}", task);
                             concurrency += 1;
-                            mark_task_wip(dbp.clone(), task.task_id);
+                            mark_task(dbp.clone(), task.id as isize, "WIP");
                             let thrd_conf = conf.clone();
                             let thrd_tx = result_tx.clone();
                             let thrd_dbp = dbp.clone();
                             thread::spawn(move || {
                                 task_channel::add(
-                                    task.task_id,
+                                    task.id as isize,
                                     task.task_data,
                                     thrd_conf,
                                     thrd_tx,
@@ -97,13 +118,13 @@ pub fn run(dbp: DBPool) {
                         "CHANNEL-FETCH" => {
                             debug!("CHANNEL-FETCH: {:?}", task);
                             concurrency += 1;
-                            mark_task_wip(dbp.clone(), task.task_id);
+                            mark_task(dbp.clone(), task.id as isize, "WIP");
                             let thrd_conf = conf.clone();
                             let thrd_tx = result_tx.clone();
                             let thrd_dbp = dbp.clone();
                             thread::spawn(move || {
                                 task_channel::fetch(
-                                    task.task_id,
+                                    task.id as isize,
                                     task.task_data,
                                     thrd_conf,
                                     thrd_tx,
@@ -113,7 +134,8 @@ pub fn run(dbp: DBPool) {
                         }
                         _ => {
                             error!("Unknown task type: {:?}", task);
-                            mark_task_error(dbp.clone(), task.task_id);
+                            let error = format!("Unknown task type: {}", task.task_type);
+                            fail_task(dbp.clone(), task.id as isize, &error);
                         }
                     }
                 }
@@ -122,19 +144,21 @@ pub fn run(dbp: DBPool) {
 
         // Check for persisten background tasks
         if let Ok(activated_tasks) = get_new_persistent_tasks(dbp.clone()) {
+            let bgtasks = bgtasks_as_list();
             for task in activated_tasks {
-                match task.task_name.as_str() {
-                    "DB-CLEAN-TASKS" => {
-                        debug!("RUN BG TASK: DB-CLEAN-TASKS");
-                        let thrd_dbp = dbp.clone();
-                        thread::spawn(move || db_clean_tasks(task.task_id, thrd_dbp));
+                match bgtasks.iter().find(|(name, _)| *name == task.task_name) {
+                    Some((name, _)) if !scheduler::try_start(name) => {
+                        debug!("BG TASK STILL RUNNING: {}", name);
                     }
-                    "BG-CHANNEL-FETCH" => {
-                        debug!("RUN BG TASK: BG-CHANNEL-FETCH");
+                    Some((name, bgtask)) => {
+                        debug!("RUN BG TASK: {}", name);
+                        let bgtask = *bgtask;
                         let thrd_dbp = dbp.clone();
-                        thread::spawn(move || bg_channel_fetch(task.task_id, thrd_dbp));
+                        thread::spawn(move || {
+                            scheduler::execute(task.task_id, task.task_name, bgtask, thrd_dbp)
+                        });
                     }
-                    _ => error!("Unknown persistent task: {:?}", task),
+                    None => error!("Unknown persistent task: {:?}", task),
                 }
             }
         }
@@ -144,11 +168,23 @@ pub fn run(dbp: DBPool) {
             concurrency -= 1;
             debug!("TASK RESULT: {:?}", result);
             match result {
-                TaskResult::Ok(id) => mark_task_done(dbp.clone(), id),
-                TaskResult::Err(id, errcode) => mark_task_error(dbp.clone(), id),
+                TaskResult::Ok(id) => {
+                    mark_task(dbp.clone(), id, "DONE");
+                    notify_task_result(dbp.clone(), id, true);
+                }
+                TaskResult::Err(id, error) => {
+                    fail_task(dbp.clone(), id, &error);
+                    notify_task_result(dbp.clone(), id, false);
+                }
             }
         }
 
+        metrics::WORKERS_ACTIVE.store(concurrency, std::sync::atomic::Ordering::Relaxed);
+        HEARTBEAT.store(
+            chrono::Utc::now().timestamp(),
+            std::sync::atomic::Ordering::Relaxed,
+        );
+
         // Check for shutdown signal
         if FLAG_SHUTDOWN.load(std::sync::atomic::Ordering::Relaxed) == true {
             info!("Shutdown requested");
@@ -160,166 +196,146 @@ pub fn run(dbp: DBPool) {
     // Do we need to join tasks, they can run for long time?
 }
 
-#[derive(Debug)]
-struct TaskRaw {
-    task_id: isize,
-    task_type: String,
-    task_data: String,
-    task_state: String,
+/// Configuration generation, None if it could not be read. Reload is then tried again on the
+/// next loop.
+fn config_generation(dbp: &DBPool) -> Option<i64> {
+    match dbp
+        .get()
+        .map_err(anyhow::Error::from)
+        .and_then(|conn| config::generation(&conn))
+    {
+        Ok(generation) => Some(generation),
+        Err(e) => {
+            error!("Could not read configuration generation: {:#}", e);
+            None
+        }
+    }
 }
 
 fn get_configuration(dbp: DBPool) -> Result<HashMap<String, String>> {
     let conn = dbp
         .get()
         .expect("Failed to get database connection from pool");
-
-    // Query the configuration table and load data into a HashMap
-    let mut config_map = HashMap::new();
-    let mut stmt = conn.prepare("SELECT key, value FROM app_configuration")?;
-
-    let config_iter = stmt.query_map([], |row| {
-        let key: String = row.get(0)?;
-        let value: String = row.get(1)?;
-        Ok((key, value))
-    })?;
-
-    // Collect results into the HashMap
-    for config in config_iter {
-        let (key, value) = config?;
-        config_map.insert(key, value);
-    }
-
-    Ok(config_map)
+    config::get_values(&conn)
 }
 
-fn get_new_tasks(dbp: DBPool) -> Result<Vec<TaskRaw>> {
+fn get_new_tasks(dbp: DBPool) -> Result<Vec<database::Task>> {
     let conn = dbp
         .get()
         .expect("Failed to get database connection from pool");
-
-    // Prepare SQL statement to select all tasks where task_state is "NEW"
-    let mut stmt = conn
-        .prepare("SELECT id, task_type, task_data, task_state FROM tasks WHERE task_state = ?1")?;
-
-    // Query for all "NEW" tasks and map each row to a TaskRaw instance
-    let tasks = stmt
-        .query_map(params!["WAIT"], |row| {
-            Ok(TaskRaw {
-                task_id: row.get(0)?,
-                task_type: row.get(1)?,
-                task_data: row.get(2)?,
-                task_state: row.get(3)?,
-            })
-        })?
-        .filter_map(|res| res.ok())
-        .collect();
-
-    Ok(tasks)
+    database::get_tasks(&conn, Some("WAIT"))
 }
 
-#[derive(Debug)]
-struct PersistentTaskRaw {
-    task_id: isize,
-    task_name: String,
-    last_exec: NaiveDateTime,
-    delay_sec: isize,
+/// Background task function. Errors are recorded to tasks_persistent.
+pub type BgTaskFn = fn(DBPool) -> Result<()>;
+
+/// Return a static list of persistent background tasks, keyed by task_name.
+/// New background jobs are plugged in here and inserted to tasks_persistent by DB upgrade.
+pub fn bgtasks_as_list() -> Vec<(&'static str, BgTaskFn)> {
+    vec![
+        ("DB-CLEAN-TASKS", db_clean_tasks),
+        ("BG-CHANNEL-FETCH", bg_channel_fetch),
+        ("DB-BACKUP", db_backup),
+    ]
 }
 
-fn get_new_persistent_tasks(dbp: DBPool) -> Result<Vec<PersistentTaskRaw>> {
+fn get_new_persistent_tasks(dbp: DBPool) -> Result<Vec<scheduler::PersistentTask>> {
     let conn = dbp
         .get()
         .expect("Failed to get database connection from pool");
 
-    // Prepare SQL statement to select all tasks where last_exec + delay_sec < current time
-    let mut stmt =
-        conn.prepare("SELECT 
-                            id, 
-                            task_name, 
-                            last_exec, 
-                            delay_sec
-                        FROM tasks_persistent
-                        WHERE (CAST(strftime('%s', last_exec) AS INTEGER) + delay_sec) < CAST(strftime('%s', 'now') AS INTEGER)
-                    ")?;
-
-    // Execute the query and get an iterator over the rows
-    let tasks = stmt
-        .query_map(params![], |row| {
-            Ok(PersistentTaskRaw {
-                task_id: row.get(0)?,
-                task_name: row.get(1)?,
-                last_exec: row.get(2)?, // Ensure this is in NaiveDateTime format
-                delay_sec: row.get(3)?,
-            })
-        })?
-        .filter_map(|res| res.ok())
+    let now = chrono::Utc::now().naive_utc();
+    let tasks = scheduler::get_persistent_tasks(&conn)?
+        .into_iter()
+        .filter(|task| task.is_due(now))
         .collect();
 
     Ok(tasks)
 }
 
-fn mark_task_wip(dbp: DBPool, task_id: isize) {
-    if let Ok(conn) = dbp.get() {
-        // Prepare the SQL statement to update the task
-        if let Err(e) = conn.execute(
-            "UPDATE tasks SET task_state = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
-            params!["WIP", task_id],
-        ) {
-            error!("Error updating task state: {}", e); // Log the error
+fn mark_task(dbp: DBPool, task_id: isize, task_state: &str) {
+    let conn = match dbp.get() {
+        Ok(conn) => conn,
+        Err(_) => {
+            error!("Error getting database connection.");
             return;
         }
+    };
 
-        debug!("Task with ID {} marked as WIP.", task_id);
-    } else {
-        error!("Error getting database connection."); // Log connection error
+    match database::update_task_state(&conn, task_id as i64, task_state) {
+        Ok(_) => debug!("Task with ID {} marked as {}.", task_id, task_state),
+        Err(e) => error!("Error updating task state: {:#}", e),
     }
 }
 
-fn mark_task_done(dbp: DBPool, task_id: isize) {
-    if let Ok(conn) = dbp.get() {
-        // Prepare the SQL statement to update the task
-        if let Err(e) = conn.execute(
-            "UPDATE tasks SET task_state = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
-            params!["DONE", task_id],
-        ) {
-            error!("Error updating task state: {}", e); // Log the error
+fn fail_task(dbp: DBPool, task_id: isize, error: &str) {
+    let conn = match dbp.get() {
+        Ok(conn) => conn,
+        Err(_) => {
+            error!("Error getting database connection.");
             return;
         }
+    };
 
-        debug!("Task with ID {} marked as DONE.", task_id);
-    } else {
-        error!("Error getting database connection."); // Log connection error
+    match database::fail_task(&conn, task_id as i64, error) {
+        Ok(_) => debug!("Task with ID {} failed: {}", task_id, error),
+        Err(e) => error!("Error updating task state: {:#}", e),
     }
 }
 
-fn mark_task_error(dbp: DBPool, task_id: isize) {
-    if let Ok(conn) = dbp.get() {
-        // Prepare the SQL statement to update the task
-        if let Err(e) = conn.execute(
-            "UPDATE tasks SET task_state = ?1, updated_at = CURRENT_TIMESTAMP WHERE id = ?2",
-            params!["ERR", task_id],
-        ) {
-            error!("Error updating task state: {}", e); // Log the error
+/// Send notification of finished VIDEO-DOWNLOAD task. Other task types are ignored.
+fn notify_task_result(dbp: DBPool, task_id: isize, success: bool) {
+    let conn = match dbp.get() {
+        Ok(conn) => conn,
+        Err(_) => {
+            error!("Error getting database connection.");
             return;
         }
+    };
 
-        debug!("Task with ID {} marked as ERROR.", task_id);
-    } else {
-        error!("Error getting database connection."); // Log connection error
-    }
+    let data = match database::get_task(&conn, task_id as i64) {
+        Ok(Some(task)) if task.task_type == "VIDEO-DOWNLOAD" => {
+            match serde_json::from_str::<task_download::TaskDownloadData>(&task.task_data) {
+                Ok(data) => data,
+                Err(_) => return,
+            }
+        }
+        _ => return,
+    };
+
+    let title = database::get_video_by_url(&conn, &data.url)
+        .ok()
+        .flatten()
+        .map(|video| video.name);
+
+    let event = match success {
+        true => notify::Event::DownloadDone {
+            url: data.url,
+            title,
+        },
+        false => notify::Event::DownloadFailed {
+            url: data.url,
+            title,
+        },
+    };
+    notify::send(dbp.clone(), event);
 }
 
 /// Result of task. Payload is the ID of the task.
 #[derive(Debug)]
 pub enum TaskResult {
-    Ok(isize),         // ID
-    Err(isize, isize), // ID, ERRCODE
+    Ok(isize),          // ID
+    Err(isize, String), // ID, error message
 }
 
+/// Move files starting with filename_prefix. Returns the total size of moved files.
 fn move_files_with_prefix(
     path_tmp: &Path,
     path_media_full: &Path,
     filename_prefix: &str,
-) -> Result<()> {
+) -> Result<u64> {
+    let mut moved_bytes = 0;
+
     // Iterate over entries in the `path_tmp` directory
     for entry in fs::read_dir(path_tmp)? {
         let entry = entry?;
@@ -333,7 +349,7 @@ fn move_files_with_prefix(
                     let destination = path_media_full.join(file_name);
 
                     // Move the file
-                    fs::copy(&path, &destination)?;
+                    moved_bytes += fs::copy(&path, &destination)?;
 
                     // Verify that `path` is within `path_tmp` before removing
                     let canonical_path = path.canonicalize()?;
@@ -349,7 +365,7 @@ fn move_files_with_prefix(
             }
         }
     }
-    Ok(())
+    Ok(moved_bytes)
 }
 
 fn parse_domain(url: &str) -> String {
@@ -363,15 +379,3 @@ fn parse_domain(url: &str) -> String {
     );
     domain
 }
-
-fn update_bgtask_exec_time(id: isize, conn: &PooledConnection<SqliteConnectionManager>) {
-    if let Err(_) = conn.execute(
-        "UPDATE tasks_persistent 
-                SET last_exec = datetime('now') 
-                WHERE id = ?1",
-        params![id],
-    ) {
-        // Nothing we can do. Let's try again later.
-        return;
-    }
-}
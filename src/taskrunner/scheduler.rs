@@ -0,0 +1,177 @@
+//! Scheduling of persistent background tasks (tasks_persistent table).
+//!
+//! Each row is run either every delay_sec seconds, or by cron expression if one is set.
+//! Disabled rows are only run when "run now" has been requested. A task is not started again
+//! while it's still running, the run waits until it's done.
+
+use anyhow::{anyhow, Result};
+use chrono::{Duration, NaiveDateTime, TimeZone, Utc};
+use croner::Cron;
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rusqlite::params;
+use std::collections::HashSet;
+use std::sync::{LazyLock, Mutex};
+use std::time::Instant;
+use tracing::{debug, error};
+
+use super::BgTaskFn;
+use crate::DBPool;
+
+/// Names of the persistent tasks that are running.
+static RUNNING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Mark the task running. False if it already is. `execute` clears the mark when done.
+pub fn try_start(task_name: &str) -> bool {
+    RUNNING
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(task_name.to_string())
+}
+
+/// Clears the running mark when dropped, also if the task panics.
+struct RunningGuard(String);
+
+impl Drop for RunningGuard {
+    fn drop(&mut self) {
+        RUNNING
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.0);
+    }
+}
+
+#[derive(Debug)]
+pub struct PersistentTask {
+    pub task_id: isize,
+    pub task_name: String,
+    pub enabled: bool,
+    pub delay_sec: i64,
+    pub cron: Option<String>,
+    pub run_now: bool,
+    pub last_exec: NaiveDateTime,
+    pub last_duration_ms: Option<i64>,
+    pub last_result: Option<String>,
+    pub last_error: Option<String>,
+}
+
+impl PersistentTask {
+    /// Next scheduled execution (UTC). None if the task is disabled or the cron is invalid.
+    pub fn next_exec(&self) -> Option<NaiveDateTime> {
+        if !self.enabled {
+            return None;
+        }
+        match &self.cron {
+            Some(expr) => {
+                let cron = parse_cron(expr).ok()?;
+                let last_exec = Utc.from_utc_datetime(&self.last_exec);
+                cron.find_next_occurrence(&last_exec, false)
+                    .ok()
+                    .map(|next| next.naive_utc())
+            }
+            None => Some(self.last_exec + Duration::seconds(self.delay_sec)),
+        }
+    }
+
+    /// Should the task be started now.
+    pub fn is_due(&self, now: NaiveDateTime) -> bool {
+        if self.run_now {
+            return true;
+        }
+        match self.next_exec() {
+            Some(next) => next <= now,
+            None => false,
+        }
+    }
+}
+
+/// Parse standard 5-field cron expression (seconds field is optional).
+pub fn parse_cron(expr: &str) -> Result<Cron> {
+    Cron::new(expr)
+        .with_seconds_optional()
+        .parse()
+        .map_err(|e| anyhow!("Invalid cron expression '{}': {:?}", expr, e))
+}
+
+/// Load every persistent task from the database.
+pub fn get_persistent_tasks(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> Result<Vec<PersistentTask>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, task_name, enabled, delay_sec, cron, run_now, last_exec,
+                last_duration_ms, last_result, last_error
+         FROM tasks_persistent
+         ORDER BY id",
+    )?;
+
+    let tasks = stmt
+        .query_map(params![], |row| {
+            Ok(PersistentTask {
+                task_id: row.get(0)?,
+                task_name: row.get(1)?,
+                enabled: row.get(2)?,
+                delay_sec: row.get(3)?,
+                cron: row.get(4)?,
+                run_now: row.get(5)?,
+                last_exec: row.get(6)?,
+                last_duration_ms: row.get(7)?,
+                last_result: row.get(8)?,
+                last_error: row.get(9)?,
+            })
+        })?
+        .filter_map(|res| res.ok())
+        .collect();
+
+    Ok(tasks)
+}
+
+/// Run single persistent task and record the outcome to the database.
+pub fn execute(task_id: isize, task_name: String, bgtask: BgTaskFn, dbp: DBPool) {
+    let _running = RunningGuard(task_name.clone());
+    debug!("Started background task: {}", task_name);
+
+    // Mark as started, so it's not picked up again while running
+    if let Ok(conn) = dbp.get() {
+        if let Err(e) = conn.execute(
+            "UPDATE tasks_persistent
+                SET last_exec = datetime('now'), run_now = 0
+                WHERE id = ?1",
+            params![task_id],
+        ) {
+            error!("Failed to update persistent task {}: {}", task_name, e);
+            return;
+        }
+    } else {
+        error!("Error getting database connection.");
+        return;
+    }
+
+    let started = Instant::now();
+    let result = bgtask(dbp.clone());
+    let duration_ms = started.elapsed().as_millis() as i64;
+
+    let (last_result, last_error) = match &result {
+        Ok(_) => ("OK", None),
+        Err(e) => {
+            error!("Background task {} failed: {:?}", task_name, e);
+            ("ERR", Some(format!("{:#}", e)))
+        }
+    };
+
+    if let Ok(conn) = dbp.get() {
+        if let Err(e) = conn.execute(
+            "UPDATE tasks_persistent
+                SET last_duration_ms = ?1, last_result = ?2, last_error = ?3,
+                    last_success = CASE WHEN ?2 = 'OK' THEN last_exec ELSE last_success END
+                WHERE id = ?4",
+            params![duration_ms, last_result, last_error, task_id],
+        ) {
+            error!("Failed to record result of {}: {}", task_name, e);
+        }
+    }
+
+    debug!(
+        "Completed background task: {} ({} ms)",
+        task_name, duration_ms
+    );
+}
@@ -1,15 +1,17 @@
 use chrono::NaiveDate;
-use regex::Regex;
-use rusqlite::params;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
+use std::time::Instant;
 use std::{collections::HashMap, process::Command};
 use std::{fs::create_dir_all, thread, time};
 use tldextract::{TldExtractor, TldOption};
 use tracing::{debug, error, event, info, trace, warn};
 
+use crate::database;
+use crate::events;
+use crate::metrics;
 use crate::DBPool;
 
 use super::{move_files_with_prefix, parse_domain, TaskResult};
@@ -30,7 +32,7 @@ pub fn add(
         Ok(data) => data,
         Err(_) => {
             debug!("Failed to parse data for task {}", task_id);
-            let _ = sender.send(TaskResult::Err(task_id, -500));
+            let _ = sender.send(TaskResult::Err(task_id, "Invalid task data".to_string()));
             return;
         }
     };
@@ -40,7 +42,10 @@ pub fn add(
 
     if &domain != "youtube.com" {
         error!("Adding channels is not supported for domain: {}", domain);
-        let _ = sender.send(TaskResult::Err(task_id, -400));
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            format!("Adding channels is not supported for {}", domain),
+        ));
         return;
     }
 
@@ -55,17 +60,24 @@ pub fn add(
             &data.url,
         ])
         .output();
+    metrics::observe_ytdlp(&output);
 
     if output.is_err() {
         debug!("Failed to get channel id for task {}", task_id);
-        let _ = sender.send(TaskResult::Err(task_id, -500));
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            "Could not get channel ID".to_string(),
+        ));
         return;
     }
     let channel_id = match std::str::from_utf8(&output.unwrap().stdout) {
         Ok(chid) => chid.trim().to_string(),
         Err(_) => {
             debug!("Failed to parse channel id for task {}", task_id);
-            let _ = sender.send(TaskResult::Err(task_id, -500));
+            let _ = sender.send(TaskResult::Err(
+                task_id,
+                "Could not parse channel ID".to_string(),
+            ));
             return;
         }
     };
@@ -84,40 +96,53 @@ pub fn add(
             &data.url,
         ])
         .output();
+    metrics::observe_ytdlp(&output);
 
     if output.is_err() {
         debug!("Failed to get channel name for task {}", task_id);
-        let _ = sender.send(TaskResult::Err(task_id, -500));
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            "Could not get channel name".to_string(),
+        ));
         return;
     }
     let channel_name = match std::str::from_utf8(&output.unwrap().stdout) {
         Ok(chnm) => chnm.trim().to_string(),
         Err(_) => {
             debug!("Failed to parse channel name for task {}", task_id);
-            let _ = sender.send(TaskResult::Err(task_id, -500));
+            let _ = sender.send(TaskResult::Err(
+                task_id,
+                "Could not parse channel name".to_string(),
+            ));
             return;
         }
     };
 
-    let normalized_channel_name = normalize_channel_name(&channel_name);
-
     // Write the data to db
     if let Ok(conn) = dbp.get() {
-        // Prepare the SQL statement to update the task
-        if let Err(e) = conn.execute(
-            "INSERT INTO channels (domain, url, channel_id, channel_name, channel_name_normalized) VALUES (?1, ?2, ?3, ?4, ?5)",
-            params![domain, url, channel_id, channel_name, normalized_channel_name],
-        ) {
+        if let Err(e) = database::insert_channel(&conn, &domain, &url, &channel_id, &channel_name) {
             error!("Error inserting channel for task {}: {}", task_id, e);
-            let _ = sender.send(TaskResult::Err(task_id, -500));
+            let _ = sender.send(TaskResult::Err(
+                task_id,
+                format!("Could not save channel: {}", e),
+            ));
             return;
         }
     } else {
         error!("Error connecting to database for task {}", task_id);
-        let _ = sender.send(TaskResult::Err(task_id, -500));
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            "Database is not available".to_string(),
+        ));
         return;
     }
 
+    events::publish(events::Event::ChannelAdded {
+        domain,
+        channel_id,
+        channel_name,
+    });
+
     // And finally, return
     let _ = sender.send(TaskResult::Ok(task_id));
 }
@@ -127,18 +152,6 @@ pub struct TaskChannelAddData {
     pub url: String,
 }
 
-fn normalize_channel_name(channel_name: &str) -> String {
-    let re = Regex::new(r#"['\"]"#).unwrap(); // This works for removing quotes
-
-    let mut normalized = re.replace_all(channel_name, "");
-
-    // Replace spaces with hyphens
-    normalized = normalized.replace(" ", "-").into();
-
-    // Convert to lowercase
-    normalized.to_lowercase()
-}
-
 /// Worker for CHANNEL-FETCH tasks.
 /// Download the metadata for every video on specific channel and populate videos table.
 pub fn fetch(
@@ -155,7 +168,7 @@ pub fn fetch(
         Ok(data) => data,
         Err(_) => {
             debug!("Failed to parse data for task {}", task_id);
-            let _ = sender.send(TaskResult::Err(task_id, -500));
+            let _ = sender.send(TaskResult::Err(task_id, "Invalid task data".to_string()));
             return;
         }
     };
@@ -165,7 +178,10 @@ pub fn fetch(
 
     if &domain != "youtube.com" {
         error!("Fetching channels is not supported for domain: {}", domain);
-        let _ = sender.send(TaskResult::Err(task_id, -400));
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            format!("Fetching channels is not supported for {}", domain),
+        ));
         return;
     }
 
@@ -174,7 +190,10 @@ pub fn fetch(
         "youtube.com" => format!("youtube.com/channel/{}", data.channel_id),
         _ => {
             error!("Fetching channels is not supported for domain: {}", domain);
-            let _ = sender.send(TaskResult::Err(task_id, -400));
+            let _ = sender.send(TaskResult::Err(
+                task_id,
+                format!("Fetching channels is not supported for {}", domain),
+            ));
             return;
         }
     };
@@ -182,6 +201,7 @@ pub fn fetch(
     debug!("Downloading url: {:?}", videos_url);
 
     // Get videos information
+    let started = Instant::now();
     let output = Command::new("yt-dlp")
         .arg("--skip-download")
         .arg("--extractor-args")
@@ -190,10 +210,15 @@ pub fn fetch(
         .arg("%(channel_id)s SPLITATTHISPOINT %(channel)s SPLITATTHISPOINT %(webpage_url)s SPLITATTHISPOINT %(upload_date)s SPLITATTHISPOINT %(title)s SPLITATTHISPOINT %(id)s")
         .arg(&videos_url) // The URL at the very end
         .output();
+    metrics::observe_ytdlp(&output);
+    metrics::CHANNEL_FETCH_DURATION.observe(started.elapsed().as_secs_f64());
 
     if output.is_err() {
         debug!("Failed to get channel id for task {}", task_id);
-        let _ = sender.send(TaskResult::Err(task_id, -500));
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            "Could not get channel videos".to_string(),
+        ));
         return;
     }
     //debug!("YT-DLP: {:?}", output);
@@ -213,7 +238,10 @@ pub fn fetch(
         }
         Err(_) => {
             error!("Failed parse channels metadata for task {}", task_id);
-            let _ = sender.send(TaskResult::Err(task_id, -500));
+            let _ = sender.send(TaskResult::Err(
+                task_id,
+                "Could not parse channel videos".to_string(),
+            ));
             return;
         }
     };
@@ -251,60 +279,37 @@ pub fn fetch(
                 }
             };
 
-            // Check if the channel exists in the channels table
-            let mut channel_exists_stmt =
-                match conn.prepare("SELECT id FROM channels WHERE channel_id = ?") {
-                    Ok(stmt) => stmt,
-                    Err(e) => {
-                        error!("Failed to prepare channel check statement: {:?}", e);
-                        continue;
-                    }
-                };
-            let channel_exists_result = channel_exists_stmt
-                .query_row(rusqlite::params![channel_id], |row| row.get::<_, i32>(0));
-
-            let channel_id_db: i32 = match channel_exists_result {
-                Ok(id) => id,
-                Err(_) => {
+            let channel = match database::get_channel(&conn, &domain, &channel_id) {
+                Ok(Some(channel)) => channel,
+                Ok(None) => {
                     error!("Channel doesn't exist: {}, skipping.", channel_id);
                     continue;
                 }
-            };
-
-            // Prepare SQL insertion
-            let sql = "INSERT INTO videos (
-                       channel_id, domain, url, name, video_id, 
-                       is_requested, is_downloaded, release_date, release_date_estimate
-                   ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
-                   ON CONFLICT(domain, video_id) DO UPDATE SET 
-                       updated_at = CURRENT_TIMESTAMP";
-
-            // Execute the prepared SQL statement
-            let mut stmt = match conn.prepare(sql) {
-                Ok(s) => s,
                 Err(e) => {
-                    error!("Failed to prepare statement: {:?}", e);
+                    error!("Failed to get channel: {:#}", e);
                     continue;
                 }
             };
 
-            if let Err(e) = stmt.execute(rusqlite::params![
-                channel_id_db,         // channel_id (from channels table)
-                "youtube.com",         // domain (fixed as "youtube.com")
-                webpage_url,           // url (now taken from row[2])
-                title,                 // name (video title from row[4])
-                video_id,              // video_id (from row[5])
-                0,                     // is_requested (default to 0)
-                0,                     // is_downloaded (default to 0)
-                None::<NaiveDate>,     // release_date set to NULL
-                release_date_estimate  // release_date_estimate (using same date)
-            ]) {
-                error!("Failed to execute insert: {:?}", e);
+            let video = database::NewVideo {
+                channel_id: Some(channel.id),
+                domain: &domain,
+                url: &webpage_url,
+                name: &title,
+                video_id: &video_id,
+                release_date: None,
+                release_date_estimate: Some(release_date_estimate),
+            };
+            if let Err(e) = database::upsert_video(&conn, &video) {
+                error!("Failed to save video: {:#}", e);
             }
         }
     } else {
         error!("Error connecting to database for task {}", task_id);
-        let _ = sender.send(TaskResult::Err(task_id, -500));
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            "Database is not available".to_string(),
+        ));
         return;
     }
 
@@ -1,14 +1,25 @@
-use chrono::{NaiveDate, NaiveDateTime};
-use rusqlite::{params, OptionalExtension};
+use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
+use std::io::{self, BufRead, BufReader, Read};
 use std::path::PathBuf;
+use std::process::{Output, Stdio};
+use std::sync::atomic::Ordering;
 use std::sync::mpsc::{Receiver, Sender};
 use std::sync::Arc;
+use std::time::Instant;
 use std::{collections::HashMap, process::Command};
-use std::{fs::create_dir_all, thread, time};
+use std::{
+    fs::{create_dir_all, remove_file},
+    thread, time,
+};
 use tldextract::{TldExtractor, TldOption};
 use tracing::{debug, error, event, info, info_span, span, trace, warn, Level};
 
+use crate::database;
+use crate::events;
+use crate::library::{find_media_file, InfoJson};
+use crate::mediaserver;
+use crate::metrics;
 use crate::DBPool;
 
 use super::{move_files_with_prefix, parse_domain, TaskResult};
@@ -22,12 +33,13 @@ pub fn worker(
     dbp: DBPool,
 ) {
     debug!("task_download::worker() started for task {}", task_id);
+    let started = Instant::now();
 
     // Unpack data
     let data: TaskDownloadData = match serde_json::from_str(&data) {
         Ok(data) => data,
         Err(_) => {
-            let _ = sender.send(TaskResult::Err(task_id, -500));
+            let _ = sender.send(TaskResult::Err(task_id, "Invalid task data".to_string()));
             return;
         }
     };
@@ -60,16 +72,23 @@ pub fn worker(
             url_no_list_query,
         ])
         .output();
+    metrics::observe_ytdlp(&filename_output);
 
-    if filename_output.is_err() {
-        let _ = sender.send(TaskResult::Err(task_id, -502));
+    if let Err(e) = &filename_output {
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            format!("Could not run yt-dlp: {}", e),
+        ));
         return;
     }
     let filename_output = filename_output.unwrap();
     let filename_parts = match std::str::from_utf8(&filename_output.stdout) {
         Ok(name) => name.trim().to_string(),
         Err(_) => {
-            let _ = sender.send(TaskResult::Err(task_id, -503));
+            let _ = sender.send(TaskResult::Err(
+                task_id,
+                "yt-dlp printed invalid file name".to_string(),
+            ));
             return;
         }
     };
@@ -188,28 +207,34 @@ pub fn worker(
     let filepath = format!("{}/{}", path_tmp, filename_template);
 
     // Refer: https://github.com/yt-dlp/yt-dlp/issues/630#issuecomment-893659460
-    let output = Command::new("yt-dlp")
-        .args([
-            "--no-playlist",
-            "--add-metadata",
-            "--embed-metadata",
-            "--write-thumbnail",
-            "--convert-thumbnails",
-            "jpg",
-            "--write-subs",
-            "--write-auto-subs",
-            "--convert-subs",
-            "srt",
-            "--sub-lang",
-            sub_lang,
-            "-o",
-            &filepath,
-            &data.url,
-        ])
-        .output();
+    let mut command = Command::new("yt-dlp");
+    command.args([
+        "--newline",
+        "--no-playlist",
+        "--add-metadata",
+        "--embed-metadata",
+        "--write-thumbnail",
+        "--write-info-json",
+        "--convert-thumbnails",
+        "jpg",
+        "--write-subs",
+        "--write-auto-subs",
+        "--convert-subs",
+        "srt",
+        "--sub-lang",
+        sub_lang,
+        "-o",
+        &filepath,
+        &data.url,
+    ]);
+    let output = output_with_progress(command, task_id);
+    metrics::observe_ytdlp(&output);
 
-    if output.is_err() {
-        let _ = sender.send(TaskResult::Err(task_id, -504));
+    if let Err(e) = &output {
+        let _ = sender.send(TaskResult::Err(
+            task_id,
+            format!("Could not run yt-dlp: {}", e),
+        ));
         return;
     }
     let output = output.unwrap();
@@ -232,50 +257,80 @@ pub fn worker(
 
     debug!("PATH_MEDIA_FULL: {:?}", path_media_full);
 
-    // Move the files
+    // Keep description, tags and codecs from the metadata, the file itself isn't moved
     let path_tmp = PathBuf::from(path_tmp);
-    let _ = move_files_with_prefix(&path_tmp, &path_media_full, &filename);
+    let info_path = path_tmp.join(format!("{}.info.json", filename));
+    let info = match InfoJson::read(&info_path) {
+        Ok(info) => Some(info),
+        Err(e) => {
+            warn!("No metadata for task {}: {:#}", task_id, e);
+            None
+        }
+    };
+    let _ = remove_file(&info_path);
+
+    // Move the files
+    match move_files_with_prefix(&path_tmp, &path_media_full, &filename) {
+        Ok(moved_bytes) => {
+            metrics::DOWNLOAD_BYTES.fetch_add(moved_bytes, Ordering::Relaxed);
+            mediaserver::queue_refresh(&path_media_full);
+        }
+        Err(e) => error!("Failed to move files for task {}: {:?}", task_id, e),
+    }
 
     // Record this as known video
     let span = span!(Level::DEBUG, "Insert video");
     let _enter = span.enter();
-    if let Ok(conn) = dbp.get() {
-        let channel_id: Option<i64> = conn
-            .query_row(
-                "SELECT id FROM channels WHERE domain = ?1 AND channel_id = ?2",
-                params![domain, channel_id],
-                |row| row.get(0),
-            )
-            .optional()
-            .ok()
-            .flatten();
-        debug!("Channel ID: {:?}", channel_id);
-
-        let mut naive_datetime_upload: Option<NaiveDateTime> = None;
-        if upload_date.is_some() {
-            let naive_date_upload = NaiveDate::parse_from_str(&upload_date.unwrap(), "%Y%m%d")
-                .expect("Could not parse datetime");
-            naive_datetime_upload = Some(naive_date_upload.and_hms(0, 0, 0));
-        }
+    match (dbp.get(), &title, &video_id) {
+        (Ok(conn), Some(title), Some(video_id)) => {
+            let channel_id = channel_id
+                .and_then(|channel_id| database::get_channel(&conn, &domain, &channel_id).ok())
+                .flatten()
+                .map(|channel| channel.id);
+            debug!("Channel ID: {:?}", channel_id);
 
-        conn.execute(
-        "INSERT OR REPLACE INTO videos (channel_id, domain, url, name, video_id, release_date, release_date_estimate, is_requested, is_downloaded)
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
-        params![
-            channel_id,
-            domain,
-            &data.url,
-            title,
-            video_id,
-            naive_datetime_upload,
-            naive_datetime_upload,
-            true,
-            true,
-        ]).unwrap();
+            let release_date = upload_date
+                .as_deref()
+                .and_then(|date| NaiveDate::parse_from_str(date, "%Y%m%d").ok());
+
+            let video = database::NewVideo {
+                channel_id,
+                domain: &domain,
+                url: &data.url,
+                name: title,
+                video_id,
+                release_date,
+                release_date_estimate: release_date,
+            };
+            if let Err(e) = database::save_downloaded_video(&conn, &video) {
+                error!("Failed to save video for task {}: {:#}", task_id, e);
+            }
+
+            let download_info = database::DownloadInfo {
+                file_path: find_media_file(&path_media_full, &filename)
+                    .map(|path| path.display().to_string()),
+                video_codec: info.as_ref().and_then(InfoJson::video_codec),
+                audio_codec: info.as_ref().and_then(InfoJson::audio_codec),
+                description: info.as_ref().and_then(|info| info.description.clone()),
+                tags: info
+                    .as_ref()
+                    .filter(|info| !info.tags.is_empty())
+                    .map(|info| info.tags.join(", ")),
+            };
+            if let Err(e) = database::save_download_info(&conn, &domain, video_id, &download_info) {
+                error!("Failed to save download info for task {}: {:#}", task_id, e);
+            }
+        }
+        (Err(e), _, _) => error!("Error connecting to database for task {}: {}", task_id, e),
+        _ => error!(
+            "Video for task {} not recorded, title or ID is missing",
+            task_id
+        ),
     }
     drop(_enter);
 
     // And finally, return
+    metrics::DOWNLOAD_DURATION.observe(started.elapsed().as_secs_f64());
     let _ = sender.send(TaskResult::Ok(task_id));
 }
 
@@ -283,3 +338,52 @@ pub fn worker(
 pub struct TaskDownloadData {
     pub url: String,
 }
+
+/// Run yt-dlp, publishing download progress parsed from its output.
+fn output_with_progress(mut command: Command, task_id: isize) -> io::Result<Output> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // Drain stderr in the background, so yt-dlp can't block on a full pipe
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    let stderr_reader = thread::spawn(move || {
+        let mut buf = Vec::new();
+        let _ = stderr.read_to_end(&mut buf);
+        buf
+    });
+
+    let mut stdout = Vec::new();
+    let mut last_percent = -1;
+    let lines = BufReader::new(child.stdout.take().expect("stdout is piped")).lines();
+    for line in lines.map_while(Result::ok) {
+        if let Some(percent) = parse_progress(&line) {
+            // Publish only whole percent changes
+            if percent as i32 != last_percent {
+                last_percent = percent as i32;
+                events::publish(events::Event::Progress {
+                    task_id: task_id as i64,
+                    percent,
+                });
+            }
+        }
+        stdout.extend_from_slice(line.as_bytes());
+        stdout.push(b'\n');
+    }
+
+    let status = child.wait()?;
+    let stderr = stderr_reader.join().unwrap_or_default();
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Parse percent from progress line, eg. "[download]  42.3% of   10.00MiB at  1.00MiB/s ETA 00:05"
+fn parse_progress(line: &str) -> Option<f32> {
+    let rest = line.strip_prefix("[download]")?.trim_start();
+    let (percent, _) = rest.split_once('%')?;
+    percent.trim().parse().ok()
+}
@@ -1,17 +1,19 @@
 use rocket::http::ContentType;
 use rocket::post;
-use tracing::{debug, error, event, info, trace, warn};
+use tracing::warn;
 
+use crate::webui::auth::Admin;
+use crate::webui::base_path;
 use crate::FLAG_SHUTDOWN;
 
 #[post("/shutdown")]
-pub async fn post_shutdown() -> (ContentType, String) {
+pub async fn post_shutdown(_admin: Admin) -> (ContentType, String) {
     // Send shutdown command to the system
     warn!("Shutdown requested from API");
     FLAG_SHUTDOWN.store(true, std::sync::atomic::Ordering::Relaxed);
 
     // Return informational page
-    let page: String = HTML_SHUTDOWN.into();
+    let page = HTML_SHUTDOWN.replace("{base}", base_path());
     (ContentType::HTML, page)
 }
 
@@ -20,10 +22,10 @@ const HTML_SHUTDOWN: &'static str = r#"
     <HEAD>
         <script type="text/javascript">
             function pollFrontPage() {
-                fetch('/')
+                fetch('{base}/')
                     .then(response => {
                         if (response.ok) {
-                            window.location.href = '/';
+                            window.location.href = '{base}/';
                         }
                     })
                     .catch(error => {
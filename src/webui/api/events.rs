@@ -0,0 +1,29 @@
+//! Server-sent events stream for live UI updates
+
+use rocket::response::stream::{Event, EventStream};
+use rocket::tokio::select;
+use rocket::tokio::sync::broadcast::error::RecvError;
+use rocket::{get, Shutdown};
+
+use crate::events;
+use crate::webui::auth::User;
+
+#[get("/events")]
+pub async fn get_events(_user: User, mut shutdown: Shutdown) -> EventStream![] {
+    let mut rx = events::subscribe();
+
+    EventStream! {
+        loop {
+            let event = select! {
+                msg = rx.recv() => match msg {
+                    Ok(event) => event,
+                    Err(RecvError::Closed) => break,
+                    Err(RecvError::Lagged(_)) => continue,
+                },
+                _ = &mut shutdown => break,
+            };
+
+            yield Event::json(&event);
+        }
+    }
+}
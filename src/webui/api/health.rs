@@ -0,0 +1,219 @@
+//! Health and readiness endpoints for orchestrators and uptime checks.
+//!
+//! Readiness shows tool versions and paths, so it needs login or an API key.
+
+use chrono::{DateTime, Utc};
+use rocket::http::Status;
+use rocket::{get, serde::json::Json, State};
+use rusqlite::params;
+use serde::Serialize;
+use std::path::Path;
+use std::process::Command;
+use std::sync::atomic::Ordering;
+
+use crate::config;
+use crate::database::{db_expected_version, db_get_version};
+use crate::taskrunner::HEARTBEAT;
+use crate::webui::auth::User;
+use crate::DBPool;
+
+/// Taskrunner loop sleeps at most 8 seconds, allow for slow database as well.
+const TASKRUNNER_STALE_SEC: i64 = 60;
+
+#[derive(Serialize)]
+pub struct Health {
+    healthy: bool,
+    version: &'static str,
+    database: DatabaseHealth,
+    taskrunner: TaskrunnerHealth,
+    tools: Vec<ToolHealth>,
+    paths: Vec<PathHealth>,
+}
+
+#[derive(Serialize)]
+pub struct DatabaseHealth {
+    ok: bool,
+    schema_version: Option<u32>,
+    expected_version: u32,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TaskrunnerHealth {
+    ok: bool,
+    last_loop: Option<String>,
+    seconds_since_last_loop: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ToolHealth {
+    name: &'static str,
+    ok: bool,
+    version: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PathHealth {
+    key: &'static str,
+    path: Option<String>,
+    ok: bool,
+    error: Option<String>,
+}
+
+/// Liveness: the web server answers. The only /api route without login, so orchestrators
+/// can probe it. It tells nothing but that the server is up.
+#[get("/health/live")]
+pub async fn get_health_live() -> Status {
+    Status::Ok
+}
+
+/// Readiness: every dependency is usable. Responds 503 if any check fails.
+#[get("/health")]
+pub async fn get_health(_user: User, db_pool: &State<DBPool>) -> (Status, Json<Health>) {
+    let dbp = db_pool.inner().clone();
+    let health = rocket::tokio::task::spawn_blocking(move || check_health(&dbp))
+        .await
+        .expect("Health check panicked");
+
+    let status = match health.healthy {
+        true => Status::Ok,
+        false => Status::ServiceUnavailable,
+    };
+    (status, Json(health))
+}
+
+fn check_health(dbp: &DBPool) -> Health {
+    let (database, paths) = check_database(dbp);
+    let taskrunner = check_taskrunner();
+    let tools = vec![
+        check_tool("yt-dlp", &["--version"]),
+        check_tool("ffmpeg", &["-version"]),
+    ];
+
+    let healthy = database.ok
+        && taskrunner.ok
+        && tools.iter().all(|tool| tool.ok)
+        && paths.iter().all(|path| path.ok);
+
+    Health {
+        healthy,
+        version: env!("CARGO_PKG_VERSION"),
+        database,
+        taskrunner,
+        tools,
+        paths,
+    }
+}
+
+fn check_database(dbp: &DBPool) -> (DatabaseHealth, Vec<PathHealth>) {
+    let expected_version = db_expected_version();
+    let conn = match dbp.get() {
+        Ok(conn) => conn,
+        Err(e) => {
+            let database = DatabaseHealth {
+                ok: false,
+                schema_version: None,
+                expected_version,
+                error: Some(e.to_string()),
+            };
+            let paths = ["path_temp", "path_media"]
+                .into_iter()
+                .map(|key| PathHealth {
+                    key,
+                    path: None,
+                    ok: false,
+                    error: Some("Database unavailable".to_string()),
+                })
+                .collect();
+            return (database, paths);
+        }
+    };
+
+    let database = match db_get_version(&conn) {
+        Ok(version) => DatabaseHealth {
+            ok: version == expected_version,
+            schema_version: Some(version),
+            expected_version,
+            error: None,
+        },
+        Err(e) => DatabaseHealth {
+            ok: false,
+            schema_version: None,
+            expected_version,
+            error: Some(format!("{:#}", e)),
+        },
+    };
+
+    let paths = ["path_temp", "path_media"]
+        .into_iter()
+        .map(|key| {
+            let path: Option<String> = conn
+                .query_row(
+                    "SELECT value FROM app_configuration WHERE key = ?1",
+                    params![key],
+                    |row| row.get(0),
+                )
+                .ok();
+            match &path {
+                Some(dir) => match config::check_writable(Path::new(dir)) {
+                    Ok(_) => PathHealth {
+                        key,
+                        path,
+                        ok: true,
+                        error: None,
+                    },
+                    Err(e) => PathHealth {
+                        key,
+                        path,
+                        ok: false,
+                        error: Some(e.to_string()),
+                    },
+                },
+                None => PathHealth {
+                    key,
+                    path,
+                    ok: false,
+                    error: Some("Not configured".to_string()),
+                },
+            }
+        })
+        .collect();
+
+    (database, paths)
+}
+
+fn check_taskrunner() -> TaskrunnerHealth {
+    let heartbeat = HEARTBEAT.load(Ordering::Relaxed);
+    if heartbeat == 0 {
+        return TaskrunnerHealth {
+            ok: false,
+            last_loop: None,
+            seconds_since_last_loop: None,
+        };
+    }
+
+    let since = Utc::now().timestamp() - heartbeat;
+    TaskrunnerHealth {
+        ok: since <= TASKRUNNER_STALE_SEC,
+        last_loop: DateTime::from_timestamp(heartbeat, 0).map(|dt| dt.naive_utc().to_string()),
+        seconds_since_last_loop: Some(since),
+    }
+}
+
+fn check_tool(name: &'static str, args: &[&str]) -> ToolHealth {
+    match Command::new(name).args(args).output() {
+        Ok(output) if output.status.success() => ToolHealth {
+            name,
+            ok: true,
+            version: String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .next()
+                .map(|line| line.trim().to_string()),
+        },
+        _ => ToolHealth {
+            name,
+            ok: false,
+            version: None,
+        },
+    }
+}
@@ -0,0 +1,27 @@
+//! Prometheus metrics endpoint, mounted at /metrics. Takes an API key like the /api routes,
+//! so scrapers don't need a session.
+
+use rocket::http::{ContentType, Status};
+use rocket::{get, State};
+use tracing::error;
+
+use crate::metrics;
+use crate::webui::auth::User;
+use crate::DBPool;
+
+#[get("/")]
+pub async fn get_metrics(
+    _user: User,
+    db_pool: &State<DBPool>,
+) -> Result<(ContentType, String), Status> {
+    match metrics::render(db_pool) {
+        Ok(text) => Ok((
+            ContentType::new("text", "plain").with_params(("version", "0.0.4")),
+            text,
+        )),
+        Err(e) => {
+            error!("Failed to render metrics: {:?}", e);
+            Err(Status::InternalServerError)
+        }
+    }
+}
@@ -1,11 +1,16 @@
-mod task;
-pub use task::*;
-
-mod channel;
-pub use channel::*;
-
-mod video;
-pub use video::*;
+mod paging;
+pub use paging::*;
 
 mod conf;
 pub use conf::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod health;
+pub use health::*;
+
+mod events;
+pub use events::*;
+
+pub mod v1;
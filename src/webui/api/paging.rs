@@ -0,0 +1,57 @@
+//! Query parameters for paged list endpoints: offset, limit, sort and order.
+
+use rocket::{http::Status, FromForm};
+use utoipa::IntoParams;
+
+use crate::database::{self, PageRequest, SortColumn, PAGE_MAX_LIMIT};
+
+#[derive(Debug, Default, FromForm, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct Paging {
+    offset: Option<u32>,
+    /// Defaults to `PAGE_DEFAULT_LIMIT`, at most `PAGE_MAX_LIMIT`.
+    limit: Option<u32>,
+    /// One of the sort columns of the endpoint, defaults to the first one.
+    sort: Option<String>,
+    /// "asc" (default) or "desc".
+    order: Option<String>,
+}
+
+impl Paging {
+    /// Page request, or 400 with the reason if the parameters are not valid.
+    pub fn request(&self, columns: &[SortColumn]) -> Result<PageRequest, (Status, String)> {
+        let mut page = PageRequest::first(columns);
+
+        if let Some(name) = self.sort.as_deref() {
+            page.sort = database::sort_column(columns, name).ok_or_else(|| {
+                let keys: Vec<&str> = columns.iter().map(|(key, _)| *key).collect();
+                (
+                    Status::BadRequest,
+                    format!("Cannot sort by {}, use one of: {}", name, keys.join(", ")),
+                )
+            })?;
+        }
+        page.descending = match self.order.as_deref() {
+            None | Some("asc") => false,
+            Some("desc") => true,
+            Some(order) => {
+                return Err((
+                    Status::BadRequest,
+                    format!("Invalid order {}, use asc or desc", order),
+                ))
+            }
+        };
+        if let Some(limit) = self.limit {
+            if limit == 0 || limit > PAGE_MAX_LIMIT {
+                return Err((
+                    Status::BadRequest,
+                    format!("Limit must be between 1 and {}", PAGE_MAX_LIMIT),
+                ));
+            }
+            page.limit = limit;
+        }
+        page.offset = self.offset.unwrap_or(0);
+
+        Ok(page)
+    }
+}
@@ -0,0 +1,89 @@
+//! v1 API endpoints for managing API keys of the logged in user
+
+use rocket::{delete, get, http::Status, post, response::status::Custom, serde::json::Json, State};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::auth::{self, ApiKey, ApiKeyScope};
+use crate::webui::auth::User;
+use crate::DBPool;
+
+/// Scope is FULL, READ or ENDPOINTS. Endpoints are "METHOD /api/v1/path" as in the route
+/// templates, eg. "POST /api/v1/videos", one per line or separated by commas.
+#[derive(Deserialize, ToSchema)]
+pub struct NewApiKey {
+    name: String,
+    scope: String,
+    endpoints: Option<String>,
+}
+
+/// The key itself is returned only this once.
+#[derive(Serialize, ToSchema)]
+pub struct CreatedApiKey {
+    id: i64,
+    key: String,
+}
+
+#[utoipa::path(tag = "apikeys", responses((status = 200, body = Vec<ApiKey>)))]
+#[get("/apikeys")]
+pub async fn get_apikeys(user: User, db_pool: &State<DBPool>) -> ApiResult<Json<Vec<ApiKey>>> {
+    let conn = db_pool.get()?;
+    Ok(Json(auth::get_api_keys(&conn, &user.0)?))
+}
+
+#[utoipa::path(
+    tag = "apikeys",
+    request_body = NewApiKey,
+    responses(
+        (status = 201, description = "Key was created", body = CreatedApiKey),
+        (status = 400, description = "Missing name or endpoints, or unknown scope", body = ErrorBody),
+    )
+)]
+#[post("/apikeys", format = "json", data = "<data>")]
+pub async fn post_apikey(
+    user: User,
+    data: Json<NewApiKey>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Custom<Json<CreatedApiKey>>> {
+    if data.name.trim().is_empty() {
+        return Err(ApiError::bad_request("Name is required"));
+    }
+    let scope = match ApiKeyScope::from_db(&data.scope, data.endpoints.as_deref()) {
+        Ok(ApiKeyScope::Endpoints(endpoints)) if endpoints.is_empty() => {
+            return Err(ApiError::bad_request(
+                "Endpoints are required for ENDPOINTS scope",
+            ))
+        }
+        Ok(scope) => scope,
+        Err(e) => return Err(ApiError::bad_request(e.to_string())),
+    };
+
+    let conn = db_pool.get()?;
+    let key = auth::create_api_key(&conn, &user.0, &data.name, &scope)?;
+    Ok(Custom(
+        Status::Created,
+        Json(CreatedApiKey {
+            id: conn.last_insert_rowid(),
+            key,
+        }),
+    ))
+}
+
+/// Revoke key of the logged in user.
+#[utoipa::path(
+    tag = "apikeys",
+    params(("id" = i64, Path, description = "API key ID")),
+    responses(
+        (status = 204, description = "Key was revoked"),
+        (status = 404, description = "No such API key", body = ErrorBody),
+    )
+)]
+#[delete("/apikeys/<id>")]
+pub async fn delete_apikey(user: User, id: i64, db_pool: &State<DBPool>) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    match auth::delete_api_key(&conn, &user.0, id)? {
+        true => Ok(Status::NoContent),
+        false => Err(ApiError::not_found("No such API key")),
+    }
+}
@@ -0,0 +1,260 @@
+//! v1 API endpoints for channels and their videos
+
+use rocket::{
+    delete, get,
+    http::{ContentType, Status},
+    post,
+    response::status::Accepted,
+    serde::json::Json,
+    State,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+
+use super::{load_task, ApiError, ApiResult, ErrorBody, Video};
+use crate::database::{
+    self, Channel, Page, Task, VideoFilter, CHANNEL_SORT_COLUMNS, VIDEO_SORT_COLUMNS,
+};
+use crate::subscriptions::{self, Preview};
+use crate::webui::api::Paging;
+use crate::webui::auth::{Admin, User};
+use crate::DBPool;
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewChannel {
+    pub url: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImportPreview {
+    /// OPML or Takeout subscriptions.csv file contents.
+    pub contents: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct ImportChannels {
+    pub channel_ids: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ImportResult {
+    pub queued: usize,
+}
+
+/// Followed channels.
+#[utoipa::path(
+    tag = "channels",
+    params(Paging),
+    responses(
+        (status = 200, body = Page<Channel>),
+        (status = 400, description = "Invalid paging", body = ErrorBody),
+    )
+)]
+#[get("/channels?<domain>&<paging..>")]
+pub async fn get_channels(
+    _user: User,
+    domain: Option<&str>,
+    paging: Paging,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Page<Channel>>> {
+    let page = paging.request(&CHANNEL_SORT_COLUMNS)?;
+    let conn = db_pool.get()?;
+    Ok(Json(database::list_channels(&conn, domain, &page)?))
+}
+
+#[utoipa::path(
+    tag = "channels",
+    params(("id" = i64, Path, description = "Channel row ID")),
+    responses(
+        (status = 200, body = Channel),
+        (status = 404, description = "No such channel", body = ErrorBody),
+    )
+)]
+#[get("/channels/<id>")]
+pub async fn get_channel(
+    _user: User,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Channel>> {
+    let conn = db_pool.get()?;
+    Ok(Json(load_channel(&conn, id)?))
+}
+
+/// Queue CHANNEL-ADD for the URL. Returns the task, the channel is added when it's done.
+#[utoipa::path(
+    tag = "channels",
+    request_body = NewChannel,
+    responses(
+        (status = 202, description = "CHANNEL-ADD task was queued", body = Task),
+        (status = 400, description = "Empty URL", body = ErrorBody),
+        (status = 409, description = "Channel is followed or being added", body = ErrorBody),
+    )
+)]
+#[post("/channels", format = "json", data = "<data>")]
+pub async fn post_channel(
+    admin: Admin,
+    data: Json<NewChannel>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Accepted<Json<Task>>> {
+    let url = data.url.trim();
+    if url.is_empty() {
+        return Err(ApiError::bad_request("Channel URL is empty"));
+    }
+
+    let conn = db_pool.get()?;
+    if let Some(channel) = database::get_channel_by_url(&conn, url)? {
+        return Err(ApiError::conflict(format!(
+            "Channel is already followed as {}",
+            channel.channel_name
+        )));
+    }
+    if let Some(task_id) = database::find_pending_url_task(&conn, "CHANNEL-ADD", url)? {
+        return Err(ApiError::conflict(format!(
+            "Channel is already being added by task {}",
+            task_id
+        )));
+    }
+
+    let task_id = database::enqueue_url_task(&conn, "CHANNEL-ADD", url, &admin.0.id)?;
+    Ok(Accepted(Json(load_task(&conn, task_id)?)))
+}
+
+/// Stop following the channel. Downloaded files are kept.
+#[utoipa::path(
+    tag = "channels",
+    params(("id" = i64, Path, description = "Channel row ID")),
+    responses(
+        (status = 204, description = "Channel and its videos were removed"),
+        (status = 404, description = "No such channel", body = ErrorBody),
+    )
+)]
+#[delete("/channels/<id>")]
+pub async fn delete_channel(_admin: Admin, id: i64, db_pool: &State<DBPool>) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    match database::delete_channel(&conn, id)? {
+        Some(_) => Ok(Status::NoContent),
+        None => Err(ApiError::not_found("No such channel")),
+    }
+}
+
+/// Queue CHANNEL-FETCH to look for new videos.
+#[utoipa::path(
+    tag = "channels",
+    params(("id" = i64, Path, description = "Channel row ID")),
+    responses(
+        (status = 202, description = "CHANNEL-FETCH task was queued", body = Task),
+        (status = 404, description = "No such channel", body = ErrorBody),
+    )
+)]
+#[post("/channels/<id>/fetch")]
+pub async fn post_channel_fetch(
+    admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Accepted<Json<Task>>> {
+    let conn = db_pool.get()?;
+    let channel = load_channel(&conn, id)?;
+
+    let data = json!({ "domain": channel.domain, "channel_id": channel.channel_id });
+    let task_id = database::enqueue_task(&conn, "CHANNEL-FETCH", &data, &admin.0.id)?;
+    Ok(Accepted(Json(load_task(&conn, task_id)?)))
+}
+
+/// Videos of the channel.
+#[utoipa::path(
+    tag = "channels",
+    params(
+        ("id" = i64, Path, description = "Channel row ID"),
+        ("season" = Option<String>, Query, description = "Year of release, or \"unknown\""),
+        Paging,
+    ),
+    responses(
+        (status = 200, body = Page<Video>),
+        (status = 400, description = "Invalid paging", body = ErrorBody),
+        (status = 404, description = "No such channel", body = ErrorBody),
+    )
+)]
+#[get("/channels/<id>/videos?<is_requested>&<is_downloaded>&<season>&<paging..>")]
+pub async fn get_channel_videos(
+    _user: User,
+    id: i64,
+    is_requested: Option<bool>,
+    is_downloaded: Option<bool>,
+    season: Option<String>,
+    paging: Paging,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Page<Video>>> {
+    let page = paging.request(&VIDEO_SORT_COLUMNS)?;
+    let filter = VideoFilter {
+        is_requested,
+        is_downloaded,
+        season,
+    };
+
+    let conn = db_pool.get()?;
+    let channel = load_channel(&conn, id)?;
+    let videos = database::list_channel_videos(&conn, channel.id, &filter, &page)?;
+    Ok(Json(videos.map(Video::from)))
+}
+
+/// Preview OPML or Takeout subscriptions.csv contents.
+#[utoipa::path(
+    tag = "channels",
+    request_body = ImportPreview,
+    responses(
+        (status = 200, body = Preview),
+        (status = 400, description = "No subscriptions found", body = ErrorBody),
+    )
+)]
+#[post("/channels/import/preview", format = "json", data = "<data>")]
+pub async fn post_channels_import_preview(
+    _admin: Admin,
+    data: Json<ImportPreview>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Preview>> {
+    let conn = db_pool.get()?;
+    subscriptions::preview(&conn, &data.contents)
+        .map(Json)
+        .map_err(|e| ApiError::bad_request(e.to_string()))
+}
+
+/// Queue CHANNEL-ADD for the previewed channels that are not followed yet.
+#[utoipa::path(
+    tag = "channels",
+    request_body = ImportChannels,
+    responses(
+        (status = 200, body = ImportResult),
+        (status = 400, description = "Invalid channel ID", body = ErrorBody),
+    )
+)]
+#[post("/channels/import", format = "json", data = "<data>")]
+pub async fn post_channels_import(
+    admin: Admin,
+    data: Json<ImportChannels>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<ImportResult>> {
+    let conn = db_pool.get()?;
+    let queued = subscriptions::import(&conn, &data.channel_ids, &admin.0.id)
+        .map_err(|e| ApiError::bad_request(e.to_string()))?;
+    Ok(Json(ImportResult { queued }))
+}
+
+/// Followed channels as OPML file, for feed readers.
+#[utoipa::path(
+    tag = "channels",
+    responses((status = 200, description = "OPML file", content_type = "text/x-opml", body = String))
+)]
+#[get("/channels/export.opml")]
+pub async fn get_channels_opml(
+    _user: User,
+    db_pool: &State<DBPool>,
+) -> ApiResult<(ContentType, String)> {
+    let conn = db_pool.get()?;
+    let opml = subscriptions::export_opml(&conn)?;
+    Ok((ContentType::new("text", "x-opml"), opml))
+}
+
+fn load_channel(conn: &rusqlite::Connection, id: i64) -> ApiResult<Channel> {
+    database::get_channel_by_id(conn, id)?.ok_or_else(|| ApiError::not_found("No such channel"))
+}
@@ -0,0 +1,81 @@
+//! v1 API endpoints for the application configuration
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rocket::{get, put, serde::json::Json, State};
+use std::collections::{BTreeMap, HashMap};
+use tracing::info;
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::config::{self, ConfigError};
+use crate::webui::auth::Admin;
+use crate::DBPool;
+
+/// Configuration values by key. Secrets are returned empty.
+#[utoipa::path(
+    tag = "configuration",
+    responses((status = 200, body = BTreeMap<String, String>))
+)]
+#[get("/config")]
+pub async fn get_config(
+    _admin: Admin,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<BTreeMap<String, String>>> {
+    let conn = db_pool.get()?;
+    Ok(Json(visible_values(&conn)?))
+}
+
+/// Update the given values, others are not changed. Nothing is saved if any of them is
+/// invalid, the errors are returned in `fields` by key.
+#[utoipa::path(
+    tag = "configuration",
+    request_body = HashMap<String, String>,
+    responses(
+        (status = 200, description = "Configuration after the update", body = BTreeMap<String, String>),
+        (status = 400, description = "Invalid values", body = ErrorBody),
+    )
+)]
+#[put("/config", format = "json", data = "<data>")]
+pub async fn put_config(
+    _admin: Admin,
+    data: Json<HashMap<String, String>>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<BTreeMap<String, String>>> {
+    let dbp = db_pool.inner().clone();
+    let values = data.into_inner();
+
+    // Path checks touch the filesystem
+    rocket::tokio::task::spawn_blocking(move || -> ApiResult<()> {
+        let mut conn = dbp.get()?;
+        match config::set_values(&mut conn, &values) {
+            Ok(_) => Ok(()),
+            Err(ConfigError::Invalid(errors)) => Err(ApiError::invalid_fields(
+                "Invalid configuration, nothing was saved",
+                errors,
+            )),
+            Err(ConfigError::Database(e)) => Err(e.into()),
+            Err(ConfigError::Other(e)) => Err(e.into()),
+        }
+    })
+    .await
+    .map_err(anyhow::Error::from)??;
+    info!("Configuration updated");
+
+    let conn = db_pool.get()?;
+    Ok(Json(visible_values(&conn)?))
+}
+
+fn visible_values(
+    conn: &PooledConnection<SqliteConnectionManager>,
+) -> anyhow::Result<BTreeMap<String, String>> {
+    Ok(config::get_values(conn)?
+        .into_iter()
+        .filter(|(key, _)| key != "first_time_setup")
+        .map(
+            |(key, value)| match config::SECRET_KEYS.contains(&key.as_str()) {
+                true => (key, String::new()),
+                false => (key, value),
+            },
+        )
+        .collect())
+}
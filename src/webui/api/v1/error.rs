@@ -0,0 +1,148 @@
+//! Uniform error responses of the v1 API:
+//! `{"error": {"code": "not_found", "message": "No such channel"}}`
+
+use rocket::{
+    catch,
+    http::Status,
+    response::{self, Responder},
+    serde::json::Json,
+    Request,
+};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use tracing::error;
+use utoipa::ToSchema;
+
+use crate::auth::UserError;
+
+pub type ApiResult<T> = Result<T, ApiError>;
+
+#[derive(Debug)]
+pub struct ApiError {
+    pub status: Status,
+    pub message: String,
+    /// Validation errors by field name, see `ApiError::invalid_fields`.
+    pub fields: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    pub error: ErrorDetails,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorDetails {
+    /// Machine readable, see `error_code`.
+    pub code: &'static str,
+    pub message: String,
+    /// Validation errors by field name, left out when there are none.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+}
+
+impl ApiError {
+    pub fn new(status: Status, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            message: message.into(),
+            fields: BTreeMap::new(),
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(Status::BadRequest, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(Status::NotFound, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(Status::Conflict, message)
+    }
+
+    /// 400 with the errors of each invalid field.
+    pub fn invalid_fields(message: impl Into<String>, fields: BTreeMap<String, String>) -> Self {
+        ApiError {
+            fields,
+            ..Self::bad_request(message)
+        }
+    }
+}
+
+/// Unexpected errors are logged, the client only gets 500.
+impl From<anyhow::Error> for ApiError {
+    fn from(e: anyhow::Error) -> Self {
+        error!("API request failed: {:#}", e);
+        Self::new(Status::InternalServerError, "Internal server error")
+    }
+}
+
+impl From<r2d2::Error> for ApiError {
+    fn from(e: r2d2::Error) -> Self {
+        error!("Failed to get DB connection: {}", e);
+        Self::new(Status::ServiceUnavailable, "Database is not available")
+    }
+}
+
+impl From<rusqlite::Error> for ApiError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::from(anyhow::Error::from(e))
+    }
+}
+
+impl From<UserError> for ApiError {
+    fn from(e: UserError) -> Self {
+        match e {
+            UserError::NotFound => Self::not_found(e.to_string()),
+            UserError::Protected => Self::new(Status::Forbidden, e.to_string()),
+            UserError::UsernameTaken => Self::conflict(e.to_string()),
+            UserError::Invalid(_) => Self::bad_request(e.to_string()),
+            UserError::Database(e) => Self::from(e),
+            UserError::Other(e) => Self::from(e),
+        }
+    }
+}
+
+/// Errors from the helpers outside v1, such as paging parameters.
+impl From<(Status, String)> for ApiError {
+    fn from((status, message): (Status, String)) -> Self {
+        Self::new(status, message)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for ApiError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let body = ErrorBody {
+            error: ErrorDetails {
+                code: error_code(self.status),
+                message: self.message,
+                fields: self.fields,
+            },
+        };
+        (self.status, Json(body)).respond_to(request)
+    }
+}
+
+/// Error code for the status.
+pub fn error_code(status: Status) -> &'static str {
+    match status.code {
+        400 => "bad_request",
+        401 => "unauthorized",
+        403 => "forbidden",
+        404 => "not_found",
+        409 => "conflict",
+        413 => "payload_too_large",
+        422 => "invalid_body",
+        502 => "bad_gateway",
+        503 => "unavailable",
+        500..=599 => "internal_error",
+        _ => "error",
+    }
+}
+
+/// Errors from Rocket itself (no such route, failed guards, unparseable body) in the same format.
+#[catch(default)]
+pub fn default_catcher(status: Status, _request: &Request) -> ApiError {
+    ApiError::new(status, status.reason().unwrap_or("Error"))
+}
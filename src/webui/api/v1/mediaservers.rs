@@ -0,0 +1,262 @@
+//! v1 API endpoints for media server integrations
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rocket::{
+    delete, get, http::Status, post, put, response::status::Created, serde::json::Json, State,
+};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use utoipa::ToSchema;
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::mediaserver;
+use crate::webui::auth::Admin;
+use crate::webui::url;
+use crate::DBPool;
+
+const SELECT_SERVER: &str =
+    "SELECT id, name, kind, url, library_id, path_remote, enabled FROM media_servers";
+
+/// Token is never returned.
+#[derive(Serialize, ToSchema)]
+pub struct MediaServer {
+    id: i64,
+    name: String,
+    /// JELLYFIN, EMBY or PLEX
+    kind: String,
+    url: String,
+    library_id: Option<String>,
+    path_remote: Option<String>,
+    enabled: bool,
+}
+
+impl MediaServer {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(MediaServer {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: row.get(2)?,
+            url: row.get(3)?,
+            library_id: row.get(4)?,
+            path_remote: row.get(5)?,
+            enabled: row.get(6)?,
+        })
+    }
+}
+
+/// Token is required for new servers, and kept as is when left out or empty on update.
+#[derive(Deserialize, ToSchema)]
+pub struct MediaServerInput {
+    name: String,
+    kind: String,
+    url: String,
+    token: Option<String>,
+    library_id: Option<String>,
+    path_remote: Option<String>,
+    enabled: bool,
+}
+
+impl MediaServerInput {
+    fn validate(&self) -> ApiResult<()> {
+        if self.name.trim().is_empty() {
+            return Err(ApiError::bad_request("Name is required"));
+        }
+        if !mediaserver::KINDS.contains(&self.kind.as_str()) {
+            return Err(ApiError::bad_request(format!(
+                "Unknown kind {}, use one of: {}",
+                self.kind,
+                mediaserver::KINDS.join(", ")
+            )));
+        }
+        if !(self.url.starts_with("http://") || self.url.starts_with("https://")) {
+            return Err(ApiError::bad_request("URL must be http:// or https:// URL"));
+        }
+        Ok(())
+    }
+
+    fn token(&self) -> Option<&str> {
+        self.token.as_deref().filter(|token| !token.is_empty())
+    }
+
+    fn library_id(&self) -> Option<&str> {
+        self.library_id.as_deref().filter(|id| !id.is_empty())
+    }
+
+    fn path_remote(&self) -> Option<&str> {
+        self.path_remote.as_deref().filter(|path| !path.is_empty())
+    }
+}
+
+#[utoipa::path(
+    tag = "mediaservers",
+    responses((status = 200, body = Vec<MediaServer>))
+)]
+#[get("/mediaservers")]
+pub async fn get_mediaservers(
+    _admin: Admin,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Vec<MediaServer>>> {
+    let conn = db_pool.get()?;
+    let mut stmt = conn.prepare(&format!("{} ORDER BY id", SELECT_SERVER))?;
+    let servers = stmt
+        .query_map([], MediaServer::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Json(servers))
+}
+
+#[utoipa::path(
+    tag = "mediaservers",
+    request_body = MediaServerInput,
+    responses(
+        (status = 201, description = "Media server was added", body = MediaServer),
+        (status = 400, description = "Missing name or token, unknown kind, or invalid URL", body = ErrorBody),
+    )
+)]
+#[post("/mediaservers", format = "json", data = "<data>")]
+pub async fn post_mediaserver(
+    _admin: Admin,
+    data: Json<MediaServerInput>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Created<Json<MediaServer>>> {
+    data.validate()?;
+    if data.token().is_none() {
+        return Err(ApiError::bad_request("Token is required"));
+    }
+
+    let conn = db_pool.get()?;
+    conn.execute(
+        "INSERT INTO media_servers (name, kind, url, token, library_id, path_remote, enabled)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            data.name.trim(),
+            data.kind,
+            data.url,
+            data.token(),
+            data.library_id(),
+            data.path_remote(),
+            data.enabled
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    let server = load_server(&conn, id)?;
+    Ok(Created::new(url_of(id)).body(Json(server)))
+}
+
+#[utoipa::path(
+    tag = "mediaservers",
+    params(("id" = i64, Path, description = "Media server ID")),
+    request_body = MediaServerInput,
+    responses(
+        (status = 200, body = MediaServer),
+        (status = 400, description = "Missing name, unknown kind, or invalid URL", body = ErrorBody),
+        (status = 404, description = "No such media server", body = ErrorBody),
+    )
+)]
+#[put("/mediaservers/<id>", format = "json", data = "<data>")]
+pub async fn put_mediaserver(
+    _admin: Admin,
+    id: i64,
+    data: Json<MediaServerInput>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<MediaServer>> {
+    data.validate()?;
+
+    let conn = db_pool.get()?;
+    let updated = conn.execute(
+        "UPDATE media_servers
+            SET name = ?1, kind = ?2, url = ?3, token = COALESCE(?4, token), library_id = ?5,
+                path_remote = ?6, enabled = ?7, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?8",
+        params![
+            data.name.trim(),
+            data.kind,
+            data.url,
+            data.token(),
+            data.library_id(),
+            data.path_remote(),
+            data.enabled,
+            id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(ApiError::not_found("No such media server"));
+    }
+
+    Ok(Json(load_server(&conn, id)?))
+}
+
+#[utoipa::path(
+    tag = "mediaservers",
+    params(("id" = i64, Path, description = "Media server ID")),
+    responses(
+        (status = 204, description = "Media server was removed"),
+        (status = 404, description = "No such media server", body = ErrorBody),
+    )
+)]
+#[delete("/mediaservers/<id>")]
+pub async fn delete_mediaserver(
+    _admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    match conn.execute("DELETE FROM media_servers WHERE id = ?1", params![id])? {
+        0 => Err(ApiError::not_found("No such media server")),
+        _ => Ok(Status::NoContent),
+    }
+}
+
+/// Connect to the media server with the saved settings.
+#[utoipa::path(
+    tag = "mediaservers",
+    params(("id" = i64, Path, description = "Media server ID")),
+    responses(
+        (status = 204, description = "Connection works"),
+        (status = 404, description = "No such media server", body = ErrorBody),
+        (status = 502, description = "Connection failed", body = ErrorBody),
+    )
+)]
+#[post("/mediaservers/<id>/test")]
+pub async fn post_mediaserver_test(
+    _admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    let server = {
+        let conn = db_pool.get()?;
+        load_server(&conn, id)?;
+        mediaserver::get_media_server(&conn, id)?
+    };
+
+    rocket::tokio::task::spawn_blocking(move || mediaserver::test_connection(&server))
+        .await
+        .map_err(anyhow::Error::from)?
+        .map_err(|e| {
+            warn!("Media server connection test failed: {:?}", e);
+            ApiError::new(
+                Status::BadGateway,
+                "Connecting to the media server failed, see the log for details",
+            )
+        })?;
+    Ok(Status::NoContent)
+}
+
+fn load_server(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: i64,
+) -> ApiResult<MediaServer> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1", SELECT_SERVER),
+        params![id],
+        MediaServer::from_row,
+    )
+    .optional()?
+    .ok_or_else(|| ApiError::not_found("No such media server"))
+}
+
+fn url_of(id: i64) -> String {
+    url(&format!("/api/v1/mediaservers/{}", id))
+}
@@ -0,0 +1,100 @@
+//! Versioned JSON API, mounted at /api/v1. Every endpoint takes and returns JSON, and
+//! errors are returned as `ApiError`.
+
+use rocket::{catchers, routes, Catcher, Route};
+
+mod error;
+pub use error::*;
+
+mod channels;
+pub use channels::*;
+
+mod videos;
+pub use videos::*;
+
+mod tasks;
+use tasks::load_task;
+pub use tasks::*;
+
+mod search;
+pub use search::*;
+
+mod user;
+pub use user::*;
+
+mod apikeys;
+pub use apikeys::*;
+
+mod config;
+pub use config::*;
+
+mod scheduler;
+pub use scheduler::*;
+
+mod notifications;
+pub use notifications::*;
+
+mod mediaservers;
+pub use mediaservers::*;
+
+mod openapi;
+pub use openapi::*;
+
+pub fn routes() -> Vec<Route> {
+    routes![
+        get_channels,
+        get_channel,
+        post_channel,
+        delete_channel,
+        post_channel_fetch,
+        get_channel_videos,
+        post_channels_import_preview,
+        post_channels_import,
+        get_channels_opml,
+        get_video,
+        get_video_tasks,
+        get_video_thumbnail,
+        post_video_download,
+        delete_video_files,
+        post_video_watched,
+        delete_video_watched,
+        post_video,
+        get_requests,
+        post_request_approve,
+        post_request_deny,
+        get_tasks,
+        get_task,
+        post_task,
+        post_task_retry,
+        post_task_cancel,
+        delete_task,
+        get_search,
+        get_user,
+        get_users,
+        post_user,
+        put_user,
+        delete_user,
+        get_apikeys,
+        post_apikey,
+        delete_apikey,
+        get_config,
+        put_config,
+        get_scheduler,
+        patch_scheduler,
+        post_scheduler_run,
+        get_notifications,
+        post_notification,
+        put_notification,
+        delete_notification,
+        post_notification_test,
+        get_mediaservers,
+        post_mediaserver,
+        put_mediaserver,
+        delete_mediaserver,
+        post_mediaserver_test,
+    ]
+}
+
+pub fn catchers() -> Vec<Catcher> {
+    catchers![default_catcher]
+}
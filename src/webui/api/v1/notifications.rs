@@ -0,0 +1,305 @@
+//! v1 API endpoints for notification targets
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rocket::{
+    delete, get, http::Status, post, put, response::status::Created, serde::json::Json, State,
+};
+use rusqlite::{params, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use url::Url;
+use utoipa::ToSchema;
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::notify;
+use crate::webui::auth::Admin;
+use crate::webui::url;
+use crate::DBPool;
+
+const SELECT_TARGET: &str = "SELECT id, name, kind, url, token IS NOT NULL, enabled,
+        on_download_done, on_download_fail, on_new_video
+    FROM notification_targets";
+
+/// URL is masked, webhook URLs carry tokens in the path or query.
+#[derive(Serialize, ToSchema)]
+pub struct NotificationTarget {
+    id: i64,
+    name: String,
+    /// WEBHOOK, DISCORD, SLACK, NTFY or GOTIFY
+    kind: String,
+    url: String,
+    has_token: bool,
+    enabled: bool,
+    on_download_done: bool,
+    on_download_fail: bool,
+    on_new_video: bool,
+}
+
+impl NotificationTarget {
+    fn from_row(row: &Row) -> rusqlite::Result<Self> {
+        Ok(NotificationTarget {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            kind: row.get(2)?,
+            url: mask_url(&row.get::<_, String>(3)?),
+            has_token: row.get(4)?,
+            enabled: row.get(5)?,
+            on_download_done: row.get(6)?,
+            on_download_fail: row.get(7)?,
+            on_new_video: row.get(8)?,
+        })
+    }
+}
+
+/// URL and token are kept as is when left out on update. Empty token removes it.
+#[derive(Deserialize, ToSchema)]
+pub struct NotificationInput {
+    name: String,
+    kind: String,
+    /// Required for new targets.
+    url: Option<String>,
+    token: Option<String>,
+    enabled: bool,
+    on_download_done: bool,
+    on_download_fail: bool,
+    on_new_video: bool,
+}
+
+impl NotificationInput {
+    fn validate(&self) -> ApiResult<()> {
+        if self.name.trim().is_empty() {
+            return Err(ApiError::bad_request("Name is required"));
+        }
+        if !notify::KINDS.contains(&self.kind.as_str()) {
+            return Err(ApiError::bad_request(format!(
+                "Unknown kind {}, use one of: {}",
+                self.kind,
+                notify::KINDS.join(", ")
+            )));
+        }
+        let is_http = |url: &String| url.starts_with("http://") || url.starts_with("https://");
+        if !self.url.as_ref().is_none_or(is_http) {
+            return Err(ApiError::bad_request("URL must be http:// or https:// URL"));
+        }
+        Ok(())
+    }
+}
+
+/// Scheme, host and port of the URL, the rest hidden.
+fn mask_url(url: &str) -> String {
+    let Ok(parsed) = Url::parse(url) else {
+        return "***".to_string();
+    };
+    let origin = parsed.origin().ascii_serialization();
+    match parsed.path() == "/" && parsed.query().is_none() && parsed.username().is_empty() {
+        true => origin,
+        false => format!("{}/***", origin),
+    }
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    responses((status = 200, body = Vec<NotificationTarget>))
+)]
+#[get("/notifications")]
+pub async fn get_notifications(
+    _admin: Admin,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Vec<NotificationTarget>>> {
+    let conn = db_pool.get()?;
+    let mut stmt = conn.prepare(&format!("{} ORDER BY id", SELECT_TARGET))?;
+    let targets = stmt
+        .query_map([], NotificationTarget::from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(Json(targets))
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    request_body = NotificationInput,
+    responses(
+        (status = 201, description = "Target was added", body = NotificationTarget),
+        (status = 400, description = "Missing name or URL, or unknown kind", body = ErrorBody),
+    )
+)]
+#[post("/notifications", format = "json", data = "<data>")]
+pub async fn post_notification(
+    _admin: Admin,
+    data: Json<NotificationInput>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Created<Json<NotificationTarget>>> {
+    data.validate()?;
+    let Some(url) = &data.url else {
+        return Err(ApiError::bad_request("URL is required"));
+    };
+    let token = data.token.as_deref().filter(|token| !token.is_empty());
+
+    let conn = db_pool.get()?;
+    conn.execute(
+        "INSERT INTO notification_targets
+            (name, kind, url, token, enabled, on_download_done, on_download_fail, on_new_video)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            data.name.trim(),
+            data.kind,
+            url,
+            token,
+            data.enabled,
+            data.on_download_done,
+            data.on_download_fail,
+            data.on_new_video
+        ],
+    )?;
+
+    let id = conn.last_insert_rowid();
+    let target = load_target(&conn, id)?;
+    Ok(Created::new(url_of(id)).body(Json(target)))
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(("id" = i64, Path, description = "Notification target ID")),
+    request_body = NotificationInput,
+    responses(
+        (status = 200, body = NotificationTarget),
+        (status = 400, description = "Missing name, or unknown kind", body = ErrorBody),
+        (status = 404, description = "No such notification target", body = ErrorBody),
+    )
+)]
+#[put("/notifications/<id>", format = "json", data = "<data>")]
+pub async fn put_notification(
+    _admin: Admin,
+    id: i64,
+    data: Json<NotificationInput>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<NotificationTarget>> {
+    data.validate()?;
+
+    let mut conn = db_pool.get()?;
+    let tx = conn.transaction()?;
+    let updated = tx.execute(
+        "UPDATE notification_targets
+            SET name = ?1, kind = ?2, url = coalesce(?3, url), enabled = ?4, on_download_done = ?5,
+                on_download_fail = ?6, on_new_video = ?7, updated_at = CURRENT_TIMESTAMP
+            WHERE id = ?8",
+        params![
+            data.name.trim(),
+            data.kind,
+            data.url,
+            data.enabled,
+            data.on_download_done,
+            data.on_download_fail,
+            data.on_new_video,
+            id
+        ],
+    )?;
+    if updated == 0 {
+        return Err(ApiError::not_found("No such notification target"));
+    }
+    if let Some(token) = &data.token {
+        let token = Some(token).filter(|token| !token.is_empty());
+        tx.execute(
+            "UPDATE notification_targets SET token = ?1 WHERE id = ?2",
+            params![token, id],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(Json(load_target(&conn, id)?))
+}
+
+#[utoipa::path(
+    tag = "notifications",
+    params(("id" = i64, Path, description = "Notification target ID")),
+    responses(
+        (status = 204, description = "Target was removed"),
+        (status = 404, description = "No such notification target", body = ErrorBody),
+    )
+)]
+#[delete("/notifications/<id>")]
+pub async fn delete_notification(
+    _admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    match conn.execute(
+        "DELETE FROM notification_targets WHERE id = ?1",
+        params![id],
+    )? {
+        0 => Err(ApiError::not_found("No such notification target")),
+        _ => Ok(Status::NoContent),
+    }
+}
+
+/// Send test notification synchronously, so the result can be shown to the user. Delivery
+/// errors are only logged, as they can contain the webhook URL.
+#[utoipa::path(
+    tag = "notifications",
+    params(("id" = i64, Path, description = "Notification target ID")),
+    responses(
+        (status = 204, description = "Test notification was sent"),
+        (status = 404, description = "No such notification target", body = ErrorBody),
+        (status = 502, description = "Sending failed", body = ErrorBody),
+    )
+)]
+#[post("/notifications/<id>/test")]
+pub async fn post_notification_test(
+    _admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    let target = {
+        let conn = db_pool.get()?;
+        load_target(&conn, id)?;
+        notify::get_target(&conn, id)?
+    };
+
+    rocket::tokio::task::spawn_blocking(move || notify::deliver(&target, &notify::Event::Test))
+        .await
+        .map_err(anyhow::Error::from)?
+        .map_err(|e| {
+            warn!("Test notification failed: {:?}", e);
+            ApiError::new(
+                Status::BadGateway,
+                "Sending the test notification failed, see the log for details",
+            )
+        })?;
+    Ok(Status::NoContent)
+}
+
+fn load_target(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: i64,
+) -> ApiResult<NotificationTarget> {
+    conn.query_row(
+        &format!("{} WHERE id = ?1", SELECT_TARGET),
+        params![id],
+        NotificationTarget::from_row,
+    )
+    .optional()?
+    .ok_or_else(|| ApiError::not_found("No such notification target"))
+}
+
+fn url_of(id: i64) -> String {
+    url(&format!("/api/v1/notifications/{}", id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn masks_url_tokens() {
+        assert_eq!(
+            mask_url("https://discord.com/api/webhooks/123/secret"),
+            "https://discord.com/***"
+        );
+        assert_eq!(
+            mask_url("https://ntfy.example.com:8443/?token=secret"),
+            "https://ntfy.example.com:8443/***"
+        );
+        assert_eq!(mask_url("http://10.0.0.2/"), "http://10.0.0.2");
+    }
+}
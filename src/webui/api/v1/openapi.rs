@@ -0,0 +1,208 @@
+//! OpenAPI document of the v1 API, generated from the route attributes and the
+//! request/response types. Served at /api/openapi.json and browsable at /api/docs/.
+
+use rocket::{get, serde::json::Json, Route};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, SecurityScheme};
+use utoipa::openapi::{OpenApi as OpenApiDoc, Server};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::{Config, SwaggerUi};
+
+use crate::webui::auth::User;
+use crate::webui::{base_path, url};
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Tubarr API",
+        description = "Channels, videos, download requests, the task queue and the settings of Tubarr.",
+        license(name = "MIT"),
+    ),
+    paths(
+        super::get_channels,
+        super::get_channel,
+        super::post_channel,
+        super::delete_channel,
+        super::post_channel_fetch,
+        super::get_channel_videos,
+        super::post_channels_import_preview,
+        super::post_channels_import,
+        super::get_channels_opml,
+        super::get_video,
+        super::get_video_tasks,
+        super::get_video_thumbnail,
+        super::post_video_download,
+        super::delete_video_files,
+        super::post_video_watched,
+        super::delete_video_watched,
+        super::post_video,
+        super::get_requests,
+        super::post_request_approve,
+        super::post_request_deny,
+        super::get_tasks,
+        super::get_task,
+        super::post_task,
+        super::post_task_retry,
+        super::post_task_cancel,
+        super::delete_task,
+        super::get_search,
+        super::get_user,
+        super::get_users,
+        super::post_user,
+        super::put_user,
+        super::delete_user,
+        super::get_apikeys,
+        super::post_apikey,
+        super::delete_apikey,
+        super::get_config,
+        super::put_config,
+        super::get_scheduler,
+        super::patch_scheduler,
+        super::post_scheduler_run,
+        super::get_notifications,
+        super::post_notification,
+        super::put_notification,
+        super::delete_notification,
+        super::post_notification_test,
+        super::get_mediaservers,
+        super::post_mediaserver,
+        super::put_mediaserver,
+        super::delete_mediaserver,
+        super::post_mediaserver_test,
+    ),
+    modifiers(&Security),
+    security(("api_key" = []), ("session" = [])),
+    tags(
+        (name = "channels", description = "Followed channels and their videos"),
+        (name = "videos", description = "Videos and search"),
+        (name = "requests", description = "Download requests waiting for approval"),
+        (name = "tasks", description = "Task queue"),
+        (name = "users", description = "The logged in user, and user accounts for admins"),
+        (name = "apikeys", description = "API keys of the logged in user"),
+        (name = "configuration", description = "Application settings"),
+        (name = "scheduler", description = "Persistent background tasks"),
+        (name = "notifications", description = "Notification targets"),
+        (name = "mediaservers", description = "Media servers refreshed after downloads"),
+    )
+)]
+pub struct ApiDoc;
+
+/// API key header and session cookie, see `webui::auth`.
+struct Security;
+
+impl Modify for Security {
+    fn modify(&self, openapi: &mut OpenApiDoc) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))),
+        );
+        components.add_security_scheme(
+            "session",
+            SecurityScheme::ApiKey(ApiKey::Cookie(ApiKeyValue::new("tubarr_session"))),
+        );
+    }
+}
+
+/// The spec, with the server URL under the configured base path.
+#[get("/openapi.json")]
+pub async fn get_openapi(_user: User) -> Json<OpenApiDoc> {
+    let mut openapi = ApiDoc::openapi();
+    openapi.servers = Some(vec![Server::new(url("/api/v1"))]);
+    Json(openapi)
+}
+
+/// Swagger UI at /api/docs/, using the bundled assets. Mounted at "/" because the handler
+/// matches the full request path.
+pub fn docs_routes() -> Vec<Route> {
+    SwaggerUi::new(format!("{}/api/docs/<_..>", base_path()))
+        .config(Config::new([url("/api/openapi.json")]))
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::*;
+
+    /// Every mounted v1 route is documented, and nothing else is.
+    #[test]
+    fn spec_matches_routes() {
+        let routes: BTreeSet<(String, String)> = super::super::routes()
+            .iter()
+            .map(|route| {
+                let path = route
+                    .uri
+                    .unmounted_origin
+                    .path()
+                    .segments()
+                    .map(|segment| match segment.strip_prefix('<') {
+                        Some(param) => format!("/{{{}}}", param.trim_end_matches('>')),
+                        None => format!("/{}", segment),
+                    })
+                    .collect::<String>();
+                (route.method.as_str().to_lowercase(), path)
+            })
+            .collect();
+
+        let openapi = ApiDoc::openapi();
+        let documented: BTreeSet<(String, String)> = openapi
+            .paths
+            .paths
+            .iter()
+            .flat_map(|(path, item)| {
+                let methods = [
+                    ("get", item.get.is_some()),
+                    ("post", item.post.is_some()),
+                    ("put", item.put.is_some()),
+                    ("patch", item.patch.is_some()),
+                    ("delete", item.delete.is_some()),
+                ];
+                methods
+                    .into_iter()
+                    .filter(|(_, documented)| *documented)
+                    .map(|(method, _)| (method.to_string(), path.clone()))
+            })
+            .collect();
+
+        assert_eq!(routes, documented);
+    }
+
+    #[test]
+    fn spec_has_schemas() {
+        let openapi = ApiDoc::openapi();
+        let schemas = &openapi.components.as_ref().unwrap().schemas;
+        for name in [
+            "Channel",
+            "Video",
+            "Task",
+            "ErrorBody",
+            "NewChannel",
+            "NewTask",
+            "SchedulerTask",
+            "NotificationTarget",
+            "MediaServer",
+            "ApiKey",
+            "UserAccount",
+        ] {
+            assert!(schemas.contains_key(name), "{} is missing", name);
+        }
+
+        // Path parameters are declared for every templated segment.
+        for (path, item) in &openapi.paths.paths {
+            let operations = [&item.get, &item.post, &item.put, &item.patch, &item.delete];
+            for operation in operations.into_iter().flatten() {
+                let params = operation.parameters.as_deref().unwrap_or_default();
+                for segment in path.split('/').filter(|s| s.starts_with('{')) {
+                    let name = segment.trim_matches(|c| c == '{' || c == '}');
+                    assert!(
+                        params.iter().any(|p| p.name == name),
+                        "{} has no parameter {}",
+                        path,
+                        name
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! v1 API endpoints for persistent background tasks (scheduler)
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rocket::{get, patch, post, serde::json::Json, State};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::taskrunner::scheduler::{get_persistent_tasks, parse_cron, PersistentTask};
+use crate::webui::auth::Admin;
+use crate::DBPool;
+
+/// Shortest allowed delay between runs.
+const MIN_DELAY_SEC: i64 = 60;
+
+#[derive(Serialize, ToSchema)]
+pub struct SchedulerTask {
+    id: isize,
+    task_name: String,
+    enabled: bool,
+    delay_sec: i64,
+    cron: Option<String>,
+    run_now: bool,
+    last_exec: String,
+    next_exec: Option<String>,
+    last_duration_ms: Option<i64>,
+    last_result: Option<String>,
+    last_error: Option<String>,
+}
+
+impl From<PersistentTask> for SchedulerTask {
+    fn from(task: PersistentTask) -> Self {
+        SchedulerTask {
+            next_exec: task.next_exec().map(|dt| dt.to_string()),
+            id: task.task_id,
+            task_name: task.task_name,
+            enabled: task.enabled,
+            delay_sec: task.delay_sec,
+            cron: task.cron,
+            run_now: task.run_now,
+            last_exec: task.last_exec.to_string(),
+            last_duration_ms: task.last_duration_ms,
+            last_result: task.last_result,
+            last_error: task.last_error,
+        }
+    }
+}
+
+/// Fields left out are not changed. Empty cron string removes the cron expression.
+#[derive(Deserialize, ToSchema)]
+pub struct SchedulerUpdate {
+    enabled: Option<bool>,
+    /// At least 60 seconds.
+    delay_sec: Option<i64>,
+    cron: Option<String>,
+}
+
+#[utoipa::path(tag = "scheduler", responses((status = 200, body = Vec<SchedulerTask>)))]
+#[get("/scheduler")]
+pub async fn get_scheduler(
+    _admin: Admin,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Vec<SchedulerTask>>> {
+    let conn = db_pool.get()?;
+    let tasks = get_persistent_tasks(&conn)?
+        .into_iter()
+        .map(SchedulerTask::from)
+        .collect();
+    Ok(Json(tasks))
+}
+
+#[utoipa::path(
+    tag = "scheduler",
+    params(("id" = i64, Path, description = "Scheduler task ID")),
+    request_body = SchedulerUpdate,
+    responses(
+        (status = 200, body = SchedulerTask),
+        (status = 400, description = "Too short delay or invalid cron expression", body = ErrorBody),
+        (status = 404, description = "No such scheduler task", body = ErrorBody),
+    )
+)]
+#[patch("/scheduler/<id>", format = "json", data = "<data>")]
+pub async fn patch_scheduler(
+    _admin: Admin,
+    id: i64,
+    data: Json<SchedulerUpdate>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<SchedulerTask>> {
+    // Validate before touching the database
+    if data
+        .delay_sec
+        .is_some_and(|delay_sec| delay_sec < MIN_DELAY_SEC)
+    {
+        return Err(ApiError::bad_request(format!(
+            "Delay must be at least {} seconds",
+            MIN_DELAY_SEC
+        )));
+    }
+    let cron = match data.cron.as_deref().map(str::trim) {
+        Some("") => Some(None),
+        Some(expr) => match parse_cron(expr) {
+            Ok(_) => Some(Some(expr.to_string())),
+            Err(e) => {
+                return Err(ApiError::bad_request(format!(
+                    "Invalid cron expression: {}",
+                    e
+                )))
+            }
+        },
+        None => None,
+    };
+
+    let mut conn = db_pool.get()?;
+    load_scheduler_task(&conn, id)?;
+
+    let tx = conn.transaction()?;
+    if let Some(enabled) = data.enabled {
+        tx.execute(
+            "UPDATE tasks_persistent SET enabled = ?1 WHERE id = ?2",
+            params![enabled, id],
+        )?;
+    }
+    if let Some(delay_sec) = data.delay_sec {
+        tx.execute(
+            "UPDATE tasks_persistent SET delay_sec = ?1 WHERE id = ?2",
+            params![delay_sec, id],
+        )?;
+    }
+    if let Some(cron) = cron {
+        tx.execute(
+            "UPDATE tasks_persistent SET cron = ?1 WHERE id = ?2",
+            params![cron, id],
+        )?;
+    }
+    tx.commit()?;
+
+    Ok(Json(load_scheduler_task(&conn, id)?))
+}
+
+/// Run the task on the next taskrunner loop, or after the current run if it's running.
+#[utoipa::path(
+    tag = "scheduler",
+    params(("id" = i64, Path, description = "Scheduler task ID")),
+    responses(
+        (status = 200, body = SchedulerTask),
+        (status = 404, description = "No such scheduler task", body = ErrorBody),
+    )
+)]
+#[post("/scheduler/<id>/run")]
+pub async fn post_scheduler_run(
+    _admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<SchedulerTask>> {
+    let conn = db_pool.get()?;
+    let updated = conn.execute(
+        "UPDATE tasks_persistent SET run_now = 1 WHERE id = ?1",
+        params![id],
+    )?;
+    if updated == 0 {
+        return Err(ApiError::not_found("No such scheduler task"));
+    }
+    Ok(Json(load_scheduler_task(&conn, id)?))
+}
+
+fn load_scheduler_task(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: i64,
+) -> ApiResult<SchedulerTask> {
+    get_persistent_tasks(conn)?
+        .into_iter()
+        .find(|task| task.task_id as i64 == id)
+        .map(SchedulerTask::from)
+        .ok_or_else(|| ApiError::not_found("No such scheduler task"))
+}
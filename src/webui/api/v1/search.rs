@@ -0,0 +1,66 @@
+//! v1 API endpoint for searching videos
+
+use chrono::NaiveDate;
+use rocket::{get, http::Status, serde::json::Json, State};
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::database::{self, SearchFilter, SearchResult};
+use crate::webui::auth::User;
+use crate::DBPool;
+
+/// Search video titles, descriptions and tags. Channel is the channel row ID, dates are
+/// YYYY-MM-DD and inclusive.
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("q" = String, Query, description = "Words to search for"),
+        ("channel" = Option<i64>, Query, description = "Channel row ID"),
+        ("from" = Option<String>, Query, format = Date),
+        ("to" = Option<String>, Query, format = Date),
+        ("downloaded" = Option<bool>, Query),
+        ("limit" = Option<u32>, Query, description = "Default 50, at most 200"),
+    ),
+    responses(
+        (status = 200, body = Vec<SearchResult>),
+        (status = 400, description = "Empty query or invalid date", body = ErrorBody),
+    )
+)]
+#[get("/search?<q>&<channel>&<from>&<to>&<downloaded>&<limit>")]
+#[allow(clippy::too_many_arguments)]
+pub async fn get_search(
+    _user: User,
+    q: Option<&str>,
+    channel: Option<i64>,
+    from: Option<&str>,
+    to: Option<&str>,
+    downloaded: Option<bool>,
+    limit: Option<u32>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Vec<SearchResult>>> {
+    let q = q.unwrap_or_default();
+    if q.trim().is_empty() {
+        return Err(ApiError::bad_request("Search query is empty"));
+    }
+
+    let filter = SearchFilter {
+        query: q.to_string(),
+        channel_id: channel,
+        from: parse_date(from)?,
+        to: parse_date(to)?,
+        downloaded,
+        limit: limit.unwrap_or(50),
+    };
+
+    let conn = db_pool.get()?;
+    Ok(Json(database::search_videos(&conn, &filter)?))
+}
+
+/// Empty value is the same as no value, so that the search form can send every field.
+fn parse_date(value: Option<&str>) -> Result<Option<NaiveDate>, (Status, String)> {
+    match value.filter(|value| !value.is_empty()) {
+        None => Ok(None),
+        Some(value) => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(Some)
+            .map_err(|_| (Status::BadRequest, format!("Invalid date: {}", value))),
+    }
+}
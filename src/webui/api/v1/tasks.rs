@@ -0,0 +1,183 @@
+//! v1 API endpoints for tasks
+
+use rocket::{
+    delete, get, http::Status, post, response::status::Created, serde::json::Json, State,
+};
+use rusqlite::Connection;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::database::{self, Page, Task, TaskFilter, TASK_SORT_COLUMNS};
+use crate::webui::api::Paging;
+use crate::webui::auth::{Admin, User};
+use crate::webui::url;
+use crate::DBPool;
+
+/// Task types that take an URL, and can be queued from the API.
+const URL_TASK_TYPES: [&str; 2] = ["VIDEO-DOWNLOAD", "CHANNEL-ADD"];
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewTask {
+    pub task_type: String,
+    pub url: String,
+}
+
+#[utoipa::path(
+    tag = "tasks",
+    params(
+        ("task_state" = Option<String>, Query, description = "WAIT, WIP, ERR, DONE or FAIL"),
+        ("task_type" = Option<String>, Query, example = "VIDEO-DOWNLOAD"),
+        Paging,
+    ),
+    responses(
+        (status = 200, body = Page<Task>),
+        (status = 400, description = "Invalid paging", body = ErrorBody),
+    )
+)]
+#[get("/tasks?<task_state>&<task_type>&<paging..>")]
+pub async fn get_tasks(
+    _user: User,
+    task_state: Option<String>,
+    task_type: Option<String>,
+    paging: Paging,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Page<Task>>> {
+    let page = paging.request(&TASK_SORT_COLUMNS)?;
+    let filter = TaskFilter {
+        task_state,
+        task_type,
+    };
+    let conn = db_pool.get()?;
+    Ok(Json(database::list_tasks(&conn, &filter, &page)?))
+}
+
+#[utoipa::path(
+    tag = "tasks",
+    params(("id" = i64, Path, description = "Task ID")),
+    responses(
+        (status = 200, body = Task),
+        (status = 404, description = "No such task", body = ErrorBody),
+    )
+)]
+#[get("/tasks/<id>")]
+pub async fn get_task(_user: User, id: i64, db_pool: &State<DBPool>) -> ApiResult<Json<Task>> {
+    let conn = db_pool.get()?;
+    Ok(Json(load_task(&conn, id)?))
+}
+
+#[utoipa::path(
+    tag = "tasks",
+    request_body = NewTask,
+    responses(
+        (status = 201, description = "Task was queued", body = Task),
+        (status = 400, description = "Unknown task type or empty URL", body = ErrorBody),
+    )
+)]
+#[post("/tasks", format = "json", data = "<data>")]
+pub async fn post_task(
+    admin: Admin,
+    data: Json<NewTask>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Created<Json<Task>>> {
+    if !URL_TASK_TYPES.contains(&data.task_type.as_str()) {
+        return Err(ApiError::bad_request(format!(
+            "Unknown task type {}, use one of: {}",
+            data.task_type,
+            URL_TASK_TYPES.join(", ")
+        )));
+    }
+    let url = data.url.trim();
+    if url.is_empty() {
+        return Err(ApiError::bad_request("URL is empty"));
+    }
+
+    let conn = db_pool.get()?;
+    let task_id = database::enqueue_url_task(&conn, &data.task_type, url, &admin.0.id)?;
+    let task = load_task(&conn, task_id)?;
+    Ok(Created::new(url_of(task_id)).body(Json(task)))
+}
+
+/// Start failed task again. 409 if the task is not ERR or FAIL.
+#[utoipa::path(
+    tag = "tasks",
+    params(("id" = i64, Path, description = "Task ID")),
+    responses(
+        (status = 200, body = Task),
+        (status = 404, description = "No such task", body = ErrorBody),
+        (status = 409, description = "Task has not failed", body = ErrorBody),
+    )
+)]
+#[post("/tasks/<id>/retry")]
+pub async fn post_task_retry(
+    _admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Task>> {
+    let conn = db_pool.get()?;
+    let task = load_task(&conn, id)?;
+    if !database::retry_task(&conn, id)? {
+        return Err(ApiError::conflict(format!(
+            "Task is {}, only failed tasks can be retried",
+            task.task_state
+        )));
+    }
+    Ok(Json(load_task(&conn, id)?))
+}
+
+/// Cancel task that is not running yet. 409 if the task is not WAIT or ERR.
+#[utoipa::path(
+    tag = "tasks",
+    params(("id" = i64, Path, description = "Task ID")),
+    responses(
+        (status = 200, body = Task),
+        (status = 404, description = "No such task", body = ErrorBody),
+        (status = 409, description = "Task is running or finished", body = ErrorBody),
+    )
+)]
+#[post("/tasks/<id>/cancel")]
+pub async fn post_task_cancel(
+    _admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Task>> {
+    let conn = db_pool.get()?;
+    let task = load_task(&conn, id)?;
+    if !database::cancel_task(&conn, id)? {
+        return Err(ApiError::conflict(format!(
+            "Task is {}, only waiting tasks can be cancelled",
+            task.task_state
+        )));
+    }
+    Ok(Json(load_task(&conn, id)?))
+}
+
+/// Remove task from the queue. 409 if the task is running.
+#[utoipa::path(
+    tag = "tasks",
+    params(("id" = i64, Path, description = "Task ID")),
+    responses(
+        (status = 204, description = "Task was removed"),
+        (status = 404, description = "No such task", body = ErrorBody),
+        (status = 409, description = "Task is running", body = ErrorBody),
+    )
+)]
+#[delete("/tasks/<id>")]
+pub async fn delete_task(_admin: Admin, id: i64, db_pool: &State<DBPool>) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    load_task(&conn, id)?;
+    match database::delete_task(&conn, id)? {
+        true => Ok(Status::NoContent),
+        false => Err(ApiError::conflict(
+            "Task is running, it can be removed when it has finished",
+        )),
+    }
+}
+
+pub(super) fn load_task(conn: &Connection, id: i64) -> ApiResult<Task> {
+    database::get_task(conn, id)?.ok_or_else(|| ApiError::not_found("No such task"))
+}
+
+fn url_of(task_id: i64) -> String {
+    url(&format!("/api/v1/tasks/{}", task_id))
+}
@@ -0,0 +1,164 @@
+//! v1 API endpoints for the logged in user, and for managing users
+
+use r2d2::PooledConnection;
+use r2d2_sqlite::SqliteConnectionManager;
+use rocket::{
+    delete, get, http::Status, post, put, response::status::Created, serde::json::Json, State,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use super::{ApiError, ApiResult, ErrorBody};
+use crate::auth::{self, Role, UserAccount};
+use crate::webui::auth::{Admin, User};
+use crate::webui::url;
+use crate::DBPool;
+
+#[derive(Serialize, ToSchema)]
+pub struct CurrentUser {
+    username: String,
+    role: &'static str,
+}
+
+impl From<User> for CurrentUser {
+    fn from(user: User) -> Self {
+        CurrentUser {
+            username: user.0.username,
+            role: user.0.role.as_db(),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewUser {
+    username: String,
+    password: String,
+    /// ADMIN or REQUESTER
+    role: String,
+}
+
+/// Password is changed only when given.
+#[derive(Deserialize, ToSchema)]
+pub struct UserUpdate {
+    /// ADMIN or REQUESTER
+    role: String,
+    enabled: bool,
+    password: Option<String>,
+}
+
+/// Logged in user, or the owner of the API key.
+#[utoipa::path(tag = "users", responses((status = 200, body = CurrentUser)))]
+#[get("/user")]
+pub async fn get_user(user: User) -> Json<CurrentUser> {
+    Json(CurrentUser::from(user))
+}
+
+#[utoipa::path(tag = "users", responses((status = 200, body = Vec<UserAccount>)))]
+#[get("/users")]
+pub async fn get_users(
+    _admin: Admin,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Vec<UserAccount>>> {
+    let conn = db_pool.get()?;
+    Ok(Json(auth::get_user_accounts(&conn)?))
+}
+
+#[utoipa::path(
+    tag = "users",
+    request_body = NewUser,
+    responses(
+        (status = 201, description = "User was created", body = UserAccount),
+        (status = 400, description = "Invalid username, password or role", body = ErrorBody),
+        (status = 409, description = "Username is already taken", body = ErrorBody),
+    )
+)]
+#[post("/users", format = "json", data = "<data>")]
+pub async fn post_user(
+    _admin: Admin,
+    data: Json<NewUser>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Created<Json<UserAccount>>> {
+    let role = parse_role(&data.role)?;
+
+    let conn = db_pool.get()?;
+    let id = hex::encode(auth::create_local_user(
+        &conn,
+        &data.username,
+        &data.password,
+        role,
+    )?);
+    let account = load_account(&conn, &id)?;
+    Ok(Created::new(url(&format!("/api/v1/users/{}", id))).body(Json(account)))
+}
+
+#[utoipa::path(
+    tag = "users",
+    params(("id" = String, Path, description = "User ID, hex encoded")),
+    request_body = UserUpdate,
+    responses(
+        (status = 200, body = UserAccount),
+        (status = 400, description = "Invalid password or role", body = ErrorBody),
+        (status = 403, description = "Protected user can't be disabled or demoted", body = ErrorBody),
+        (status = 404, description = "No such user", body = ErrorBody),
+    )
+)]
+#[put("/users/<id>", format = "json", data = "<data>")]
+pub async fn put_user(
+    _admin: Admin,
+    id: &str,
+    data: Json<UserUpdate>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<UserAccount>> {
+    let role = parse_role(&data.role)?;
+    let user_id = decode_id(id)?;
+    let password = data
+        .password
+        .as_deref()
+        .filter(|password| !password.is_empty());
+
+    let conn = db_pool.get()?;
+    auth::update_user_account(&conn, &user_id, role, data.enabled, password)?;
+    Ok(Json(load_account(&conn, &hex::encode(user_id))?))
+}
+
+#[utoipa::path(
+    tag = "users",
+    params(("id" = String, Path, description = "User ID, hex encoded")),
+    responses(
+        (status = 204, description = "User was removed"),
+        (status = 403, description = "Protected user can't be removed", body = ErrorBody),
+        (status = 404, description = "No such user", body = ErrorBody),
+    )
+)]
+#[delete("/users/<id>")]
+pub async fn delete_user(_admin: Admin, id: &str, db_pool: &State<DBPool>) -> ApiResult<Status> {
+    let user_id = decode_id(id)?;
+    let conn = db_pool.get()?;
+    auth::delete_user_account(&conn, &user_id)?;
+    Ok(Status::NoContent)
+}
+
+/// Unlike `Role::from_db`, unknown roles are rejected.
+fn parse_role(role: &str) -> ApiResult<Role> {
+    match role {
+        "ADMIN" | "REQUESTER" => Ok(Role::from_db(role)),
+        _ => Err(ApiError::bad_request(format!(
+            "Unknown role {}, use ADMIN or REQUESTER",
+            role
+        ))),
+    }
+}
+
+fn decode_id(id: &str) -> ApiResult<Vec<u8>> {
+    hex::decode(id).map_err(|_| ApiError::not_found("No such user"))
+}
+
+fn load_account(
+    conn: &PooledConnection<SqliteConnectionManager>,
+    id: &str,
+) -> ApiResult<UserAccount> {
+    auth::get_user_accounts(conn)?
+        .into_iter()
+        .find(|account| account.id == id)
+        .ok_or_else(|| ApiError::not_found("No such user"))
+}
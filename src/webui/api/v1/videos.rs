@@ -0,0 +1,406 @@
+//! v1 API endpoints for videos and download requests
+
+use rocket::{
+    delete,
+    fs::NamedFile,
+    get,
+    http::Status,
+    post,
+    response::status::{Accepted, Custom},
+    serde::json::Json,
+    State,
+};
+use rusqlite::Connection;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use utoipa::ToSchema;
+
+use super::{load_task, ApiError, ApiResult, ErrorBody};
+use crate::auth::Role;
+use crate::database::{self, Task};
+use crate::library::{self, VideoFiles};
+use crate::mediaserver;
+use crate::requests::{self, RequestOutcome, VideoRequest};
+use crate::webui::auth::{Admin, User};
+use crate::DBPool;
+
+#[derive(Serialize, ToSchema)]
+pub struct Video {
+    #[serde(flatten)]
+    pub video: database::Video,
+    /// Year of release, or "unknown".
+    pub season: String,
+}
+
+impl From<database::Video> for Video {
+    fn from(video: database::Video) -> Self {
+        Video {
+            season: video.season(),
+            video,
+        }
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct VideoDetails {
+    #[serde(flatten)]
+    pub video: Video,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub video_codec: Option<String>,
+    pub audio_codec: Option<String>,
+    /// Downloaded files, if they are still there.
+    pub files: Option<VideoFiles>,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct NewVideoRequest {
+    pub url: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CreatedRequest {
+    pub request_id: i64,
+    /// Download task, if the request didn't need approval.
+    pub task_id: Option<i64>,
+}
+
+/// Video by its ID on the site.
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("domain" = String, Path, example = "youtube.com"),
+        ("video_id" = String, Path, description = "ID on the site"),
+    ),
+    responses(
+        (status = 200, body = VideoDetails),
+        (status = 404, description = "No such video", body = ErrorBody),
+    )
+)]
+#[get("/videos/<domain>/<video_id>")]
+pub async fn get_video(
+    _user: User,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<VideoDetails>> {
+    let conn = db_pool.get()?;
+    let video = load_video(&conn, domain, video_id)?;
+    let info = database::get_download_info(&conn, video.id)?.unwrap_or_default();
+
+    Ok(Json(VideoDetails {
+        video: Video::from(video),
+        tags: info
+            .tags
+            .iter()
+            .flat_map(|tags| tags.split(','))
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect(),
+        files: info
+            .file_path
+            .as_deref()
+            .map(Path::new)
+            .and_then(library::inspect),
+        description: info.description,
+        video_codec: info.video_codec,
+        audio_codec: info.audio_codec,
+    }))
+}
+
+/// Download tasks of the video, newest first.
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("domain" = String, Path, example = "youtube.com"),
+        ("video_id" = String, Path, description = "ID on the site"),
+    ),
+    responses(
+        (status = 200, body = Vec<Task>),
+        (status = 404, description = "No such video", body = ErrorBody),
+    )
+)]
+#[get("/videos/<domain>/<video_id>/tasks")]
+pub async fn get_video_tasks(
+    _user: User,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Vec<Task>>> {
+    let conn = db_pool.get()?;
+    let video = load_video(&conn, domain, video_id)?;
+    Ok(Json(database::get_video_tasks(
+        &conn,
+        &video.url,
+        &video.video_id,
+    )?))
+}
+
+/// Thumbnail downloaded with the video.
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("domain" = String, Path, example = "youtube.com"),
+        ("video_id" = String, Path, description = "ID on the site"),
+    ),
+    responses(
+        (status = 200, description = "Thumbnail image", content_type = "image/jpeg"),
+        (status = 404, description = "No such video or thumbnail", body = ErrorBody),
+    )
+)]
+#[get("/videos/<domain>/<video_id>/thumbnail")]
+pub async fn get_video_thumbnail(
+    _user: User,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> ApiResult<NamedFile> {
+    let thumbnail = {
+        let conn = db_pool.get()?;
+        let video = load_video(&conn, domain, video_id)?;
+        database::get_download_info(&conn, video.id)?
+            .and_then(|info| info.file_path)
+            .and_then(|path| library::thumbnail(Path::new(&path)))
+            .ok_or_else(|| ApiError::not_found("No thumbnail"))?
+    };
+    NamedFile::open(thumbnail)
+        .await
+        .map_err(|_| ApiError::not_found("No thumbnail"))
+}
+
+/// Download the video again. 409 if it's being downloaded already.
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("domain" = String, Path, example = "youtube.com"),
+        ("video_id" = String, Path, description = "ID on the site"),
+    ),
+    responses(
+        (status = 202, description = "VIDEO-DOWNLOAD task was queued", body = Task),
+        (status = 404, description = "No such video", body = ErrorBody),
+        (status = 409, description = "Download is waiting or running", body = ErrorBody),
+    )
+)]
+#[post("/videos/<domain>/<video_id>/download")]
+pub async fn post_video_download(
+    admin: Admin,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Accepted<Json<Task>>> {
+    let conn = db_pool.get()?;
+    let video = load_video(&conn, domain, video_id)?;
+    if let Some(task_id) = database::find_pending_url_task(&conn, "VIDEO-DOWNLOAD", &video.url)? {
+        return Err(ApiError::conflict(format!(
+            "Video is already being downloaded by task {}",
+            task_id
+        )));
+    }
+
+    let task_id = requests::enqueue_download(&conn, &video.url, &admin.0.id)?;
+    Ok(Accepted(Json(load_task(&conn, task_id)?)))
+}
+
+/// Delete the downloaded files: video, thumbnail and subtitles. The video stays requested.
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("domain" = String, Path, example = "youtube.com"),
+        ("video_id" = String, Path, description = "ID on the site"),
+    ),
+    responses(
+        (status = 204, description = "Files were deleted"),
+        (status = 404, description = "No such video, or no files", body = ErrorBody),
+    )
+)]
+#[delete("/videos/<domain>/<video_id>/files")]
+pub async fn delete_video_files(
+    _admin: Admin,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    let video = load_video(&conn, domain, video_id)?;
+    let file_path = database::get_download_info(&conn, video.id)?
+        .and_then(|info| info.file_path)
+        .ok_or_else(|| ApiError::not_found("Files of the video are not known"))?;
+
+    let file_path = Path::new(&file_path);
+    library::remove(file_path)?;
+    database::clear_video_files(&conn, video.id)?;
+    if let Some(folder) = file_path.parent() {
+        mediaserver::queue_refresh(folder);
+    }
+    Ok(Status::NoContent)
+}
+
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("domain" = String, Path, example = "youtube.com"),
+        ("video_id" = String, Path, description = "ID on the site"),
+    ),
+    responses(
+        (status = 204, description = "Video is marked watched"),
+        (status = 404, description = "No such video", body = ErrorBody),
+    )
+)]
+#[post("/videos/<domain>/<video_id>/watched")]
+pub async fn post_video_watched(
+    _user: User,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    set_watched(db_pool, domain, video_id, true)
+}
+
+#[utoipa::path(
+    tag = "videos",
+    params(
+        ("domain" = String, Path, example = "youtube.com"),
+        ("video_id" = String, Path, description = "ID on the site"),
+    ),
+    responses(
+        (status = 204, description = "Video is marked not watched"),
+        (status = 404, description = "No such video", body = ErrorBody),
+    )
+)]
+#[delete("/videos/<domain>/<video_id>/watched")]
+pub async fn delete_video_watched(
+    _user: User,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    set_watched(db_pool, domain, video_id, false)
+}
+
+/// Request download. 201 when the download was queued, 202 when the request waits for approval.
+#[utoipa::path(
+    tag = "videos",
+    request_body = NewVideoRequest,
+    responses(
+        (status = 201, description = "Download task was queued", body = CreatedRequest),
+        (status = 202, description = "Request waits for approval", body = CreatedRequest),
+        (status = 400, description = "Not a http(s) URL", body = ErrorBody),
+    )
+)]
+#[post("/videos", format = "json", data = "<data>")]
+pub async fn post_video(
+    user: User,
+    data: Json<NewVideoRequest>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Custom<Json<CreatedRequest>>> {
+    let url = data.url.trim();
+    if !requests::is_valid_url(url) {
+        return Err(ApiError::bad_request(
+            "Video URL must be http:// or https:// URL",
+        ));
+    }
+
+    let conn = db_pool.get()?;
+    let response = match requests::request_video(&conn, &user.0, url)? {
+        RequestOutcome::Queued {
+            request_id,
+            task_id,
+        } => Custom(
+            Status::Created,
+            Json(CreatedRequest {
+                request_id,
+                task_id: Some(task_id),
+            }),
+        ),
+        RequestOutcome::Pending { request_id } => Custom(
+            Status::Accepted,
+            Json(CreatedRequest {
+                request_id,
+                task_id: None,
+            }),
+        ),
+    };
+    Ok(response)
+}
+
+/// Admins see every request, others only their own.
+#[utoipa::path(
+    tag = "requests",
+    params(("state" = Option<String>, Query, description = "PENDING, APPROVED or DENIED")),
+    responses((status = 200, body = Vec<VideoRequest>))
+)]
+#[get("/requests?<state>")]
+pub async fn get_requests(
+    user: User,
+    state: Option<&str>,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<Vec<VideoRequest>>> {
+    let user_id = match user.0.role {
+        Role::Admin => None,
+        Role::Requester => Some(user.0.id.as_slice()),
+    };
+
+    let conn = db_pool.get()?;
+    Ok(Json(requests::get_requests(&conn, user_id, state)?))
+}
+
+#[utoipa::path(
+    tag = "requests",
+    params(("id" = i64, Path, description = "Request ID")),
+    responses(
+        (status = 200, description = "Download task was queued", body = CreatedRequest),
+        (status = 404, description = "No such pending request", body = ErrorBody),
+    )
+)]
+#[post("/requests/<id>/approve")]
+pub async fn post_request_approve(
+    admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Json<CreatedRequest>> {
+    let conn = db_pool.get()?;
+    match requests::approve_request(&conn, &admin.0, id)? {
+        Some(task_id) => Ok(Json(CreatedRequest {
+            request_id: id,
+            task_id: Some(task_id),
+        })),
+        None => Err(ApiError::not_found("No such pending request")),
+    }
+}
+
+#[utoipa::path(
+    tag = "requests",
+    params(("id" = i64, Path, description = "Request ID")),
+    responses(
+        (status = 204, description = "Request was denied"),
+        (status = 404, description = "No such pending request", body = ErrorBody),
+    )
+)]
+#[post("/requests/<id>/deny")]
+pub async fn post_request_deny(
+    admin: Admin,
+    id: i64,
+    db_pool: &State<DBPool>,
+) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    match requests::deny_request(&conn, &admin.0, id)? {
+        true => Ok(Status::NoContent),
+        false => Err(ApiError::not_found("No such pending request")),
+    }
+}
+
+fn set_watched(
+    db_pool: &DBPool,
+    domain: &str,
+    video_id: &str,
+    is_watched: bool,
+) -> ApiResult<Status> {
+    let conn = db_pool.get()?;
+    let video = load_video(&conn, domain, video_id)?;
+    database::set_video_watched(&conn, video.id, is_watched)?;
+    Ok(Status::NoContent)
+}
+
+fn load_video(conn: &Connection, domain: &str, video_id: &str) -> ApiResult<database::Video> {
+    database::get_video(conn, domain, video_id)?.ok_or_else(|| ApiError::not_found("No such video"))
+}
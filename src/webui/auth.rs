@@ -0,0 +1,151 @@
+//! Request guard for logged in users, and the catchers for failed logins.
+//!
+//! Every /api and page route takes `User` as an argument. Requests without valid session
+//! cookie get 401, which the page catcher turns into redirect to the login page.
+//! /api routes and /metrics also accept API keys, from X-Api-Key header or api_key query parameter.
+//! Key used outside of its scope gets 403. Behind a trusted reverse proxy the username
+//! header is used when there's no session. Admin-only routes take `Admin` instead.
+//! The exceptions are the login routes and /api/health/live.
+
+use rocket::http::Status;
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Redirect;
+use rocket::serde::json::Json;
+use rocket::{catch, State};
+use serde_json::{json, Value};
+use tracing::error;
+
+use crate::auth;
+use crate::webui::{base_path, url};
+use crate::DBPool;
+
+/// Name of the session cookie.
+pub const SESSION_COOKIE: &str = "tubarr_session";
+
+/// Header for API keys.
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+/// Query parameter for API keys, for clients that can't set headers.
+pub const API_KEY_QUERY: &str = "api_key";
+
+/// Logged in user of the request.
+pub struct User(pub auth::User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for User {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let db_pool = match req.guard::<&State<DBPool>>().await {
+            Outcome::Success(db_pool) => db_pool,
+            _ => return Outcome::Error((Status::InternalServerError, ())),
+        };
+        let conn = match db_pool.get() {
+            Ok(conn) => conn,
+            Err(e) => {
+                error!("Failed to get DB connection: {}", e);
+                return Outcome::Error((Status::InternalServerError, ()));
+            }
+        };
+
+        // Browser session
+        if let Some(cookie) = req.cookies().get(SESSION_COOKIE) {
+            match auth::get_session_user(&conn, cookie.value()) {
+                Ok(Some(user)) => return Outcome::Success(User(user)),
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Failed to look up session: {:?}", e);
+                    return Outcome::Error((Status::InternalServerError, ()));
+                }
+            }
+        }
+
+        // Username from trusted reverse proxy
+        if let Some(remote) = req.remote() {
+            match auth::trusted_proxy_header(&conn, remote.ip()) {
+                Ok(Some(header)) => {
+                    if let Some(username) = req.headers().get_one(&header) {
+                        return match auth::proxy_login(&conn, username) {
+                            Ok(Some(user)) => Outcome::Success(User(user)),
+                            Ok(None) => Outcome::Error((Status::Forbidden, ())),
+                            Err(e) => {
+                                error!("Failed proxy login: {:?}", e);
+                                Outcome::Error((Status::InternalServerError, ()))
+                            }
+                        };
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    error!("Failed to read proxy settings: {:?}", e);
+                    return Outcome::Error((Status::InternalServerError, ()));
+                }
+            }
+        }
+
+        // API key, accepted only on /api routes and /metrics
+        let route_path = match req.route() {
+            Some(route) => route.uri.path(),
+            None => return Outcome::Error((Status::Unauthorized, ())),
+        };
+        let route_path = route_path.strip_prefix(base_path()).unwrap_or(route_path);
+        if !route_path.starts_with("/api/") && route_path != "/metrics" {
+            return Outcome::Error((Status::Unauthorized, ()));
+        }
+        let key = match req.headers().get_one(API_KEY_HEADER) {
+            Some(key) => Some(key.to_string()),
+            None => req
+                .query_value::<String>(API_KEY_QUERY)
+                .and_then(|key| key.ok()),
+        };
+        let Some(key) = key else {
+            return Outcome::Error((Status::Unauthorized, ()));
+        };
+
+        match auth::get_api_key_user(&conn, &key) {
+            Ok(Some((user, scope))) => {
+                if scope.allows(req.method().as_str(), route_path) {
+                    Outcome::Success(User(user))
+                } else {
+                    Outcome::Error((Status::Forbidden, ()))
+                }
+            }
+            Ok(None) => Outcome::Error((Status::Unauthorized, ())),
+            Err(e) => {
+                error!("Failed to look up API key: {:?}", e);
+                Outcome::Error((Status::InternalServerError, ()))
+            }
+        }
+    }
+}
+
+/// Logged in user with admin role. Others get 403.
+pub struct Admin(pub auth::User);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Admin {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        match req.guard::<User>().await {
+            Outcome::Success(User(user)) if user.role == auth::Role::Admin => {
+                Outcome::Success(Admin(user))
+            }
+            Outcome::Success(_) => Outcome::Error((Status::Forbidden, ())),
+            Outcome::Error(e) => Outcome::Error(e),
+            Outcome::Forward(status) => Outcome::Forward(status),
+        }
+    }
+}
+
+/// Pages send the browser to the login page.
+#[catch(401)]
+pub fn unauthorized_page() -> Redirect {
+    Redirect::to(url("/login"))
+}
+
+/// API callers get plain 401.
+#[catch(401)]
+pub fn unauthorized_api() -> Json<Value> {
+    Json(json!({ "error": "unauthorized" }))
+}
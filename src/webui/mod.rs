@@ -1,5 +1,6 @@
 use anyhow::Result;
 use rocket::{
+    catchers,
     fairing::AdHoc,
     form::Form,
     fs::{relative, FileServer},
@@ -12,21 +13,65 @@ use rocket::{http::ext::IntoCollection, response::content};
 use rocket::{Build, Rocket, Shutdown, State};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::net::IpAddr;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::OnceLock;
 use tokio::time::{self, sleep, Duration};
 
 use super::DBPool;
 use super::FLAG_SHUTDOWN;
 
 mod api;
+mod auth;
 mod pages;
 mod statics;
 
-pub fn run(dbp: DBPool) {
+static BASE_PATH: OnceLock<String> = OnceLock::new();
+
+/// Where and how the web UI is served. Rocket defaults are used for the ones not given.
+#[derive(Debug, Clone, Default)]
+pub struct ServerConfig {
+    pub address: Option<IpAddr>,
+    pub port: Option<u16>,
+    /// Certificate chain and private key, PEM. HTTPS is used when both are given.
+    pub tls: Option<(PathBuf, PathBuf)>,
+    /// URL path the web UI is served under, eg. "/tubarr". Normalized by `normalize_base_path`.
+    pub base_path: String,
+}
+
+/// URL path the web UI is served under, without trailing slash. Empty when served at the root.
+pub fn base_path() -> &'static str {
+    BASE_PATH.get().map(String::as_str).unwrap_or("")
+}
+
+/// Absolute URL path of a page or API route, eg. url("/login") is "/tubarr/login".
+pub fn url(path: &str) -> String {
+    format!("{}{}", base_path(), path)
+}
+
+/// Base path with leading slash and without trailing slash, "" for the root. Pages embed it
+/// as it is, so only plain URL path characters are allowed.
+pub fn normalize_base_path(path: &str) -> Result<String> {
+    let path = path.trim().trim_matches('/');
+    let is_valid = path
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-._~/".contains(c));
+    if !is_valid || path.contains("//") {
+        return Err(anyhow::anyhow!("Invalid base path: {}", path));
+    }
+
+    match path.is_empty() {
+        true => Ok(String::new()),
+        false => Ok(format!("/{}", path)),
+    }
+}
+
+pub fn run(dbp: DBPool, server: ServerConfig) {
     let rt = tokio::runtime::Runtime::new().expect("failed to create runtime");
 
     rt.block_on(async {
-        rocket(dbp)
+        rocket(dbp, server)
             .await
             .expect("Failed to build rocket")
             .launch()
@@ -39,32 +84,67 @@ pub fn run(dbp: DBPool) {
 }
 
 // Rocket configuration and setup function
-pub async fn rocket(dbp: DBPool) -> Result<Rocket<Ignite>> {
+pub async fn rocket(dbp: DBPool, server: ServerConfig) -> Result<Rocket<Ignite>> {
+    let mut figment = rocket::Config::figment();
+    if let Some(address) = server.address {
+        figment = figment.merge(("address", address));
+    }
+    if let Some(port) = server.port {
+        figment = figment.merge(("port", port));
+    }
+    if let Some((certs, key)) = server.tls {
+        figment = figment.merge(("tls.certs", certs)).merge(("tls.key", key));
+    }
+
+    let _ = BASE_PATH.set(server.base_path);
+    let root = match base_path() {
+        "" => "/".to_string(),
+        base => base.to_string(),
+    };
+
     // Launch Rocket and attach the shutdown monitor
-    let rocket = rocket::build()
+    let rocket = rocket::custom(figment)
         .manage(dbp)
-        .mount("/static", routes![statics::style_css])
+        .mount(url("/static"), routes![statics::style_css])
         .mount(
-            "/api",
+            url("/api"),
             routes![
-                api::post_task,
-                api::get_tasks,
-                api::post_channel,
-                api::get_channels,
-                api::get_videos,
-                api::post_channel_fetch,
-                api::post_video,
                 api::post_shutdown,
+                api::get_health,
+                api::get_health_live,
+                api::get_events,
+                api::v1::get_openapi,
             ],
         )
+        .mount(url("/metrics"), routes![api::get_metrics])
+        .mount(url("/api/v1"), api::v1::routes())
+        .mount("/", api::v1::docs_routes())
         .mount(
-            "/",
+            root.as_str(),
             routes![
                 pages::get_home,
                 pages::get_channel_videos,
-                pages::get_configuration
+                pages::get_configuration,
+                pages::get_scheduler_page,
+                pages::get_notifications_page,
+                pages::get_mediaservers_page,
+                pages::get_apikeys_page,
+                pages::get_requests_page,
+                pages::get_users_page,
+                pages::get_search_page,
+                pages::get_queue_page,
+                pages::get_video_page,
+                pages::get_login,
+                pages::post_login,
+                pages::get_login_oidc,
+                pages::get_login_oidc_callback,
+                pages::post_logout,
             ],
         )
+        .register(root.as_str(), catchers![auth::unauthorized_page])
+        .register(url("/api"), catchers![auth::unauthorized_api])
+        .register(url("/metrics"), catchers![auth::unauthorized_api])
+        .register(url("/api/v1"), api::v1::catchers())
         .ignite()
         .await?;
 
@@ -0,0 +1,140 @@
+//! API keys page
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::User;
+
+#[get("/apikeys")]
+pub async fn get_apikeys_page(_user: User) -> (ContentType, String) {
+    let page = render_page("", HTML_APIKEYS);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_APIKEYS: &str = r#"
+<div class="section">
+    <h1>Create API Key:</h1>
+    <form onsubmit="addKey(event)" style="flex-wrap: wrap;">
+        <label for="key-name">Name:</label>
+        <input type="text" id="key-name" required>
+        <label for="key-scope">Access:</label>
+        <select id="key-scope" onchange="toggleEndpoints()">
+            <option value="FULL">Full access</option>
+            <option value="READ">Read only</option>
+            <option value="DOWNLOAD">Request downloads only</option>
+            <option value="ENDPOINTS">Specific endpoints</option>
+        </select>
+        <label for="key-endpoints" class="key-endpoints" hidden>Endpoints:</label>
+        <input type="text" id="key-endpoints" class="key-endpoints" placeholder="POST /api/v1/videos, GET /api/v1/tasks" hidden>
+        <button type="submit">Create</button>
+    </form>
+    <p>Send the key in X-Api-Key header, or as api_key query parameter. Keys work only on /api routes and /metrics.
+    See the <a href="{base}/api/docs/">API documentation</a>.</p>
+    <p id="new-key" hidden>New key, copy it now as it won't be shown again: <code id="new-key-value"></code></p>
+</div>
+
+<div class="section">
+    <h2>API Keys</h2>
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>Name</th>
+                <th>Key</th>
+                <th>Access</th>
+                <th>Created</th>
+                <th>Last used</th>
+                <th></th>
+            </tr>
+        </thead>
+        <tbody id="keys-list">
+            <!-- API keys will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+function toggleEndpoints() {
+    const hidden = document.getElementById('key-scope').value !== 'ENDPOINTS';
+    document.querySelectorAll('.key-endpoints').forEach(el => el.hidden = hidden);
+}
+
+function describeScope(key) {
+    switch (key.scope) {
+        case "FULL": return "Full access";
+        case "READ": return "Read only";
+        default: return key.endpoints.split("\n").join(", ");
+    }
+}
+
+async function fetchKeys() {
+    try {
+        const response = await fetch('{base}/api/v1/apikeys');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        const keys = await response.json();
+        const list = document.getElementById('keys-list');
+        list.innerHTML = ''; // Clear the existing list
+
+        keys.forEach(key => {
+            const tr = document.createElement('tr');
+            tr.innerHTML = `
+                <td></td>
+                <td></td>
+                <td></td>
+                <td>${key.created_at}</td>
+                <td>${key.last_used || "never"}</td>
+                <td><button onclick="deleteKey(${key.id})">Revoke</button></td>
+            `;
+            tr.children[0].textContent = key.name;
+            tr.children[1].textContent = `${key.key_prefix}...`;
+            tr.children[2].textContent = describeScope(key);
+            list.appendChild(tr);
+        });
+    } catch (error) {
+        console.error('Error fetching API keys:', error);
+    }
+}
+
+async function addKey(event) {
+    event.preventDefault();
+    let scope = document.getElementById('key-scope').value;
+    let endpoints = document.getElementById('key-endpoints').value;
+    if (scope === "DOWNLOAD") {
+        scope = "ENDPOINTS";
+        endpoints = "POST /api/v1/videos";
+    }
+
+    const response = await fetch('{base}/api/v1/apikeys', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({
+            name: document.getElementById('key-name').value,
+            scope: scope,
+            endpoints: endpoints,
+        })
+    });
+
+    if (response.ok) {
+        const created = await response.json();
+        document.getElementById('new-key-value').textContent = created.key;
+        document.getElementById('new-key').hidden = false;
+    } else {
+        alert(`Failed to create API key: ${await apiError(response)}`);
+    }
+    await fetchKeys();
+}
+
+async function deleteKey(id) {
+    if (!confirm("Revoke this API key?")) return;
+    const response = await fetch(`{base}/api/v1/apikeys/${id}`, { method: 'DELETE' });
+    if (!response.ok) {
+        alert(`Failed to revoke API key: ${await apiError(response)}`);
+    }
+    await fetchKeys();
+}
+
+fetchKeys(); // Initial fetch
+</script>
+"#;
@@ -1,18 +1,21 @@
 //! channels page
 
+use crate::database;
 use crate::DBPool;
-use rocket::http::ContentType;
+use rocket::http::{ContentType, Status};
 use rocket::{get, State};
 use tracing::{debug, error, event, info, info_span, span, trace, warn, Level};
 
 use super::render_page;
+use crate::webui::auth::User;
 
 #[get("/channels/<domain>/<channel>")]
 pub async fn get_channel_videos(
+    _user: User,
     domain: String,
     channel: String,
     db_pool: &State<DBPool>,
-) -> (ContentType, String) {
+) -> Result<(ContentType, String), Status> {
     let span = span!(Level::DEBUG, "get_channel_videos");
     let _enter = span.enter();
 
@@ -21,21 +24,21 @@ pub async fn get_channel_videos(
 
     // Get additional data from DB
     let conn = db_pool.get().expect("Failed to get DB connection");
-    let mut stmt = conn
-        .prepare("SELECT channel_id FROM channels WHERE domain = ? AND channel_name_normalized = ?")
-        .expect("Failed to prepare query");
-
-    let channel_id: String = stmt
-        .query_row(rusqlite::params![domain, channel], |row| row.get(0))
-        .expect("Failed to retrieve channel_id");
+    let id = match database::get_channel_by_name(&conn, &domain, &channel) {
+        Ok(Some(found)) => found.id,
+        Ok(None) => return Err(Status::NotFound),
+        Err(e) => {
+            error!("Failed to get channel: {:#}", e);
+            return Err(Status::InternalServerError);
+        }
+    };
 
     // Render page with dynamic placeholders for JavaScript
     let page_content = HTML_CHANNEL_VIDEOS
-        .replace("{{DOMAIN}}", &domain)
         .replace("{{CHANNEL}}", &channel)
-        .replace("{{CHANNEL_ID}}", &channel_id);
+        .replace("{{ID}}", &id.to_string());
 
-    (ContentType::HTML, render_page("", &page_content))
+    Ok((ContentType::HTML, render_page("", &page_content)))
 }
 
 // HTML template for channel videos page
@@ -44,25 +47,39 @@ const HTML_CHANNEL_VIDEOS: &str = r#"
     <h1>Channel Videos - {{CHANNEL}}</h1>
     
     <!-- Fetch All Videos Button -->
-    <button id="fetch-videos-button" onclick="fetchAllVideos()">Fetch All Videos</button>
+    <button id="fetch-videos-button" class="admin-only" onclick="fetchAllVideos()">Fetch All Videos</button>
     
     <div id="seasons-container">
         <!-- Videos grouped by season will be populated here by JavaScript -->
     </div>
+    <div class="pager">
+        <span id="videos-count"></span>
+        <button id="load-more-button" onclick="fetchVideos(true)" hidden>Load more</button>
+    </div>
 </div>
 
 <script>
-// Fetch videos grouped by season
-async function fetchVideos() {
-    const domain = "{{DOMAIN}}";
-    const channel = "{{CHANNEL}}";
-    const response = await fetch(`/api/videos/${domain}/${channel}`);
+const PAGE_SIZE = 200;
+let videos = [];
+
+// Fetch videos, newest first, and show them grouped by season. With more, the next page is
+// added to the loaded ones. Otherwise the loaded ones are fetched again.
+async function fetchVideos(more = false) {
+    const offset = more ? videos.length : 0;
+    const limit = more ? PAGE_SIZE : Math.min(Math.max(videos.length, PAGE_SIZE), 1000);
+    const response = await fetch(`{base}/api/v1/channels/{{ID}}/videos?sort=release_date&order=desc&offset=${offset}&limit=${limit}`);
     if (!response.ok) {
         console.error("Failed to fetch videos");
         return;
     }
 
-    const videos = await response.json();
+    const page = await response.json();
+    // Estimated date is shown when the exact one is not known
+    page.items.forEach(video => video.release_date = video.release_date || video.release_date_estimate || "");
+    videos = more ? videos.concat(page.items) : page.items;
+    document.getElementById("videos-count").textContent = `Showing ${videos.length} of ${page.total} videos`;
+    document.getElementById("load-more-button").hidden = videos.length >= page.total;
+
     const seasonsContainer = document.getElementById("seasons-container");
     seasonsContainer.innerHTML = ""; // Clear any existing content
 
@@ -102,7 +119,7 @@ async function fetchVideos() {
             const videoDiv = document.createElement("div");
             videoDiv.classList.add("video-item");
             videoDiv.innerHTML = `
-                <a href="${video.url}" target="_blank">
+                <a href="{base}/videos/${video.domain}/${video.video_id}">
                     <strong>${video.name}</strong>
                 </a>
                 <p>Release Date: ${video.release_date}</p>
@@ -119,19 +136,21 @@ async function fetchVideos() {
 
 // Function to request all videos in a season
 async function requestSeason(season) {
-    const domain = "{{DOMAIN}}";
-    const channel = "{{CHANNEL}}";
-    
-    // Fetch videos for the current channel to get videos by season
-    const response = await fetch(`/api/videos/${domain}/${channel}`);
-    if (!response.ok) {
-        console.error("Failed to fetch videos");
-        return;
+    // Fetch all videos of the season that are not requested yet, also the ones not loaded
+    const videosInSeason = [];
+    let total = 1;
+    while (videosInSeason.length < total) {
+        const response = await fetch(`{base}/api/v1/channels/{{ID}}/videos?season=${encodeURIComponent(season)}&is_requested=false&offset=${videosInSeason.length}&limit=1000`);
+        if (!response.ok) {
+            console.error("Failed to fetch videos");
+            return;
+        }
+        const page = await response.json();
+        if (page.items.length === 0) break;
+        videosInSeason.push(...page.items);
+        total = page.total;
     }
 
-    const videos = await response.json();
-    const videosInSeason = videos.filter(video => (video.season || "unknown") === season && !video.is_requested);
-
     if (videosInSeason.length === 0) {
         alert("All videos in this season have already been requested.");
         return;
@@ -148,7 +167,7 @@ async function requestSeason(season) {
 // Function to send a request to download a video
 async function requestVideo(url) {
     try {
-        const response = await fetch("/api/video/", {
+        const response = await fetch("{base}/api/v1/videos", {
             method: "POST",
             headers: {
                 "Content-Type": "application/json",  // Set content type to JSON
@@ -163,7 +182,7 @@ async function requestVideo(url) {
             await fetchVideos(); // Refresh videos to reflect "Requested" status
         } else {
             console.error("Failed to request video download");
-            alert("Failed to request download. Please try again.");
+            alert(`Failed to request download: ${await apiError(response)}`);
         }
     } catch (error) {
         console.error("Error in requestVideo:", error);
@@ -172,20 +191,8 @@ async function requestVideo(url) {
 
 // Function to fetch all videos
 async function fetchAllVideos() {
-    const domain = "{{DOMAIN}}";
-    const channelId = "{{CHANNEL_ID}}";
-
     try {
-        const response = await fetch("/api/channel/fetch", {
-            method: "POST",
-            headers: {
-                "Content-Type": "application/x-www-form-urlencoded",
-            },
-            body: new URLSearchParams({
-                domain: domain,
-                channel_id: channelId
-            })
-        });
+        const response = await fetch("{base}/api/v1/channels/{{ID}}/fetch", { method: "POST" });
 
         if (response.ok) {
             console.log("Fetch request sent successfully");
@@ -198,6 +205,13 @@ async function fetchAllVideos() {
     }
 }
 
+// Refresh when new videos are found or a fetch finishes
+subscribeEvents(event => {
+    if (event.type === "video_discovered" || (event.type === "task_state" && event.task_state === "DONE")) {
+        fetchVideos();
+    }
+});
+
 // Initial fetch of videos
 fetchVideos();
 </script>
@@ -5,9 +5,13 @@ use rocket::get;
 use rocket::http::ContentType;
 
 use super::render_page;
+use crate::webui::auth::Admin;
 
 #[get("/configuration")]
-pub async fn get_configuration(db_pool: &rocket::State<DBPool>) -> (ContentType, String) {
+pub async fn get_configuration(
+    _admin: Admin,
+    db_pool: &rocket::State<DBPool>,
+) -> (ContentType, String) {
     // let conn = db_pool.get().expect("Failed to get DB connection");
 
     let version = env!("CARGO_PKG_VERSION");
@@ -24,13 +28,109 @@ const HTML_CONFIGURATION: &'static str = r#"
 
 </div>
 
+<div class="section">
+    <h1>Settings</h1>
+    <form class="settings" id="settings" onsubmit="saveSettings(event)">
+        <!-- Fields will be populated here by JavaScript -->
+    </form>
+    <p id="settings-status"></p>
+</div>
+
 <div class="section">
     <h1>Admin Actions:</h1>
 
     <p>This will shutdown the system. Restarting should be configured on the host system.</p>
-    <form action="/api/shutdown" method="post">
+    <form action="{base}/api/shutdown" method="post">
         <button type="submit">Shutdown the system</button>
     </form>
 
 </div>
+
+<script>
+// Known settings in display order, the rest are shown after these by key
+const SETTINGS = [
+    { key: "path_temp", label: "Temporary download folder" },
+    { key: "path_media", label: "Media folder" },
+    { key: "sub_lang", label: "Subtitle languages (comma separated patterns, - excludes)" },
+    { key: "retry_limit", label: "Download retry limit" },
+    { key: "backup_path", label: "Database backup folder (empty disables backups)" },
+    { key: "backup_keep", label: "Database backups to keep" },
+    { key: "auth_default_role", label: "Role for new external users (ADMIN or REQUESTER)" },
+    { key: "auth_proxy_header", label: "Trusted proxy username header" },
+    { key: "auth_proxy_trusted", label: "Trusted proxy addresses (IPs or CIDR ranges)" },
+    { key: "oidc_issuer", label: "OpenID Connect issuer URL" },
+    { key: "oidc_client_id", label: "OpenID Connect client ID" },
+    { key: "oidc_client_secret", label: "OpenID Connect client secret", secret: true },
+    { key: "oidc_redirect_url", label: "OpenID Connect redirect URL" },
+];
+
+async function fetchSettings() {
+    try {
+        const response = await fetch('{base}/api/v1/config');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        const values = await response.json();
+        const known = SETTINGS.map(s => s.key);
+        const settings = SETTINGS.filter(s => s.key in values).concat(
+            Object.keys(values)
+                .filter(key => !known.includes(key))
+                .map(key => ({ key: key, label: key }))
+        );
+
+        const form = document.getElementById('settings');
+        form.innerHTML = '';
+        settings.forEach(setting => {
+            const div = document.createElement('div');
+            div.innerHTML = `
+                <label for="conf-${setting.key}">${setting.label}:</label>
+                <input type="${setting.secret ? "password" : "text"}" id="conf-${setting.key}"
+                    name="${setting.key}" ${setting.secret ? 'placeholder="unchanged"' : ""}>
+                <div class="field-error" id="error-${setting.key}"></div>
+            `;
+            div.querySelector('input').value = values[setting.key];
+            form.appendChild(div);
+        });
+        const button = document.createElement('button');
+        button.type = 'submit';
+        button.textContent = 'Save';
+        form.appendChild(button);
+    } catch (error) {
+        console.error('Error fetching settings:', error);
+    }
+}
+
+async function saveSettings(event) {
+    event.preventDefault();
+    const body = {};
+    document.querySelectorAll('#settings input').forEach(input => {
+        // Empty secret means keep the current one
+        if (input.type === 'password' && input.value === '') return;
+        body[input.name] = input.value;
+    });
+    document.querySelectorAll('.field-error').forEach(div => div.textContent = '');
+
+    const status = document.getElementById('settings-status');
+    const response = await fetch('{base}/api/v1/config', {
+        method: 'PUT',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (response.ok) {
+        status.textContent = "Settings saved.";
+        await fetchSettings();
+    } else if (response.status === 400) {
+        const result = await response.json();
+        for (const [key, message] of Object.entries(result.error.fields || {})) {
+            const div = document.getElementById(`error-${key}`);
+            if (div) div.textContent = message;
+        }
+        status.textContent = "Settings were not saved, check the errors above.";
+    } else {
+        status.textContent = `Failed to save settings: ${await apiError(response)}`;
+    }
+}
+
+fetchSettings(); // Initial fetch
+</script>
 "#;
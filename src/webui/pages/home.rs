@@ -5,9 +5,10 @@ use rocket::get;
 use rocket::http::ContentType;
 
 use super::render_page;
+use crate::webui::auth::User;
 
 #[get("/")]
-pub async fn get_home(db_pool: &rocket::State<DBPool>) -> (ContentType, String) {
+pub async fn get_home(_user: User, db_pool: &rocket::State<DBPool>) -> (ContentType, String) {
     // let conn = db_pool.get().expect("Failed to get DB connection");
 
     let page = render_page("", HTML_HOME);
@@ -16,21 +17,118 @@ pub async fn get_home(db_pool: &rocket::State<DBPool>) -> (ContentType, String)
 }
 
 const HTML_HOME: &'static str = r#"
-<div class="section">
+<div class="section admin-only">
     <h1>Add Channel:</h1>
-    <form action="/api/channel" method="post">
+    <form id="channel-form" onsubmit="addChannel(event)">
         <label for="url">URL to add:</label>
         <input type="text" id="url" name="url" required>
         <button type="submit">Add</button>
     </form>
-    <p>This will add a background task to fetch the channel's information.</p>
+    <p id="channel-status">This will add a background task to fetch the channel's information.</p>
+</div>
+
+<div class="section admin-only">
+    <h2>Import Subscriptions:</h2>
+    <form id="import-form" onsubmit="previewImport(event)">
+        <label for="import-file">OPML or Google Takeout subscriptions.csv:</label>
+        <input type="file" id="import-file" accept=".opml,.xml,.csv" required>
+        <button type="submit">Preview</button>
+    </form>
+    <p id="import-status"></p>
+    <div id="import-preview" style="display: none;">
+        <table class="data-table">
+            <thead>
+                <tr><th>Import</th><th>Channel</th><th>ID</th></tr>
+            </thead>
+            <tbody id="import-rows"></tbody>
+        </table>
+        <button onclick="queueImport()">Add selected channels</button>
+    </div>
 </div>
+<script>
+async function addChannel(event) {
+    event.preventDefault();
+    const input = document.getElementById('url');
+    const status = document.getElementById('channel-status');
+    const response = await fetch('{base}/api/v1/channels', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ url: input.value }),
+    });
+    if (!response.ok) {
+        status.textContent = `Failed to add channel: ${await apiError(response)}`;
+        return;
+    }
+
+    const task = await response.json();
+    status.textContent = `Channel will be added by task ${task.id}`;
+    input.value = '';
+}
+
+async function previewImport(event) {
+    event.preventDefault();
+    const file = document.getElementById('import-file').files[0];
+    const status = document.getElementById('import-status');
+    const response = await fetch('{base}/api/v1/channels/import/preview', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ contents: await file.text() }),
+    });
+    if (!response.ok) {
+        status.textContent = `Import failed: ${await apiError(response)}`;
+        return;
+    }
+
+    const preview = await response.json();
+    const rows = document.getElementById('import-rows');
+    rows.innerHTML = '';
+    preview.subscriptions.forEach(sub => {
+        const tr = document.createElement('tr');
+        tr.innerHTML = `<td><input type="checkbox"></td><td><a target="_blank"></a></td><td></td>`;
+        const checkbox = tr.querySelector('input');
+        checkbox.value = sub.channel_id;
+        checkbox.checked = !sub.followed;
+        checkbox.disabled = sub.followed;
+        const link = tr.querySelector('a');
+        link.href = sub.url;
+        link.textContent = sub.title || sub.channel_id;
+        tr.cells[2].textContent = sub.followed ? `${sub.channel_id} (already followed)` : sub.channel_id;
+        rows.appendChild(tr);
+    });
+
+    const followed = preview.subscriptions.filter(sub => sub.followed).length;
+    status.textContent = `${preview.subscriptions.length} channels, ${followed} already followed`
+        + (preview.skipped ? `, ${preview.skipped} entries skipped (not YouTube channels)` : '');
+    document.getElementById('import-preview').style.display = preview.subscriptions.length ? '' : 'none';
+}
+
+async function queueImport() {
+    const channelIds = [...document.querySelectorAll('#import-rows input:checked:not(:disabled)')]
+        .map(checkbox => checkbox.value);
+    const status = document.getElementById('import-status');
+    const response = await fetch('{base}/api/v1/channels/import', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ channel_ids: channelIds }),
+    });
+    if (!response.ok) {
+        status.textContent = `Import failed: ${await apiError(response)}`;
+        return;
+    }
+
+    const result = await response.json();
+    status.textContent = `Queued ${result.queued} channels to be added`;
+    document.getElementById('import-preview').style.display = 'none';
+}
+</script>
 
 <div class="section">
     <h2>Followed Channels</h2>
+    <p><a href="{base}/api/v1/channels/export.opml" download="tubarr-subscriptions.opml">Export as OPML</a></p>
     <ul id="channels-list">
         <!-- Channel items will be populated here by JavaScript -->
     </ul>
+    <div class="pager" id="channels-pager"></div>
 </div>
 
 <script>
@@ -43,20 +141,25 @@ function normalizeChannelName(channelName) {
     return normalized;
 }
 
-// Fetch channels and update the list every 5 seconds
-async function fetchChannels() {
+let channelsOffset = 0;
+
+// Fetch one page of channels, sorted by name
+async function fetchChannels(offset = channelsOffset) {
     try {
-        const response = await fetch('/api/channels');
+        const response = await fetch(`{base}/api/v1/channels?sort=channel_name&offset=${offset}&limit=50`);
         if (!response.ok) throw new Error('Network response was not ok');
         
-        const channels = await response.json();
+        const page = await response.json();
+        const channels = page.items;
+        channelsOffset = page.offset;
+        renderPager('channels-pager', page, fetchChannels);
         const channelsList = document.getElementById('channels-list');
         channelsList.innerHTML = ''; // Clear the existing list
 
         channels.forEach(channel => {
             const li = document.createElement('li');
             const encodedName = normalizeChannelName(channel.channel_name);
-            const link = `/channels/${channel.domain}/${encodedName}`;
+            const link = `{base}/channels/${channel.domain}/${encodedName}`;
 
             li.innerHTML = `<a href="${link}">
                                 <strong>${channel.channel_name}</strong> 
@@ -69,8 +172,6 @@ async function fetchChannels() {
     }
 }
 
-// Poll the API every 5 seconds
-// setInterval(fetchChannels, 5000);
 fetchChannels(); // Initial fetch to load channels right away
 </script>
 
@@ -79,6 +180,7 @@ fetchChannels(); // Initial fetch to load channels right away
     <ul id="task-list">
         <!-- Task items will be populated here by JavaScript -->
     </ul>
+    <div class="pager" id="tasks-pager"></div>
 </div>
 <script>
 function formatElapsedTime(createdAt, updatedAt, taskState) {
@@ -105,18 +207,25 @@ function formatElapsedTime(createdAt, updatedAt, taskState) {
     }
 }
 
-async function fetchTasks() {
+// Latest download progress by task ID
+const progress = {};
+
+let tasksOffset = 0;
+
+// Fetch one page of downloads, latest updated first
+async function fetchTasks(offset = tasksOffset) {
     try {
-        const response = await fetch('/api/tasks');
+        const response = await fetch(`{base}/api/v1/tasks?task_type=VIDEO-DOWNLOAD&sort=updated_at&order=desc&offset=${offset}&limit=20`);
         if (!response.ok) throw new Error('Network response was not ok');
         
-        const tasks = await response.json();
+        const page = await response.json();
+        tasksOffset = page.offset;
+        renderPager('tasks-pager', page, fetchTasks);
         const taskList = document.getElementById('task-list');
         taskList.innerHTML = ''; // Clear the existing list
 
-        tasks
-            .filter(task => task.task_type === "VIDEO-DOWNLOAD" && task.task_data.includes('"url":'))
-            .sort((a, b) => new Date(b.updated_at) - new Date(a.updated_at)) // Sort by updated_at, latest first
+        page.items
+            .filter(task => task.task_data.includes('"url":'))
             .forEach(task => {
                 const taskData = JSON.parse(task.task_data);
                 
@@ -136,6 +245,7 @@ async function fetchTasks() {
                         <span class="task-url">URL: <a href="${url}" target="_blank">${url}</a></span>
                         <span class="task-retries">Retry Count: ${task.retry_count}</span>
                         <span class="task-elapsed">Elapsed: ${elapsed}</span>
+                        <span class="task-progress" id="progress-${task.id}">${progress[task.id] !== undefined ? `Progress: ${progress[task.id]}%` : ""}</span>
                         <span class="task-updated">Last Updated: ${new Date(task.updated_at).toLocaleString()}</span>
                     </div>
                 `;
@@ -147,8 +257,24 @@ async function fetchTasks() {
     }
 }
 
-// Poll the API every 5 seconds
-setInterval(fetchTasks, 5000);
+// Update live from server events
+subscribeEvents(event => {
+    switch (event.type) {
+        case "task_created":
+        case "task_state":
+            fetchTasks();
+            break;
+        case "progress": {
+            progress[event.task_id] = event.percent.toFixed(1);
+            const span = document.getElementById(`progress-${event.task_id}`);
+            if (span) span.textContent = `Progress: ${progress[event.task_id]}%`;
+            break;
+        }
+        case "channel_added":
+            fetchChannels();
+            break;
+    }
+});
 fetchTasks(); // Initial fetch
 </script>
 
@@ -0,0 +1,262 @@
+//! Login and logout
+
+use rocket::form::Form;
+use rocket::http::{ContentType, Cookie, CookieJar, SameSite};
+use rocket::request::{FromRequest, Outcome, Request};
+use rocket::response::Redirect;
+use rocket::time::Duration;
+use rocket::{get, post, FromForm, State};
+use tracing::{error, info, warn};
+
+use crate::auth;
+use crate::webui::auth::SESSION_COOKIE;
+use crate::webui::{base_path, url};
+use crate::DBPool;
+
+#[get("/login?<failed>&<sso_failed>")]
+pub async fn get_login(
+    failed: Option<bool>,
+    sso_failed: Option<bool>,
+    db_pool: &State<DBPool>,
+) -> (ContentType, String) {
+    let conn = db_pool.get().expect("Failed to get DB connection");
+
+    let mut message = "";
+    if !auth::admin_password_is_set(&conn).unwrap_or(false) {
+        message =
+            "<p>Admin password has not been set. Set it with <code>tubarr user passwd</code>, \
+            or restart Tubarr with <code>--non-interactive</code> to generate one into \
+            admin-password.txt in the data folder.</p>";
+    } else if failed.unwrap_or(false) {
+        message = "<p>Wrong username or password.</p>";
+    } else if sso_failed.unwrap_or(false) {
+        message = "<p>Single sign-on failed. See the server log for details.</p>";
+    }
+
+    let mut sso = "";
+    if auth::oidc_config(&conn).ok().flatten().is_some() {
+        sso = HTML_LOGIN_SSO;
+    }
+
+    let page = HTML_LOGIN
+        .replace("{message}", message)
+        .replace("{sso}", sso)
+        .replace("{base}", base_path());
+    (ContentType::HTML, page)
+}
+
+#[derive(FromForm)]
+pub struct FromPostLogin {
+    username: String,
+    password: String,
+}
+
+#[post("/login", data = "<data>")]
+pub async fn post_login(
+    data: Form<FromPostLogin>,
+    cookies: &CookieJar<'_>,
+    https: Https,
+    db_pool: &State<DBPool>,
+) -> Redirect {
+    let dbp = db_pool.inner().clone();
+    let username = data.username.trim().to_string();
+    let password = data.password.clone();
+
+    // Password hashing is slow on purpose, keep it off the async workers
+    let result = rocket::tokio::task::spawn_blocking(move || {
+        let conn = dbp.get()?;
+        match auth::authenticate(&conn, &username, &password)? {
+            Some(user) => auth::create_session(&conn, &user).map(Some),
+            None => {
+                warn!("Failed login for user: {}", username);
+                Ok(None)
+            }
+        }
+    })
+    .await;
+
+    match result {
+        Ok(Ok(Some(token))) => {
+            info!("User logged in: {}", data.username.trim());
+            add_session_cookie(cookies, token, https);
+            Redirect::to(url("/"))
+        }
+        Ok(Ok(None)) => Redirect::to(url("/login?failed=true")),
+        Ok(Err(e)) => {
+            error!("Login failed: {:?}", e);
+            Redirect::to(url("/login?failed=true"))
+        }
+        Err(_) => Redirect::to(url("/login?failed=true")),
+    }
+}
+
+/// Cookie holding the OIDC state, ties the callback to the browser that started the login.
+const OIDC_STATE_COOKIE: &str = "tubarr_oidc_state";
+
+/// Start single sign-on, send the browser to the OIDC provider.
+#[get("/login/oidc")]
+pub async fn get_login_oidc(
+    cookies: &CookieJar<'_>,
+    https: Https,
+    db_pool: &State<DBPool>,
+) -> Redirect {
+    let dbp = db_pool.inner().clone();
+
+    let result = rocket::tokio::task::spawn_blocking(move || {
+        let conn = dbp.get()?;
+        match auth::oidc_config(&conn)? {
+            Some(config) => auth::authorization_url(&config),
+            None => Err(anyhow::anyhow!("OIDC login is not configured")),
+        }
+    })
+    .await;
+
+    match result {
+        Ok(Ok((url, state))) => {
+            // Lax, the provider redirects back with a top-level GET
+            let cookie = Cookie::build((OIDC_STATE_COOKIE, state))
+                .path(cookie_path())
+                .http_only(true)
+                .secure(https.0)
+                .same_site(SameSite::Lax)
+                .max_age(Duration::seconds(auth::OIDC_LOGIN_TIMEOUT.as_secs() as i64));
+            cookies.add(cookie);
+            Redirect::to(url)
+        }
+        Ok(Err(e)) => {
+            error!("Failed to start OIDC login: {:?}", e);
+            Redirect::to(url("/login?sso_failed=true"))
+        }
+        Err(_) => Redirect::to(url("/login?sso_failed=true")),
+    }
+}
+
+/// OIDC provider sends the browser back here.
+#[get("/login/oidc/callback?<code>&<state>&<error>")]
+pub async fn get_login_oidc_callback(
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+    cookies: &CookieJar<'_>,
+    https: Https,
+    db_pool: &State<DBPool>,
+) -> Redirect {
+    let (Some(code), Some(state)) = (code, state) else {
+        warn!("OIDC login was not completed: {:?}", error);
+        return Redirect::to(url("/login?sso_failed=true"));
+    };
+    let started_here = cookies
+        .get(OIDC_STATE_COOKIE)
+        .is_some_and(|cookie| cookie.value() == state);
+    cookies.remove(Cookie::build(OIDC_STATE_COOKIE).path(cookie_path()));
+    if !started_here {
+        warn!("OIDC login was not started in this browser");
+        return Redirect::to(url("/login?sso_failed=true"));
+    }
+    let dbp = db_pool.inner().clone();
+
+    let result = rocket::tokio::task::spawn_blocking(move || {
+        let conn = dbp.get()?;
+        let config = auth::oidc_config(&conn)?
+            .ok_or_else(|| anyhow::anyhow!("OIDC login is not configured"))?;
+        let user = auth::finish_login(&conn, &config, &code, &state)?;
+        let token = auth::create_session(&conn, &user)?;
+        Ok::<_, anyhow::Error>((user, token))
+    })
+    .await;
+
+    match result {
+        Ok(Ok((user, token))) => {
+            info!("User logged in with OIDC: {}", user.username);
+            add_session_cookie(cookies, token, https);
+            Redirect::to(url("/"))
+        }
+        Ok(Err(e)) => {
+            error!("OIDC login failed: {:?}", e);
+            Redirect::to(url("/login?sso_failed=true"))
+        }
+        Err(_) => Redirect::to(url("/login?sso_failed=true")),
+    }
+}
+
+#[post("/logout")]
+pub async fn post_logout(cookies: &CookieJar<'_>, db_pool: &State<DBPool>) -> Redirect {
+    if let Some(cookie) = cookies.get(SESSION_COOKIE) {
+        let conn = db_pool.get().expect("Failed to get DB connection");
+        if let Err(e) = auth::delete_session(&conn, cookie.value()) {
+            error!("Failed to remove session: {:?}", e);
+        }
+    }
+    cookies.remove(Cookie::build(SESSION_COOKIE).path(cookie_path()));
+
+    Redirect::to(url("/login"))
+}
+
+/// Did the browser connect over HTTPS, directly or through a reverse proxy that says so.
+/// The proxy header is taken from anyone: it can only make the cookie stricter.
+pub struct Https(bool);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for Https {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let forwarded = req
+            .headers()
+            .get_one("X-Forwarded-Proto")
+            .is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+        Outcome::Success(Https(req.rocket().config().tls_enabled() || forwarded))
+    }
+}
+
+fn add_session_cookie(cookies: &CookieJar<'_>, token: String, https: Https) {
+    let cookie = Cookie::build((SESSION_COOKIE, token))
+        .path(cookie_path())
+        .http_only(true)
+        .secure(https.0)
+        .same_site(SameSite::Lax)
+        .max_age(Duration::days(auth::SESSION_DAYS));
+    cookies.add(cookie);
+}
+
+/// Whole web UI, with or without the trailing slash.
+fn cookie_path() -> &'static str {
+    match base_path() {
+        "" => "/",
+        base => base,
+    }
+}
+
+const HTML_LOGIN: &str = r#"
+<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta charset="UTF-8">
+    <title>Tubarr - Login</title>
+    <link rel="stylesheet" href="{base}/static/style.css" type="text/css">
+</head>
+<body>
+    <div class="login">
+        <div class="logo">Tubarr</div>
+        <div class="section">
+            <h1>Login</h1>
+            {message}
+            <form method="post" action="{base}/login">
+                <label for="username">Username:</label>
+                <input type="text" id="username" name="username" autocomplete="username" required autofocus>
+                <label for="password">Password:</label>
+                <input type="password" id="password" name="password" autocomplete="current-password" required>
+                <button type="submit">Login</button>
+            </form>
+            {sso}
+        </div>
+    </div>
+</body>
+</html>
+"#;
+
+const HTML_LOGIN_SSO: &str = r#"
+            <form method="get" action="{base}/login/oidc">
+                <button type="submit">Login with single sign-on</button>
+            </form>
+"#;
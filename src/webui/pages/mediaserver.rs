@@ -0,0 +1,169 @@
+//! Media servers page
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::Admin;
+
+#[get("/mediaservers")]
+pub async fn get_mediaservers_page(_admin: Admin) -> (ContentType, String) {
+    let page = render_page("", HTML_MEDIASERVERS);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_MEDIASERVERS: &str = r#"
+<div class="section">
+    <h1>Add Media Server:</h1>
+    <form onsubmit="addServer(event)" style="flex-wrap: wrap;">
+        <label for="server-name">Name:</label>
+        <input type="text" id="server-name" required>
+        <label for="server-kind">Type:</label>
+        <select id="server-kind">
+            <option value="JELLYFIN">Jellyfin</option>
+            <option value="EMBY">Emby</option>
+            <option value="PLEX">Plex</option>
+        </select>
+        <label for="server-url">URL:</label>
+        <input type="text" id="server-url" placeholder="http://host:8096" required>
+        <label for="server-token">API key / token:</label>
+        <input type="text" id="server-token" required>
+        <label for="server-library">Plex library ID:</label>
+        <input type="text" id="server-library" placeholder="optional">
+        <label for="server-path">Media path on server:</label>
+        <input type="text" id="server-path" placeholder="optional">
+        <button type="submit">Add</button>
+    </form>
+    <p>Library refresh is requested once downloads have finished. Media path is needed only if the
+       server sees the media folder at a different path, eg. inside a container. Without a library ID
+       Plex refreshes every library.</p>
+</div>
+
+<div class="section">
+    <h2>Media Servers</h2>
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>Name</th>
+                <th>Type</th>
+                <th>URL</th>
+                <th>Library ID</th>
+                <th>Media path on server</th>
+                <th>Enabled</th>
+                <th></th>
+            </tr>
+        </thead>
+        <tbody id="servers-list">
+            <!-- Media servers will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+let servers = [];
+
+async function fetchServers() {
+    try {
+        const response = await fetch('{base}/api/v1/mediaservers');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        servers = await response.json();
+        const list = document.getElementById('servers-list');
+        list.innerHTML = ''; // Clear the existing list
+
+        servers.forEach(server => {
+            const tr = document.createElement('tr');
+            tr.innerHTML = `
+                <td></td>
+                <td></td>
+                <td><input type="text" id="url-${server.id}"></td>
+                <td><input type="text" id="library-${server.id}"></td>
+                <td><input type="text" id="path-${server.id}"></td>
+                <td><input type="checkbox" id="enabled-${server.id}" ${server.enabled ? "checked" : ""}></td>
+                <td>
+                    <button onclick="saveServer(${server.id})">Save</button>
+                    <button onclick="testServer(${server.id})">Test connection</button>
+                    <button onclick="deleteServer(${server.id})">Delete</button>
+                </td>
+            `;
+            tr.children[0].textContent = server.name;
+            tr.children[1].textContent = server.kind;
+            tr.querySelector(`#url-${server.id}`).value = server.url;
+            tr.querySelector(`#library-${server.id}`).value = server.library_id || "";
+            tr.querySelector(`#path-${server.id}`).value = server.path_remote || "";
+            list.appendChild(tr);
+        });
+    } catch (error) {
+        console.error('Error fetching media servers:', error);
+    }
+}
+
+async function addServer(event) {
+    event.preventDefault();
+    const body = {
+        name: document.getElementById('server-name').value,
+        kind: document.getElementById('server-kind').value,
+        url: document.getElementById('server-url').value,
+        token: document.getElementById('server-token').value,
+        library_id: document.getElementById('server-library').value,
+        path_remote: document.getElementById('server-path').value,
+        enabled: true,
+    };
+
+    const response = await fetch('{base}/api/v1/mediaservers', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (!response.ok) {
+        alert(`Failed to add media server: ${await apiError(response)}`);
+    }
+    await fetchServers();
+}
+
+async function saveServer(id) {
+    const server = servers.find(s => s.id === id);
+    const body = {
+        name: server.name,
+        kind: server.kind,
+        url: document.getElementById(`url-${id}`).value,
+        library_id: document.getElementById(`library-${id}`).value,
+        path_remote: document.getElementById(`path-${id}`).value,
+        enabled: document.getElementById(`enabled-${id}`).checked,
+    };
+
+    const response = await fetch(`{base}/api/v1/mediaservers/${id}`, {
+        method: 'PUT',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (!response.ok) {
+        alert(`Failed to save media server: ${await apiError(response)}`);
+    }
+    await fetchServers();
+}
+
+async function testServer(id) {
+    const response = await fetch(`{base}/api/v1/mediaservers/${id}/test`, { method: 'POST' });
+    if (response.ok) {
+        alert("Connection OK.");
+    } else {
+        alert(`Connection failed: ${await apiError(response)}`);
+    }
+}
+
+async function deleteServer(id) {
+    if (!confirm("Delete this media server?")) return;
+    const response = await fetch(`{base}/api/v1/mediaservers/${id}`, { method: 'DELETE' });
+    if (!response.ok) {
+        alert(`Failed to delete media server: ${await apiError(response)}`);
+    }
+    await fetchServers();
+}
+
+fetchServers(); // Initial fetch
+</script>
+"#;
@@ -7,6 +7,38 @@ pub use channels::*;
 mod conf;
 pub use conf::*;
 
+mod scheduler;
+pub use scheduler::*;
+
+mod notify;
+pub use notify::*;
+
+mod mediaserver;
+pub use mediaserver::*;
+
+mod login;
+pub use login::*;
+
+mod apikeys;
+pub use apikeys::*;
+
+mod requests;
+pub use requests::*;
+
+mod users;
+pub use users::*;
+
+mod search;
+pub use search::*;
+
+mod queue;
+pub use queue::*;
+
+mod video;
+pub use video::*;
+
+use super::base_path;
+
 fn render_page(head: &str, body: &str) -> String {
     let mut page = String::with_capacity(1024);
     page.push_str(TMPL_1);
@@ -14,7 +46,7 @@ fn render_page(head: &str, body: &str) -> String {
     page.push_str(TMPL_2);
     page.push_str(body);
     page.push_str(TMPL_3);
-    page
+    page.replace("{base}", base_path())
 }
 
 const TMPL_1: &'static str = r###"
@@ -23,7 +55,45 @@ const TMPL_1: &'static str = r###"
 <head>
     <meta charset="UTF-8">
     <title>Tubarr</title>
-    <link rel="stylesheet" href="/static/style.css" type="text/css">
+    <link rel="stylesheet" href="{base}/static/style.css" type="text/css">
+    <script>
+        // Live updates from the server. onEvent gets each event as an object with "type" field.
+        function subscribeEvents(onEvent) {
+            const source = new EventSource('{base}/api/events');
+            source.onmessage = (message) => onEvent(JSON.parse(message.data));
+            return source;
+        }
+
+        // Error message from a failed API response
+        async function apiError(response) {
+            try {
+                return (await response.json()).error.message;
+            } catch {
+                return response.statusText;
+            }
+        }
+
+        // Previous and next buttons for a paged list. page is the API response with total,
+        // offset and limit, load(offset) fetches another page.
+        function renderPager(elementId, page, load) {
+            const pager = document.getElementById(elementId);
+            pager.innerHTML = '';
+            if (page.total <= page.limit && page.offset === 0) return;
+
+            const last = Math.min(page.offset + page.items.length, page.total);
+            const prev = document.createElement('button');
+            prev.textContent = 'Previous';
+            prev.disabled = page.offset === 0;
+            prev.onclick = () => load(Math.max(page.offset - page.limit, 0));
+            const next = document.createElement('button');
+            next.textContent = 'Next';
+            next.disabled = last >= page.total;
+            next.onclick = () => load(page.offset + page.limit);
+            const info = document.createElement('span');
+            info.textContent = `${page.items.length ? page.offset + 1 : 0}-${last} of ${page.total}`;
+            pager.append(prev, info, next);
+        }
+    </script>
 "###;
 
 const TMPL_2: &'static str = r###"
@@ -37,14 +107,27 @@ const TMPL_2: &'static str = r###"
                 <input type="text" id="video-url" placeholder="Enter video URL" />
                 <button onclick="downloadVideo()">Download</button>
             </div>
-            <div class="user-button">User</div>
+            <form class="search-box" method="get" action="{base}/search">
+                <input type="search" name="q" placeholder="Search videos" />
+            </form>
+            <form class="user-menu" method="post" action="{base}/logout">
+                <span id="user-name">User</span>
+                <button class="user-button" type="submit">Logout</button>
+            </form>
         </header>
         <aside class="side-bar">
             <!-- Side Navigation Menu -->
             <nav>
                 <ul>
-                    <li><a href="/">Home</a></li>
-                    <li><a href="/configuration">Configuration</a></li>
+                    <li><a href="{base}/">Home</a></li>
+                    <li><a href="{base}/queue">Queue</a></li>
+                    <li><a href="{base}/requests">Requests</a></li>
+                    <li class="admin-only"><a href="{base}/scheduler">Scheduler</a></li>
+                    <li class="admin-only"><a href="{base}/notifications">Notifications</a></li>
+                    <li class="admin-only"><a href="{base}/mediaservers">Media Servers</a></li>
+                    <li class="admin-only"><a href="{base}/users">Users</a></li>
+                    <li><a href="{base}/apikeys">API Keys</a></li>
+                    <li class="admin-only"><a href="{base}/configuration">Configuration</a></li>
                 </ul>
             </nav>
         </aside>
@@ -55,6 +138,22 @@ const TMPL_3: &'static str = r###"
         </main>
     </div>
     <script>
+        // Admin-only parts of the pages are hidden from other users
+        let currentUser = null;
+        function applyRole() {
+            const hidden = !currentUser || currentUser.role !== 'ADMIN';
+            document.querySelectorAll('.admin-only').forEach(el => el.hidden = hidden);
+        }
+
+        fetch('{base}/api/v1/user')
+            .then(response => response.json())
+            .then(user => {
+                currentUser = user;
+                document.getElementById('user-name').textContent = user.username;
+                applyRole();
+            })
+            .catch(error => console.error('Error fetching user:', error));
+
         async function downloadVideo() {
             const url = document.getElementById('video-url').value;
             if (!url) {
@@ -63,7 +162,7 @@ const TMPL_3: &'static str = r###"
             }
 
             try {
-                const response = await fetch('/api/video', {
+                const response = await fetch('{base}/api/v1/videos', {
                     method: 'POST',
                     headers: {
                         'Content-Type': 'application/json'
@@ -71,10 +170,12 @@ const TMPL_3: &'static str = r###"
                     body: JSON.stringify({ url: url })
                 });
 
-                if (response.ok) {
+                if (response.status === 202) {
+                    alert("Video request is waiting for admin approval.");
+                } else if (response.ok) {
                     alert("Video download request submitted successfully.");
                 } else {
-                    alert("Failed to submit video download request.");
+                    alert("Failed to submit video download request: " + await apiError(response));
                 }
             } catch (error) {
                 alert("An error occurred: " + error.message);
@@ -0,0 +1,167 @@
+//! Notifications page
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::Admin;
+
+#[get("/notifications")]
+pub async fn get_notifications_page(_admin: Admin) -> (ContentType, String) {
+    let page = render_page("", HTML_NOTIFICATIONS);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_NOTIFICATIONS: &str = r#"
+<div class="section">
+    <h1>Add Notification Target:</h1>
+    <form onsubmit="addTarget(event)">
+        <label for="target-name">Name:</label>
+        <input type="text" id="target-name" required>
+        <label for="target-kind">Type:</label>
+        <select id="target-kind">
+            <option value="WEBHOOK">Webhook (JSON)</option>
+            <option value="DISCORD">Discord</option>
+            <option value="SLACK">Slack</option>
+            <option value="NTFY">ntfy</option>
+            <option value="GOTIFY">Gotify</option>
+        </select>
+        <label for="target-url">URL:</label>
+        <input type="text" id="target-url" required>
+        <label for="target-token">Token:</label>
+        <input type="text" id="target-token" placeholder="optional">
+        <button type="submit">Add</button>
+    </form>
+    <p>Discord and Slack take the webhook URL. ntfy takes the topic URL, eg. https://ntfy.sh/mytopic.
+       Gotify takes the server URL and the application token.</p>
+</div>
+
+<div class="section">
+    <h2>Notification Targets</h2>
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>Name</th>
+                <th>Type</th>
+                <th>URL</th>
+                <th>Enabled</th>
+                <th>Download done</th>
+                <th>Download failed</th>
+                <th>New video</th>
+                <th></th>
+            </tr>
+        </thead>
+        <tbody id="targets-list">
+            <!-- Notification targets will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+let targets = [];
+
+async function fetchTargets() {
+    try {
+        const response = await fetch('{base}/api/v1/notifications');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        targets = await response.json();
+        const list = document.getElementById('targets-list');
+        list.innerHTML = ''; // Clear the existing list
+
+        targets.forEach(target => {
+            const tr = document.createElement('tr');
+            tr.innerHTML = `
+                <td></td>
+                <td></td>
+                <td></td>
+                <td><input type="checkbox" id="enabled-${target.id}" ${target.enabled ? "checked" : ""}></td>
+                <td><input type="checkbox" id="done-${target.id}" ${target.on_download_done ? "checked" : ""}></td>
+                <td><input type="checkbox" id="fail-${target.id}" ${target.on_download_fail ? "checked" : ""}></td>
+                <td><input type="checkbox" id="new-${target.id}" ${target.on_new_video ? "checked" : ""}></td>
+                <td>
+                    <button onclick="saveTarget(${target.id})">Save</button>
+                    <button onclick="testTarget(${target.id})">Test</button>
+                    <button onclick="deleteTarget(${target.id})">Delete</button>
+                </td>
+            `;
+            tr.children[0].textContent = target.name;
+            tr.children[1].textContent = target.kind;
+            tr.children[2].textContent = target.url;
+            list.appendChild(tr);
+        });
+    } catch (error) {
+        console.error('Error fetching notification targets:', error);
+    }
+}
+
+async function addTarget(event) {
+    event.preventDefault();
+    const body = {
+        name: document.getElementById('target-name').value,
+        kind: document.getElementById('target-kind').value,
+        url: document.getElementById('target-url').value,
+        token: document.getElementById('target-token').value,
+        enabled: true,
+        on_download_done: true,
+        on_download_fail: true,
+        on_new_video: false,
+    };
+
+    const response = await fetch('{base}/api/v1/notifications', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (!response.ok) {
+        alert(`Failed to add notification target: ${await apiError(response)}`);
+    }
+    await fetchTargets();
+}
+
+async function saveTarget(id) {
+    const target = targets.find(t => t.id === id);
+    const body = {
+        name: target.name,
+        kind: target.kind,
+        enabled: document.getElementById(`enabled-${id}`).checked,
+        on_download_done: document.getElementById(`done-${id}`).checked,
+        on_download_fail: document.getElementById(`fail-${id}`).checked,
+        on_new_video: document.getElementById(`new-${id}`).checked,
+    };
+
+    const response = await fetch(`{base}/api/v1/notifications/${id}`, {
+        method: 'PUT',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (!response.ok) {
+        alert(`Failed to save notification target: ${await apiError(response)}`);
+    }
+    await fetchTargets();
+}
+
+async function testTarget(id) {
+    const response = await fetch(`{base}/api/v1/notifications/${id}/test`, { method: 'POST' });
+    if (response.ok) {
+        alert("Test notification sent.");
+    } else {
+        alert(`Test notification failed: ${await apiError(response)}`);
+    }
+}
+
+async function deleteTarget(id) {
+    if (!confirm("Delete this notification target?")) return;
+    const response = await fetch(`{base}/api/v1/notifications/${id}`, { method: 'DELETE' });
+    if (!response.ok) {
+        alert(`Failed to delete notification target: ${await apiError(response)}`);
+    }
+    await fetchTargets();
+}
+
+fetchTargets(); // Initial fetch
+</script>
+"#;
@@ -0,0 +1,224 @@
+//! Task queue page: what is downloading, waiting or failed
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::User;
+
+#[get("/queue")]
+pub async fn get_queue_page(_user: User) -> (ContentType, String) {
+    let page = render_page("", HTML_QUEUE);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_QUEUE: &str = r#"
+<div class="section">
+    <h1>Queue</h1>
+    <form onsubmit="event.preventDefault(); fetchQueue(0);">
+        <label for="queue-state">Show:</label>
+        <select id="queue-state" onchange="fetchQueue(0)">
+            <option value="">All</option>
+            <option value="WAIT">Waiting</option>
+            <option value="WIP">Running</option>
+            <option value="ERR">Failed</option>
+            <option value="FAIL">Cancelled or given up</option>
+            <option value="DONE">Done</option>
+        </select>
+        <label for="queue-type">Type:</label>
+        <select id="queue-type" onchange="fetchQueue(0)">
+            <option value="">All</option>
+            <option value="VIDEO-DOWNLOAD">Video download</option>
+            <option value="CHANNEL-ADD">Channel add</option>
+            <option value="CHANNEL-FETCH">Channel fetch</option>
+        </select>
+    </form>
+    <form class="admin-only" onsubmit="event.preventDefault();">
+        <label>Selected:</label>
+        <button type="button" onclick="bulkAction('retry')">Retry</button>
+        <button type="button" onclick="bulkAction('cancel')">Cancel</button>
+        <button type="button" onclick="bulkAction('delete')">Delete</button>
+    </form>
+    <p id="queue-status">The list refreshes when tasks change.</p>
+</div>
+
+<div class="section">
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th class="admin-only"><input type="checkbox" id="queue-select-all" onchange="selectAll(this.checked)"></th>
+                <th>ID</th>
+                <th>Type</th>
+                <th>Target</th>
+                <th>State</th>
+                <th>Retries</th>
+                <th>Created</th>
+                <th>Updated</th>
+                <th>Error</th>
+                <th class="admin-only"></th>
+            </tr>
+        </thead>
+        <tbody id="queue-list">
+            <!-- Tasks will be populated here by JavaScript -->
+        </tbody>
+    </table>
+    <div class="pager" id="queue-pager"></div>
+</div>
+
+<script>
+// Which actions each task state allows, same rules as the API
+const ACTIONS = {
+    retry: state => state === 'ERR' || state === 'FAIL',
+    cancel: state => state === 'WAIT' || state === 'ERR',
+    delete: state => state !== 'WIP',
+};
+const DONE = { retry: 'retried', cancel: 'cancelled', delete: 'deleted' };
+
+let queueOffset = 0;
+let tasks = [];
+const selected = new Set();
+// Latest download progress by task ID
+const progress = {};
+
+// What the task works on: URL of a video or channel, or the channel of CHANNEL-FETCH
+function taskTarget(task) {
+    try {
+        const data = JSON.parse(task.task_data);
+        if (data.url) return { text: data.url, href: data.url };
+        if (data.channel_id) return { text: `${data.domain}/channel/${data.channel_id}` };
+    } catch {
+        // Shown as is below
+    }
+    return { text: task.task_data };
+}
+
+// Database timestamps are UTC
+function formatTime(timestamp) {
+    return new Date(Date.parse(timestamp.replace(' ', 'T') + 'Z')).toLocaleString();
+}
+
+async function fetchQueue(offset = queueOffset) {
+    const params = new URLSearchParams({ sort: 'id', order: 'desc', offset: offset, limit: 50 });
+    for (const [name, id] of [['task_state', 'queue-state'], ['task_type', 'queue-type']]) {
+        const value = document.getElementById(id).value;
+        if (value) params.set(name, value);
+    }
+
+    try {
+        const response = await fetch(`{base}/api/v1/tasks?${params}`);
+        if (!response.ok) throw new Error(await apiError(response));
+
+        const page = await response.json();
+        queueOffset = page.offset;
+        tasks = page.items;
+        renderPager('queue-pager', page, fetchQueue);
+        renderQueue();
+    } catch (error) {
+        console.error('Error fetching tasks:', error);
+    }
+}
+
+function renderQueue() {
+    const list = document.getElementById('queue-list');
+    list.innerHTML = ''; // Clear the existing list
+
+    // Forget selected tasks that are not shown anymore
+    const shown = new Set(tasks.map(task => task.id));
+    selected.forEach(id => shown.has(id) || selected.delete(id));
+    document.getElementById('queue-select-all').checked =
+        tasks.length > 0 && selected.size === tasks.length;
+
+    tasks.forEach(task => {
+        const tr = document.createElement('tr');
+        tr.innerHTML = `
+            <td class="admin-only"><input type="checkbox"></td>
+            <td>${task.id}</td>
+            <td>${task.task_type}</td>
+            <td><a target="_blank"></a></td>
+            <td></td>
+            <td>${task.retry_count}</td>
+            <td>${formatTime(task.created_at)}</td>
+            <td>${formatTime(task.updated_at)}</td>
+            <td></td>
+            <td class="admin-only"></td>
+        `;
+        const checkbox = tr.querySelector('input');
+        checkbox.checked = selected.has(task.id);
+        checkbox.onchange = () => checkbox.checked ? selected.add(task.id) : selected.delete(task.id);
+
+        const target = taskTarget(task);
+        const link = tr.querySelector('a');
+        link.textContent = target.text;
+        if (target.href && /^https?:/.test(target.href)) link.href = target.href;
+
+        const state = tr.children[4];
+        state.id = `state-${task.id}`;
+        state.textContent = progress[task.id] !== undefined && task.task_state === 'WIP'
+            ? `WIP ${progress[task.id]}%` : task.task_state;
+        tr.children[8].textContent = task.last_error || '';
+
+        const actions = tr.children[9];
+        for (const action of Object.keys(ACTIONS)) {
+            if (!ACTIONS[action](task.task_state)) continue;
+            const button = document.createElement('button');
+            button.textContent = action[0].toUpperCase() + action.slice(1);
+            button.onclick = () => runActions(action, [task.id]);
+            actions.appendChild(button);
+        }
+        list.appendChild(tr);
+    });
+    applyRole();
+}
+
+function selectAll(checked) {
+    tasks.forEach(task => checked ? selected.add(task.id) : selected.delete(task.id));
+    renderQueue();
+}
+
+// Selected tasks the action applies to, others are skipped
+async function bulkAction(action) {
+    const ids = tasks
+        .filter(task => selected.has(task.id) && ACTIONS[action](task.task_state))
+        .map(task => task.id);
+    if (!ids.length) {
+        alert(`None of the selected tasks can be ${DONE[action]}.`);
+        return;
+    }
+    if (action === 'delete' && !confirm(`Delete ${ids.length} tasks?`)) return;
+    await runActions(action, ids);
+}
+
+async function runActions(action, ids) {
+    const requests = ids.map(id => action === 'delete'
+        ? fetch(`{base}/api/v1/tasks/${id}`, { method: 'DELETE' })
+        : fetch(`{base}/api/v1/tasks/${id}/${action}`, { method: 'POST' }));
+
+    const errors = [];
+    for (const [i, response] of (await Promise.all(requests)).entries()) {
+        if (!response.ok) errors.push(`Task ${ids[i]}: ${await apiError(response)}`);
+    }
+    if (errors.length) alert(`Failed to ${action} tasks:\n${errors.join('\n')}`);
+    await fetchQueue();
+}
+
+// Refresh once after a burst of task events
+let refreshTimer = null;
+subscribeEvents(event => {
+    switch (event.type) {
+        case "task_created":
+        case "task_state":
+            clearTimeout(refreshTimer);
+            refreshTimer = setTimeout(() => fetchQueue(), 500);
+            break;
+        case "progress":
+            progress[event.task_id] = event.percent.toFixed(1);
+            const state = document.getElementById(`state-${event.task_id}`);
+            if (state) state.textContent = `WIP ${progress[event.task_id]}%`;
+            break;
+    }
+});
+
+fetchQueue(0); // Initial fetch
+</script>
+"#;
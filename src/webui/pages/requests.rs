@@ -0,0 +1,100 @@
+//! Video requests page, admins approve or deny requests here
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::User;
+
+#[get("/requests")]
+pub async fn get_requests_page(_user: User) -> (ContentType, String) {
+    let page = render_page("", HTML_REQUESTS);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_REQUESTS: &str = r#"
+<div class="section">
+    <h1>Requests</h1>
+    <form onsubmit="event.preventDefault(); fetchRequests();">
+        <label for="request-state">Show:</label>
+        <select id="request-state" onchange="fetchRequests()">
+            <option value="PENDING">Pending approval</option>
+            <option value="APPROVED">Approved</option>
+            <option value="DENIED">Denied</option>
+            <option value="">All</option>
+        </select>
+    </form>
+    <p>Requests from admins are approved right away. Other requests wait here for an admin.</p>
+</div>
+
+<div class="section">
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>URL</th>
+                <th>Requested by</th>
+                <th>Requested</th>
+                <th>State</th>
+                <th>Decided by</th>
+                <th>Task</th>
+                <th></th>
+            </tr>
+        </thead>
+        <tbody id="requests-list">
+            <!-- Requests will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+async function fetchRequests() {
+    try {
+        const state = document.getElementById('request-state').value;
+        const response = await fetch(state ? `{base}/api/v1/requests?state=${state}` : '{base}/api/v1/requests');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        const requests = await response.json();
+        const list = document.getElementById('requests-list');
+        list.innerHTML = ''; // Clear the existing list
+
+        requests.forEach(request => {
+            const tr = document.createElement('tr');
+            const actions = request.state === "PENDING" ? `
+                <button class="admin-only" onclick="decide(${request.id}, 'approve')">Approve</button>
+                <button class="admin-only" onclick="decide(${request.id}, 'deny')">Deny</button>` : "";
+            tr.innerHTML = `
+                <td><a target="_blank"></a></td>
+                <td></td>
+                <td></td>
+                <td></td>
+                <td></td>
+                <td>${request.task_id || ""}</td>
+                <td>${actions}</td>
+            `;
+            const link = tr.querySelector('a');
+            link.textContent = request.url;
+            if (/^https?:/.test(request.url)) link.href = request.url;
+            tr.children[1].textContent = request.username || "removed user";
+            tr.children[2].textContent = request.created_at;
+            tr.children[3].textContent = request.state;
+            tr.children[4].textContent = request.decided_by || "";
+            list.appendChild(tr);
+        });
+        applyRole();
+    } catch (error) {
+        console.error('Error fetching requests:', error);
+    }
+}
+
+async function decide(id, decision) {
+    const response = await fetch(`{base}/api/v1/requests/${id}/${decision}`, { method: 'POST' });
+    if (!response.ok) {
+        alert(`Failed to ${decision} request: ${await apiError(response)}`);
+    }
+    await fetchRequests();
+}
+
+fetchRequests(); // Initial fetch
+</script>
+"#;
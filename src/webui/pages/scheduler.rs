@@ -0,0 +1,138 @@
+//! Scheduler page, persistent background tasks
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::Admin;
+
+#[get("/scheduler")]
+pub async fn get_scheduler_page(_admin: Admin) -> (ContentType, String) {
+    let page = render_page("", HTML_SCHEDULER);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_SCHEDULER: &str = r#"
+<div class="section">
+    <h1>Scheduler</h1>
+    <p>Persistent background tasks. Cron expression overrides the interval when set. Times are UTC.</p>
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>Task</th>
+                <th>Enabled</th>
+                <th>Interval (s)</th>
+                <th>Cron</th>
+                <th>Last run</th>
+                <th>Next run</th>
+                <th>Duration</th>
+                <th>Outcome</th>
+                <th></th>
+            </tr>
+        </thead>
+        <tbody id="scheduler-list">
+            <!-- Persistent tasks will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+// Rows by task ID. Built once so the poll does not overwrite what is being edited.
+const rows = {};
+
+function createRow(task) {
+    const tr = document.createElement('tr');
+    tr.innerHTML = `
+        <td></td>
+        <td><input type="checkbox" id="enabled-${task.id}"></td>
+        <td><input type="number" id="delay-${task.id}" min="60"></td>
+        <td><input type="text" id="cron-${task.id}" placeholder="e.g. 0 3 * * *"></td>
+        <td></td>
+        <td></td>
+        <td></td>
+        <td></td>
+        <td>
+            <button onclick="saveTask(${task.id})">Save</button>
+            <button onclick="runTask(${task.id})">Run now</button>
+        </td>
+    `;
+    tr.children[0].textContent = task.task_name;
+    document.getElementById('scheduler-list').appendChild(tr);
+    rows[task.id] = tr;
+    resetInputs(task);
+}
+
+// Settings from the server, done for new rows and after saving
+function resetInputs(task) {
+    document.getElementById(`enabled-${task.id}`).checked = task.enabled;
+    document.getElementById(`delay-${task.id}`).value = task.delay_sec;
+    document.getElementById(`cron-${task.id}`).value = task.cron || "";
+}
+
+// Read-only cells, updated on every poll
+function updateRow(task) {
+    const outcome = task.last_result
+        ? (task.last_result === "ERR" ? `ERR: ${task.last_error || ""}` : task.last_result)
+        : "-";
+    const duration = task.last_duration_ms !== null ? `${(task.last_duration_ms / 1000).toFixed(1)}s` : "-";
+
+    const cells = rows[task.id].children;
+    cells[4].textContent = task.last_exec;
+    cells[5].textContent = task.run_now ? "queued" : (task.next_exec || "-");
+    cells[6].textContent = duration;
+    cells[7].textContent = outcome;
+}
+
+async function fetchScheduler(savedId = null) {
+    try {
+        const response = await fetch('{base}/api/v1/scheduler');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        const tasks = await response.json();
+        tasks.forEach(task => {
+            if (!rows[task.id]) {
+                createRow(task);
+            } else if (task.id === savedId) {
+                resetInputs(task);
+            }
+            updateRow(task);
+        });
+    } catch (error) {
+        console.error('Error fetching scheduler:', error);
+    }
+}
+
+async function saveTask(id) {
+    const body = {
+        enabled: document.getElementById(`enabled-${id}`).checked,
+        delay_sec: parseInt(document.getElementById(`delay-${id}`).value),
+        cron: document.getElementById(`cron-${id}`).value,
+    };
+
+    const response = await fetch(`{base}/api/v1/scheduler/${id}`, {
+        method: 'PATCH',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (!response.ok) {
+        alert(`Failed to save: ${await apiError(response)}`);
+        return;
+    }
+    await fetchScheduler(id);
+}
+
+async function runTask(id) {
+    const response = await fetch(`{base}/api/v1/scheduler/${id}/run`, { method: 'POST' });
+    if (!response.ok) {
+        alert(`Failed to queue the task: ${await apiError(response)}`);
+    }
+    await fetchScheduler();
+}
+
+// Poll the API every 5 seconds
+setInterval(fetchScheduler, 5000);
+fetchScheduler(); // Initial fetch
+</script>
+"#;
@@ -0,0 +1,127 @@
+//! Video search page, opened from the search box in the top bar
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::User;
+
+#[get("/search")]
+pub async fn get_search_page(_user: User) -> (ContentType, String) {
+    let page = render_page("", HTML_SEARCH);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_SEARCH: &str = r#"
+<div class="section">
+    <h1>Search</h1>
+    <form id="search-form" onsubmit="event.preventDefault(); searchVideos();">
+        <input type="text" id="search-query" placeholder="Words in title, description or tags" />
+        <label for="search-channel">Channel:</label>
+        <select id="search-channel">
+            <option value="">All</option>
+        </select>
+        <label for="search-from">Released from:</label>
+        <input type="date" id="search-from" />
+        <label for="search-to">to:</label>
+        <input type="date" id="search-to" />
+        <label for="search-downloaded">Show:</label>
+        <select id="search-downloaded">
+            <option value="">All</option>
+            <option value="true">Downloaded</option>
+            <option value="false">Not downloaded</option>
+        </select>
+        <button type="submit">Search</button>
+    </form>
+    <p id="search-status"></p>
+</div>
+
+<div class="section">
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>Title</th>
+                <th>Channel</th>
+                <th>Released</th>
+                <th>Downloaded</th>
+            </tr>
+        </thead>
+        <tbody id="search-results">
+            <!-- Results will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+async function fetchChannels() {
+    try {
+        const response = await fetch('{base}/api/v1/channels?sort=channel_name&limit=1000');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        const select = document.getElementById('search-channel');
+        (await response.json()).items.forEach(channel => {
+            const option = document.createElement('option');
+            option.value = channel.id;
+            option.textContent = channel.channel_name;
+            select.appendChild(option);
+        });
+    } catch (error) {
+        console.error('Error fetching channels:', error);
+    }
+}
+
+async function searchVideos() {
+    const status = document.getElementById('search-status');
+    const list = document.getElementById('search-results');
+    const q = document.getElementById('search-query').value.trim();
+    if (!q) return;
+
+    const params = new URLSearchParams({ q: q });
+    for (const [name, id] of [['channel', 'search-channel'], ['from', 'search-from'],
+                              ['to', 'search-to'], ['downloaded', 'search-downloaded']]) {
+        const value = document.getElementById(id).value;
+        if (value) params.set(name, value);
+    }
+    history.replaceState(null, '', `{base}/search?q=${encodeURIComponent(q)}`);
+
+    try {
+        const response = await fetch(`{base}/api/v1/search?${params}`);
+        if (!response.ok) {
+            status.textContent = await apiError(response);
+            return;
+        }
+
+        const results = await response.json();
+        status.textContent = results.length ? '' : 'No matching videos.';
+        list.innerHTML = ''; // Clear the existing list
+
+        results.forEach(video => {
+            const tr = document.createElement('tr');
+            tr.innerHTML = `
+                <td><a></a></td>
+                <td><a></a></td>
+                <td>${video.release_date || video.release_date_estimate || ""}</td>
+                <td>${video.is_downloaded ? "Yes" : "No"}</td>
+            `;
+            const title = tr.querySelector('td:nth-child(1) a');
+            title.href = `{base}/videos/${video.domain}/${video.video_id}`;
+            title.textContent = video.name;
+            const channel = tr.querySelector('td:nth-child(2) a');
+            if (video.channel_name_normalized) {
+                channel.href = `{base}/channels/${video.domain}/${video.channel_name_normalized}`;
+                channel.textContent = video.channel_name;
+            }
+            list.appendChild(tr);
+        });
+    } catch (error) {
+        console.error('Error searching videos:', error);
+    }
+}
+
+document.getElementById('search-query').value =
+    new URLSearchParams(location.search).get('q') || '';
+fetchChannels();
+searchVideos(); // Initial search from the top bar
+</script>
+"#;
@@ -0,0 +1,140 @@
+//! Users page
+
+use rocket::get;
+use rocket::http::ContentType;
+
+use super::render_page;
+use crate::webui::auth::Admin;
+
+#[get("/users")]
+pub async fn get_users_page(_admin: Admin) -> (ContentType, String) {
+    let page = render_page("", HTML_USERS);
+
+    (ContentType::HTML, page)
+}
+
+const HTML_USERS: &str = r#"
+<div class="section">
+    <h1>Add User:</h1>
+    <form onsubmit="addUser(event)">
+        <label for="user-username">Username:</label>
+        <input type="text" id="user-username" required>
+        <label for="user-password">Password:</label>
+        <input type="password" id="user-password" required>
+        <label for="user-role">Role:</label>
+        <select id="user-role">
+            <option value="REQUESTER">Requester</option>
+            <option value="ADMIN">Admin</option>
+        </select>
+        <button type="submit">Add</button>
+    </form>
+    <p>Requesters can browse channels and request videos. Their requests wait for admin approval.</p>
+</div>
+
+<div class="section">
+    <h2>Users</h2>
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>Username</th>
+                <th>Role</th>
+                <th>Enabled</th>
+                <th>New password</th>
+                <th>Created</th>
+                <th></th>
+            </tr>
+        </thead>
+        <tbody id="users-list">
+            <!-- Users will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+async function fetchUsers() {
+    try {
+        const response = await fetch('{base}/api/v1/users');
+        if (!response.ok) throw new Error('Network response was not ok');
+
+        const users = await response.json();
+        const list = document.getElementById('users-list');
+        list.innerHTML = ''; // Clear the existing list
+
+        users.forEach(user => {
+            const tr = document.createElement('tr');
+            const disabled = user.protected ? "disabled" : "";
+            tr.innerHTML = `
+                <td></td>
+                <td>
+                    <select id="role-${user.id}" ${disabled}>
+                        <option value="REQUESTER" ${user.role === "REQUESTER" ? "selected" : ""}>Requester</option>
+                        <option value="ADMIN" ${user.role === "ADMIN" ? "selected" : ""}>Admin</option>
+                    </select>
+                </td>
+                <td><input type="checkbox" id="enabled-${user.id}" ${user.enabled ? "checked" : ""} ${disabled}></td>
+                <td><input type="password" id="password-${user.id}" placeholder="unchanged"></td>
+                <td>${user.created_at}</td>
+                <td>
+                    <button onclick="saveUser('${user.id}')">Save</button>
+                    ${user.protected ? "" : `<button onclick="deleteUser('${user.id}')">Delete</button>`}
+                </td>
+            `;
+            tr.children[0].textContent = user.username;
+            list.appendChild(tr);
+        });
+    } catch (error) {
+        console.error('Error fetching users:', error);
+    }
+}
+
+async function addUser(event) {
+    event.preventDefault();
+    const body = {
+        username: document.getElementById('user-username').value,
+        password: document.getElementById('user-password').value,
+        role: document.getElementById('user-role').value,
+    };
+
+    const response = await fetch('{base}/api/v1/users', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (!response.ok) {
+        alert(`Failed to add user: ${await apiError(response)}`);
+    }
+    await fetchUsers();
+}
+
+async function saveUser(id) {
+    const body = {
+        role: document.getElementById(`role-${id}`).value,
+        enabled: document.getElementById(`enabled-${id}`).checked,
+        password: document.getElementById(`password-${id}`).value,
+    };
+
+    const response = await fetch(`{base}/api/v1/users/${id}`, {
+        method: 'PUT',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify(body)
+    });
+
+    if (!response.ok) {
+        alert(`Failed to save user: ${await apiError(response)}`);
+    }
+    await fetchUsers();
+}
+
+async function deleteUser(id) {
+    if (!confirm("Delete this user?")) return;
+    const response = await fetch(`{base}/api/v1/users/${id}`, { method: 'DELETE' });
+    if (!response.ok) {
+        alert(`Failed to delete user: ${await apiError(response)}`);
+    }
+    await fetchUsers();
+}
+
+fetchUsers(); // Initial fetch
+</script>
+"#;
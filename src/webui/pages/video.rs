@@ -0,0 +1,247 @@
+//! Video page: metadata, downloaded files, task history and actions
+
+use rocket::http::{ContentType, Status};
+use rocket::{get, State};
+use tracing::error;
+
+use super::render_page;
+use crate::database;
+use crate::webui::auth::User;
+use crate::DBPool;
+
+#[get("/videos/<domain>/<video_id>")]
+pub async fn get_video_page(
+    _user: User,
+    domain: &str,
+    video_id: &str,
+    db_pool: &State<DBPool>,
+) -> Result<(ContentType, String), Status> {
+    let conn = db_pool.get().expect("Failed to get DB connection");
+    match database::get_video(&conn, domain, video_id) {
+        Ok(Some(_)) => Ok((ContentType::HTML, render_page("", HTML_VIDEO))),
+        Ok(None) => Err(Status::NotFound),
+        Err(e) => {
+            error!("Failed to get video: {:#}", e);
+            Err(Status::InternalServerError)
+        }
+    }
+}
+
+const HTML_VIDEO: &str = r#"
+<div class="section video-details">
+    <h1 id="video-name"></h1>
+    <p><a id="video-channel"></a></p>
+    <img id="video-thumbnail" alt="Thumbnail" hidden>
+    <p id="video-released"></p>
+    <p id="video-state"></p>
+    <p>
+        <a id="video-open" target="_blank">Open on site</a>
+        <button id="video-request" onclick="requestVideo()" hidden>Request</button>
+        <button id="video-download" class="admin-only" onclick="downloadAgain()">Download again</button>
+        <button id="video-delete" class="admin-only" onclick="deleteFiles()">Delete files</button>
+        <button id="video-watched" onclick="toggleWatched()"></button>
+    </p>
+    <p id="video-tags"></p>
+    <p id="video-description" class="description"></p>
+</div>
+
+<div class="section">
+    <h2>Files</h2>
+    <table class="data-table">
+        <tbody id="video-files">
+            <!-- Files will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<div class="section">
+    <h2>Tasks</h2>
+    <table class="data-table">
+        <thead>
+            <tr>
+                <th>ID</th>
+                <th>URL</th>
+                <th>State</th>
+                <th>Retries</th>
+                <th>Created</th>
+                <th>Updated</th>
+                <th>Error</th>
+            </tr>
+        </thead>
+        <tbody id="video-tasks">
+            <!-- Tasks will be populated here by JavaScript -->
+        </tbody>
+    </table>
+</div>
+
+<script>
+// Same path as this page, under the API
+const API = '{base}/api/v1' + location.pathname.slice('{base}'.length);
+let video = null;
+
+// Database timestamps are UTC
+function formatTime(timestamp) {
+    return new Date(Date.parse(timestamp.replace(' ', 'T') + 'Z')).toLocaleString();
+}
+
+function formatSize(bytes) {
+    const units = ['B', 'KB', 'MB', 'GB', 'TB'];
+    let unit = 0;
+    while (bytes >= 1024 && unit < units.length - 1) {
+        bytes /= 1024;
+        unit++;
+    }
+    return `${bytes.toFixed(unit ? 1 : 0)} ${units[unit]}`;
+}
+
+async function fetchVideo() {
+    const response = await fetch(API);
+    if (!response.ok) {
+        document.getElementById('video-name').textContent = await apiError(response);
+        return;
+    }
+    video = await response.json();
+
+    document.title = `${video.name} - Tubarr`;
+    document.getElementById('video-name').textContent = video.name;
+    const open = document.getElementById('video-open');
+    if (/^https?:/.test(video.url)) open.href = video.url;
+
+    const released = document.getElementById('video-released');
+    if (video.release_date) {
+        released.textContent = `Released ${video.release_date.slice(0, 10)}`;
+    } else if (video.release_date_estimate) {
+        released.textContent = `Released around ${video.release_date_estimate.slice(0, 10)} (estimated)`;
+    } else {
+        released.textContent = 'Release date is not known';
+    }
+    document.getElementById('video-state').textContent = [
+        video.is_downloaded ? 'Downloaded' : video.is_requested ? 'Requested' : 'Not requested',
+        video.is_watched ? 'watched' : 'not watched',
+    ].join(', ');
+
+    document.getElementById('video-request').hidden = video.is_requested;
+    document.getElementById('video-delete').disabled = !video.files;
+    document.getElementById('video-watched').textContent =
+        video.is_watched ? 'Mark not watched' : 'Mark watched';
+    document.getElementById('video-tags').textContent =
+        video.tags.length ? `Tags: ${video.tags.join(', ')}` : '';
+    document.getElementById('video-description').textContent = video.description || '';
+
+    const thumbnail = document.getElementById('video-thumbnail');
+    thumbnail.hidden = !video.files || !video.files.has_thumbnail;
+    if (!thumbnail.hidden) thumbnail.src = `${API}/thumbnail`;
+
+    renderFiles();
+    fetchChannel();
+    applyRole();
+}
+
+function renderFiles() {
+    const rows = [];
+    if (video.files) {
+        rows.push(['File', video.files.path]);
+        rows.push(['Size', formatSize(video.files.size)]);
+        rows.push(['Video codec', video.video_codec || 'unknown']);
+        rows.push(['Audio codec', video.audio_codec || 'unknown']);
+        rows.push(['Subtitles', video.files.subtitles.map(s => s.language || 'unknown').join(', ') || 'none']);
+    } else if (video.is_downloaded) {
+        rows.push(['File', 'Not found. It was moved, or downloaded before Tubarr kept track of files.']);
+    } else {
+        rows.push(['File', 'Not downloaded']);
+    }
+
+    const list = document.getElementById('video-files');
+    list.innerHTML = ''; // Clear the existing list
+    rows.forEach(([name, value]) => {
+        const tr = document.createElement('tr');
+        tr.innerHTML = '<th></th><td></td>';
+        tr.children[0].textContent = name;
+        tr.children[1].textContent = value;
+        list.appendChild(tr);
+    });
+}
+
+async function fetchChannel() {
+    const link = document.getElementById('video-channel');
+    if (!video.channel_id) return;
+    const response = await fetch(`{base}/api/v1/channels/${video.channel_id}`);
+    if (!response.ok) return;
+
+    const channel = await response.json();
+    link.textContent = channel.channel_name;
+    link.href = `{base}/channels/${channel.domain}/${channel.channel_name_normalized}`;
+}
+
+async function fetchTasks() {
+    const response = await fetch(`${API}/tasks`);
+    if (!response.ok) return;
+
+    const list = document.getElementById('video-tasks');
+    list.innerHTML = ''; // Clear the existing list
+    (await response.json()).forEach(task => {
+        const tr = document.createElement('tr');
+        tr.innerHTML = `
+            <td>${task.id}</td>
+            <td></td>
+            <td>${task.task_state}</td>
+            <td>${task.retry_count}</td>
+            <td>${formatTime(task.created_at)}</td>
+            <td>${formatTime(task.updated_at)}</td>
+            <td></td>
+        `;
+        tr.children[1].textContent = JSON.parse(task.task_data).url || '';
+        tr.children[6].textContent = task.last_error || '';
+        list.appendChild(tr);
+    });
+}
+
+async function requestVideo() {
+    const response = await fetch('{base}/api/v1/videos', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({ url: video.url })
+    });
+    if (response.status === 202) {
+        alert("Video request is waiting for admin approval.");
+    } else if (!response.ok) {
+        alert("Failed to request video: " + await apiError(response));
+    }
+    await refresh();
+}
+
+async function downloadAgain() {
+    const response = await fetch(`${API}/download`, { method: 'POST' });
+    if (!response.ok) alert("Failed to queue download: " + await apiError(response));
+    await refresh();
+}
+
+async function deleteFiles() {
+    if (!confirm("Delete the video, thumbnail and subtitle files?")) return;
+    const response = await fetch(`${API}/files`, { method: 'DELETE' });
+    if (!response.ok) alert("Failed to delete files: " + await apiError(response));
+    await refresh();
+}
+
+async function toggleWatched() {
+    const response = await fetch(`${API}/watched`, { method: video.is_watched ? 'DELETE' : 'POST' });
+    if (!response.ok) alert("Failed to update video: " + await apiError(response));
+    await fetchVideo();
+}
+
+async function refresh() {
+    await Promise.all([fetchVideo(), fetchTasks()]);
+}
+
+// Follow the downloads of this video
+let refreshTimer = null;
+subscribeEvents(event => {
+    if (event.type === "task_created" || event.type === "task_state") {
+        clearTimeout(refreshTimer);
+        refreshTimer = setTimeout(refresh, 500);
+    }
+});
+
+refresh(); // Initial fetch
+</script>
+"#;
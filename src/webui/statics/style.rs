@@ -70,6 +70,58 @@ body, html {
     background-color: #3a4b66; /* Bluish hover effect for user button */
 }
 
+.search-box input[type="search"] {
+    padding: 8px;
+    width: 240px;
+    font-size: 1em;
+    border-radius: 4px;
+    border: 1px solid #444;
+    background-color: #2a2a2a;
+    color: #e0e0e0;
+}
+
+.search-box input[type="search"]:focus {
+    outline: none;
+    border-color: #8aa7ff; /* Bluish border on focus */
+}
+
+.pager {
+    display: flex;
+    align-items: center;
+    gap: 10px;
+    margin: 10px 0;
+}
+
+.user-menu {
+    display: flex;
+    align-items: center;
+    gap: 10px;
+    margin-top: 0;
+}
+
+/* Login page */
+.login {
+    max-width: 400px;
+    margin: 100px auto;
+}
+
+.login form {
+    flex-direction: column;
+    align-items: stretch;
+}
+
+/* Settings form */
+.settings {
+    flex-direction: column;
+    align-items: stretch;
+    max-width: 600px;
+}
+
+.field-error {
+    color: #ff8a8a;
+    font-size: 0.9em;
+}
+
 /* Sidebar Navigation */
 .side-bar {
     grid-area: side-bar;
@@ -145,7 +197,17 @@ form label {
     font-size: 1em;
 }
 
-form input[type="text"] {
+form input[type="text"],
+form input[type="password"] {
+    padding: 8px;
+    font-size: 1em;
+    border-radius: 4px;
+    border: 1px solid #444;
+    background-color: #2a2a2a;
+    color: #e0e0e0;
+}
+
+form select {
     padding: 8px;
     font-size: 1em;
     border-radius: 4px;
@@ -235,7 +297,7 @@ form button:hover {
     text-decoration: underline;
 }
 
-.task-elapsed, .task-retries, .task-updated {
+.task-elapsed, .task-retries, .task-updated, .task-progress {
     display: block;
     margin-top: 5px;
     color: #d0d0d0;
@@ -345,6 +407,65 @@ details button:hover {
     background-color: #5a6b86;
 }
 
+/* Video Page */
+.video-details img {
+    display: block;
+    max-width: 480px;
+    width: 100%;
+    margin: 10px 0;
+    border-radius: 5px;
+}
+
+.video-details a {
+    color: #8aa7ff;
+}
+
+.video-details .description {
+    white-space: pre-wrap;
+    color: #d0d0d0;
+}
+
+/* Data Tables */
+.data-table {
+    width: 100%;
+    border-collapse: collapse;
+    margin-top: 10px;
+    background-color: #2a2f3b;
+}
+
+.data-table th, .data-table td {
+    padding: 8px;
+    border: 1px solid #333;
+    text-align: left;
+    font-size: 0.9em;
+}
+
+.data-table th {
+    color: #b0c7ff;
+}
+
+.data-table input[type="text"], .data-table input[type="number"] {
+    padding: 4px;
+    border-radius: 4px;
+    border: 1px solid #444;
+    background-color: #2a2a2a;
+    color: #e0e0e0;
+}
+
+.data-table button {
+    padding: 4px 10px;
+    font-size: 0.9em;
+    background-color: #3a4b66;
+    color: #e0e0e0;
+    border: none;
+    border-radius: 4px;
+    cursor: pointer;
+}
+
+.data-table button:hover {
+    background-color: #4a5b76;
+}
+
 /* Scrollbar Styling (optional, for dark theme consistency) */
 details::-webkit-scrollbar, .videos-list::-webkit-scrollbar {
     width: 8px;